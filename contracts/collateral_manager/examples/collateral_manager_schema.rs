@@ -3,6 +3,7 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
+use steadifi::collateral_manager::hook::PositionChangedExecuteMsg;
 use steadifi::collateral_manager::msg::{BalanceResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
 
 fn main() {
@@ -15,4 +16,5 @@ fn main() {
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(BalanceResponse), &out_dir);
+    export_schema(&schema_for!(PositionChangedExecuteMsg), &out_dir);
 }
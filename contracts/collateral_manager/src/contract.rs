@@ -1,18 +1,38 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env,
-    MessageInfo, Response, StdError, StdResult, Uint128,
+    coin, from_binary, to_binary, Addr, Binary, ContractResult, Deps, DepsMut, Env, MessageInfo,
+    Order, Reply, Response, StdError, StdResult, Uint128, WasmMsg,
 };
-use cw0::maybe_addr;
-use cw20::Cw20ReceiveMsg;
+use cw0::{maybe_addr, Duration, NativeBalance};
+use cw20::{Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
+use cw_controllers::ClaimsResponse;
 
-use crate::error::ContractError;
-use crate::helper::can_withdraw;
-use crate::state::{ADMIN, BORROW, COLLATERAL, SUPPORTED_ASSETS};
+use crate::error::{ContractError, HookError};
+use crate::helper::{
+    assert_healthy, burn_future_msgs, decrease_total_borrowed, decrease_total_held, future_state,
+    increase_future_supply, increase_total_borrowed, increase_total_held, position_changed_hooks,
+    transfer_asset_msg, transfer_from_asset_msg, HOOK_REPLY_ID, MAX_HOOKS,
+};
+use crate::state::{
+    Config, ADMIN, BORROW, CLAIMS, COLLATERAL, CONFIG, FUTURE_FACTORY, FUTURE_SUPPLY, HOOKS,
+    PRICE_MAX_AGE, SETTLEMENT_POOLS, SETTLEMENT_PRICES, SUPPORTED_ASSETS, TOTAL_BORROWED,
+    TOTAL_HELD, WITHDRAW_COOLDOWN,
+};
+use crate::valuation::{
+    convert_decimals, get_checked_oracle_price, get_checked_oracle_prices, implied_rate, to_amount,
+    to_value, value_position, PriceFreshness,
+};
+use mint_authority::msg::ExecuteMsg as MintAuthorityExecuteMsg;
 use steadifi::asset::{AssetInfo, AssetInfoUnvalidated, NormalAssetInfo};
+use steadifi::collateral_manager::hook::PositionAction;
 use steadifi::collateral_manager::msg::{
-    BalanceResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg,
+    BalanceResponse, Cw20HookMsg, ExecuteMsg, FutureState, FutureStateResponse, InstantiateMsg,
+    PositionOp, QueryMsg, SettlementFixing, TermStructurePoint, TermStructureResponse,
+};
+use steadifi::mars_protocol_math::Decimal;
+use steadifi::oracle_manager::{
+    get_oracle_price_history_config, get_oracle_quote_asset, get_oracle_twap_price,
 };
 
 //TODO make CW2 compliant
@@ -24,9 +44,36 @@ pub fn instantiate(
     mut deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    _msg: InstantiateMsg,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     ADMIN.set(deps.branch(), Some(info.sender))?;
+    if msg.close_factor.is_zero()
+        || msg.close_factor > Decimal::one()
+        || msg.liquidation_bonus >= Decimal::one()
+    {
+        return Err(ContractError::InvalidLiquidationParams {});
+    }
+    let oracle_manager_addr = deps.api.addr_validate(&msg.oracle_manager_addr)?;
+    // Positions are valued with the prices of the oracle manager, so they have to share a quote asset
+    let oracle_quote_asset = get_oracle_quote_asset(deps.querier, &oracle_manager_addr)?;
+    if msg.quote_asset != oracle_quote_asset {
+        return Err(ContractError::QuoteAssetMismatch {
+            quote_asset: msg.quote_asset,
+            oracle_quote_asset,
+        });
+    }
+    let config = Config {
+        max_ltv: msg.max_ltv,
+        oracle_manager_addr,
+        quote_asset: msg.quote_asset,
+        settlement_window: msg.settlement_window,
+        finalization_delay: msg.finalization_delay,
+        fixing_twap_window: msg.fixing_twap_window,
+        fixing_period: msg.fixing_period,
+        liquidation_bonus: msg.liquidation_bonus,
+        close_factor: msg.close_factor,
+    };
+    CONFIG.save(deps.storage, &config)?;
     Ok(Response::default())
 }
 
@@ -41,15 +88,47 @@ pub fn execute(
 ) -> Result<Response, ContractError> {
     match msg {
         // Handling of native tokens
-        ExecuteMsg::NativeDeposit {} => execute_native_deposit(deps, info),
-        ExecuteMsg::NativeSettle { .. } => Ok(Response::default()),
+        ExecuteMsg::NativeDeposit {} => execute_native_deposit(deps, env, info),
+        ExecuteMsg::NativeSettle { asset_name } => {
+            execute_native_settle(deps, env, info, asset_name)
+        }
         ExecuteMsg::NativeWithdraw { coin_denom, amount } => {
-            execute_native_withdraw(deps, info, coin_denom, amount)
+            execute_native_withdraw(deps, env, info, coin_denom, amount)
         }
-        ExecuteMsg::NativeLiquidate { .. } => Ok(Response::default()),
 
         // Handling of CW20 tokens
         ExecuteMsg::Receive(msg) => execute_receive_cw20(deps, env, info, msg),
+        ExecuteMsg::Cw20Withdraw { asset_name, amount } => {
+            execute_cw20_withdraw(deps, env, info, asset_name, amount)
+        }
+
+        // Handling of borrows of future assets
+        ExecuteMsg::Borrow { asset_name, amount } => {
+            execute_borrow(deps, env, info, asset_name, amount)
+        }
+
+        // Handling of several operations on a position at once
+        ExecuteMsg::Batch { ops } => execute_batch(deps, env, info, ops),
+
+        // Handling of debt moving from an expiring future into the next series
+        ExecuteMsg::Roll {
+            from_asset,
+            to_asset,
+            amount,
+        } => execute_roll(deps, env, info, from_asset, to_asset, amount),
+
+        // Handling of withdrawals waiting for their cooldown
+        ExecuteMsg::ClaimWithdrawals { asset_name } => {
+            execute_claim_withdrawals(deps, env, info, asset_name)
+        }
+
+        // Handling of expired futures
+        ExecuteMsg::FixSettlementPrice { asset_name } => {
+            execute_fix_settlement_price(deps, env, asset_name)
+        }
+        ExecuteMsg::SetSettlementPrice { asset_name, price } => {
+            execute_set_settlement_price(deps, env, info, asset_name, price)
+        }
 
         // Handling of supported assets
         ExecuteMsg::AddSupportedAsset {
@@ -59,11 +138,73 @@ pub fn execute(
         ExecuteMsg::RemoveSupportedAsset { asset_name } => {
             execute_remove_supported_asset(deps, info, asset_name)
         }
+        ExecuteMsg::SetWithdrawCooldown {
+            asset_name,
+            cooldown,
+        } => execute_set_withdraw_cooldown(deps, info, asset_name, cooldown),
+        ExecuteMsg::SetPriceMaxAge {
+            asset_name,
+            max_age,
+        } => execute_set_price_max_age(deps, info, asset_name, max_age),
+
+        // Handling of contracts notified of position changes
+        ExecuteMsg::AddHook { addr } => execute_add_hook(deps, info, addr),
+        ExecuteMsg::RemoveHook { addr } => execute_remove_hook(deps, info, addr),
 
+        ExecuteMsg::SetFutureFactory { address } => execute_set_future_factory(deps, info, address),
         ExecuteMsg::UpdateAdmin { new_admin } => execute_update_admin(deps, info, new_admin),
     }
 }
 
+fn execute_add_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    let addr = deps.api.addr_validate(&addr)?;
+    // Admin is checked before the maximum, with the error the hooks controller would give
+    ADMIN
+        .assert_admin(deps.as_ref(), &info.sender)
+        .map_err(HookError::from)?;
+    if HOOKS.query_hooks(deps.as_ref())?.hooks.len() >= MAX_HOOKS {
+        return Err(ContractError::TooManyHooks { max: MAX_HOOKS });
+    }
+    Ok(HOOKS.execute_add_hook(&ADMIN, deps, info, addr)?)
+}
+
+fn execute_remove_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    let addr = deps.api.addr_validate(&addr)?;
+    Ok(HOOKS.execute_remove_hook(&ADMIN, deps, info, addr)?)
+}
+
+fn execute_set_future_factory(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: Option<String>,
+) -> Result<Response, ContractError> {
+    // Only contract admin can change the future factory
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let address_attribute = match address {
+        Some(address) => {
+            let address = deps.api.addr_validate(&address)?;
+            FUTURE_FACTORY.save(deps.storage, &address)?;
+            address.to_string()
+        }
+        None => {
+            FUTURE_FACTORY.remove(deps.storage);
+            "none".to_string()
+        }
+    };
+    Ok(Response::new()
+        .add_attribute("action", "set_future_factory")
+        .add_attribute("address", address_attribute))
+}
+
 fn execute_update_admin(
     deps: DepsMut,
     info: MessageInfo,
@@ -75,84 +216,324 @@ fn execute_update_admin(
 }
 
 /// Native Deposits
-fn execute_native_deposit(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+fn execute_native_deposit(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut response = Response::new();
     for coin in info.funds.into_iter() {
         // Check to see if token is on whitelist
-        let asset_info = SUPPORTED_ASSETS.may_load(deps.storage, &coin.denom)?;
-        match asset_info {
-            Some(..) => COLLATERAL.update(
-                deps.storage,
-                (&info.sender, &coin.denom),
-                |balance: Option<Uint128>| -> StdResult<_> {
-                    Ok(balance.unwrap_or_default().checked_add(coin.amount)?)
-                },
-            )?,
-            None => {
-                return Err(ContractError::AssetNotSupported {});
-            }
-        };
+        let asset_info = load_supported_asset(&deps, &coin.denom)?;
+        if !asset_info.is_native() {
+            return Err(StdError::generic_err(format!(
+                "{} corresponds to a cw20 token",
+                coin.denom
+            ))
+            .into());
+        }
+        let deposit_response =
+            apply_deposit(deps.branch(), &env, &info.sender, coin.denom, coin.amount)?;
+        response = merge_responses(response, deposit_response);
     }
     // TODO: A more informative response
-    Ok(Response::default())
+    Ok(response)
 }
 
 ///Native Withdrawals
 fn execute_native_withdraw(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     coin_denom: String,
     withdraw_amount: Uint128,
 ) -> Result<Response, ContractError> {
-    let address = info.sender;
-    // Check if asset is supported
     if let Some(asset_info) = SUPPORTED_ASSETS.may_load(deps.storage, &coin_denom)? {
-        let current_amount = COLLATERAL
-            .may_load(deps.storage, (&address, &coin_denom))?
-            .unwrap_or_default();
-        // Current amount shouldn't be zero
-        if current_amount.is_zero() {
-            return Err(ContractError::AssetIsZero {});
-        }
-        // Withdraw amount should be less or equal to current amount
-        if let Err(_) = current_amount.checked_sub(withdraw_amount) {
-            return Err(ContractError::NotEnoughAsset {
-                coin_denom,
-                current_amount,
-                withdraw_amount,
-            });
+        if !asset_info.is_native() {
+            return Err(StdError::generic_err(format!(
+                "{} corresponds to a cw20 token",
+                coin_denom
+            ))
+            .into());
         }
-        // Check collateral requirements with oracle prices and corresponding rations
-        if can_withdraw(&deps, &address, asset_info, withdraw_amount)? == false {
-            return Err(ContractError::NotEnoughTotalCollateral {});
+    }
+    execute_withdraw(deps, env, info.sender, coin_denom, withdraw_amount)
+}
+
+///CW20 Withdrawals
+fn execute_cw20_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_name: String,
+    withdraw_amount: Uint128,
+) -> Result<Response, ContractError> {
+    if let Some(asset_info) = SUPPORTED_ASSETS.may_load(deps.storage, &asset_name)? {
+        if asset_info.is_native() {
+            return Err(StdError::generic_err(format!(
+                "{} corresponds to a native token",
+                asset_name
+            ))
+            .into());
         }
-    } else {
-        //Asset not supported by contract
-        return Err(ContractError::AssetNotSupported {});
     }
+    execute_withdraw(deps, env, info.sender, asset_name, withdraw_amount)
+}
 
-    let response = Response::new().add_message(CosmosMsg::Bank(BankMsg::Send {
-        to_address: address.into(),
-        amount: vec![Coin {
-            denom: coin_denom,
-            amount: withdraw_amount,
-        }],
-    }));
+fn execute_withdraw(
+    mut deps: DepsMut,
+    env: Env,
+    address: Addr,
+    asset_name: String,
+    withdraw_amount: Uint128,
+) -> Result<Response, ContractError> {
+    let response = apply_withdraw(deps.branch(), &env, &address, asset_name, withdraw_amount)?;
+    // Check collateral requirements with oracle prices and corresponding ratios
+    assert_healthy(deps.as_ref(), &env.block, &address)?;
     Ok(response)
 }
 
+/// Borrow future assets against the deposited collateral
+fn execute_borrow(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_name: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let response = apply_borrow(deps.branch(), &env, &info.sender, asset_name, amount)?;
+    assert_healthy(deps.as_ref(), &env.block, &info.sender)?;
+    Ok(response)
+}
+
+/// Applies all operations in order and checks the health of the position once at the end
+fn execute_batch(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ops: Vec<PositionOp>,
+) -> Result<Response, ContractError> {
+    let address = info.sender;
+    // Native funds sent with the batch have to be used up exactly by its deposits
+    let mut funds = NativeBalance(info.funds);
+    funds.normalize();
+    // Only withdrawals and borrows can make a position unhealthy
+    let mut check_health = false;
+
+    let mut response = Response::new().add_attribute("action", "batch");
+    for op in ops {
+        match &op {
+            PositionOp::Deposit { amount, .. }
+            | PositionOp::Withdraw { amount, .. }
+            | PositionOp::Borrow { amount, .. }
+            | PositionOp::Repay { amount, .. } => {
+                if amount.is_zero() {
+                    return Err(ContractError::InvalidZeroAmount {});
+                }
+            }
+        }
+        let op_response = match op {
+            PositionOp::Deposit { asset_name, amount } => {
+                let asset_info = load_supported_asset(&deps, &asset_name)?;
+                let mut op_response = Response::new();
+                if asset_info.is_native() {
+                    funds = (funds - coin(amount.u128(), asset_info.get_name()))
+                        .map_err(|_| ContractError::BatchFundsMismatch {})?;
+                } else {
+                    op_response = op_response.add_message(transfer_from_asset_msg(
+                        &asset_info,
+                        &address,
+                        &env.contract.address,
+                        amount,
+                    )?);
+                }
+                merge_responses(
+                    op_response,
+                    apply_deposit(deps.branch(), &env, &address, asset_name, amount)?,
+                )
+            }
+            PositionOp::Withdraw { asset_name, amount } => {
+                check_health = true;
+                apply_withdraw(deps.branch(), &env, &address, asset_name, amount)?
+            }
+            PositionOp::Borrow { asset_name, amount } => {
+                check_health = true;
+                apply_borrow(deps.branch(), &env, &address, asset_name, amount)?
+            }
+            PositionOp::Repay { asset_name, amount } => {
+                let asset_info = load_supported_asset(&deps, &asset_name)?;
+                merge_responses(
+                    Response::new()
+                        .add_message(transfer_from_asset_msg(
+                            &asset_info,
+                            &address,
+                            &env.contract.address,
+                            amount,
+                        )?)
+                        .add_messages(burn_future_msgs(
+                            deps.storage,
+                            &asset_info,
+                            &env.contract.address,
+                            amount,
+                        )?),
+                    apply_repay(deps.branch(), &address, asset_name, amount)?,
+                )
+            }
+        };
+        response = merge_responses(response, op_response);
+    }
+    if !funds.is_empty() {
+        return Err(ContractError::BatchFundsMismatch {});
+    }
+    if check_health {
+        assert_healthy(deps.as_ref(), &env.block, &address)?;
+    }
+    Ok(response)
+}
+
+/// Repays amount of the future from_asset and borrows the later series to_asset for the same value
+/// at the current oracle prices, checking the position once at the end. The from_asset tokens are
+/// pulled from the sender with TransferFrom, so the sender has to give an allowance first
+fn execute_roll(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    from_asset: String,
+    to_asset: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let address = info.sender;
+    let from_info = load_supported_asset(&deps, &from_asset)?;
+    let to_info = load_supported_asset(&deps, &to_asset)?;
+    match (&from_info, &to_info) {
+        (
+            AssetInfo::FutureAsset {
+                underlying: from_underlying,
+                expiry: from_expiry,
+                ..
+            },
+            AssetInfo::FutureAsset {
+                underlying: to_underlying,
+                expiry: to_expiry,
+                ..
+            },
+        ) => {
+            if from_underlying.get_name() != to_underlying.get_name() || to_expiry <= from_expiry {
+                return Err(ContractError::InvalidRollTarget {});
+            }
+        }
+        _ => return Err(ContractError::NotAFutureAsset {}),
+    }
+    if matches!(
+        future_state(deps.storage, &env.block, &from_info)?,
+        Some(FutureState::Expired) | Some(FutureState::Finalized)
+    ) {
+        return Err(ContractError::RollAfterExpiry {});
+    }
+
+    let prices = get_checked_oracle_prices(
+        deps.as_ref(),
+        &env.block,
+        &[from_asset.clone(), to_asset.clone()],
+        PriceFreshness::Required,
+    )?;
+    let price = |asset_name: &String| -> StdResult<_> {
+        prices.get(asset_name).copied().ok_or_else(|| {
+            StdError::generic_err(format!("No oracle price returned for {}", asset_name))
+        })
+    };
+    let rolled_value = to_value(amount, from_info.get_decimals(), price(&from_asset)?)?;
+    let rolled_amount = to_amount(rolled_value, to_info.get_decimals(), price(&to_asset)?)?;
+
+    let mut response = Response::new()
+        .add_message(transfer_from_asset_msg(
+            &from_info,
+            &address,
+            &env.contract.address,
+            amount,
+        )?)
+        .add_messages(burn_future_msgs(
+            deps.storage,
+            &from_info,
+            &env.contract.address,
+            amount,
+        )?)
+        .add_attribute("action", "roll")
+        .add_attribute("from_asset", from_asset.clone())
+        .add_attribute("to_asset", to_asset.clone())
+        .add_attribute("rolled_amount", rolled_amount);
+    response = merge_responses(
+        response,
+        apply_repay(deps.branch(), &address, from_asset, amount)?,
+    );
+    response = merge_responses(
+        response,
+        apply_borrow(deps.branch(), &env, &address, to_asset, rolled_amount)?,
+    );
+    assert_healthy(deps.as_ref(), &env.block, &address)?;
+    Ok(response)
+}
+
+/// Pays out all withdrawals of asset_name whose cooldown has passed
+fn execute_claim_withdrawals(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_name: String,
+) -> Result<Response, ContractError> {
+    let asset_info = load_supported_asset(&deps, &asset_name)?;
+    let amount =
+        CLAIMS.claim_tokens(deps.storage, (&info.sender, &asset_name), &env.block, None)?;
+    if amount.is_zero() {
+        return Err(ContractError::NothingToClaim {});
+    }
+    decrease_total_held(deps.storage, &asset_name, amount)?;
+
+    Ok(Response::new()
+        .add_message(transfer_asset_msg(&asset_info, &info.sender, amount)?)
+        .add_attribute("action", "claim_withdrawals")
+        .add_attribute("from", info.sender)
+        .add_attribute("amount", amount)
+        .add_attribute("asset_name", asset_name))
+}
+
 fn execute_receive_cw20(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     cw20_msg: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
     match from_binary(&cw20_msg.msg) {
         Ok(Cw20HookMsg::Deposit { asset_name }) => {
             let cw20_sender = deps.api.addr_validate(cw20_msg.sender.as_str())?;
-            execute_cw20_deposit(deps, cw20_sender, info.sender, cw20_msg.amount, asset_name)
+            execute_cw20_deposit(
+                deps,
+                env,
+                cw20_sender,
+                info.sender,
+                cw20_msg.amount,
+                asset_name,
+            )
+        }
+        Ok(Cw20HookMsg::Liquidate {
+            asset_name,
+            borrower,
+            collateral_asset,
+        }) => execute_liquidate(
+            deps,
+            env,
+            info,
+            cw20_msg,
+            asset_name,
+            borrower,
+            collateral_asset,
+        ),
+        Ok(Cw20HookMsg::Settle { asset_name }) => {
+            execute_settle(deps, env, info, cw20_msg, asset_name)
+        }
+        Ok(Cw20HookMsg::Redeem { asset_name }) => {
+            execute_redeem(deps, env, info, cw20_msg, asset_name)
         }
-        Ok(Cw20HookMsg::Withdraw { .. }) => Ok(Response::new()),
-        Ok(_) => Ok(Response::new()),
         Err(_) => Err(StdError::generic_err("invalid cw20 hook message").into()),
     }
 }
@@ -160,116 +541,153 @@ fn execute_receive_cw20(
 /// CW20 Deposits
 fn execute_cw20_deposit(
     deps: DepsMut,
+    env: Env,
     sender: Addr,
     cw20_contract_addr: Addr,
     amount: Uint128,
     asset_name: String,
 ) -> Result<Response, ContractError> {
-    if let Some(asset_info) = SUPPORTED_ASSETS.may_load(deps.storage, &asset_name)? {
-        match asset_info {
-            AssetInfo::NormalAsset(normal_asset_info_validated) => {
-                match normal_asset_info_validated {
-                    NormalAssetInfo::CW20Token { contract_addr, .. } => {
-                        if cw20_contract_addr != contract_addr {
-                            return Err(StdError::generic_err
-                                           (format!("Address on whitelist and sender contract address for cw20 asset {} do not match", asset_name)).into());
-                        }
-                        COLLATERAL.update(
-                            deps.storage,
-                            (&sender, &asset_name),
-                            |balance: Option<Uint128>| -> StdResult<_> {
-                                Ok(balance.unwrap_or_default().checked_add(amount)?)
-                            },
-                        )?;
-                    }
-
-                    NormalAssetInfo::NativeToken { .. } => {
-                        return Err(StdError::generic_err(format!(
-                            "{} corresponds to a native token",
-                            asset_name
-                        ))
-                        .into());
-                    }
-                }
+    let asset_info = load_supported_asset(&deps, &asset_name)?;
+    match asset_info {
+        AssetInfo::NormalAsset(NormalAssetInfo::NativeToken { .. }) => {
+            return Err(StdError::generic_err(format!(
+                "{} corresponds to a native token",
+                asset_name
+            ))
+            .into());
+        }
+        AssetInfo::NormalAsset(NormalAssetInfo::CW20Token { contract_addr, .. })
+        | AssetInfo::FutureAsset { contract_addr, .. } => {
+            if cw20_contract_addr != contract_addr {
+                return Err(StdError::generic_err(format!("Address on whitelist and sender contract address for cw20 asset {} do not match", asset_name)).into());
             }
+        }
+    }
+    apply_deposit(deps, &env, &sender, asset_name, amount)
+}
 
-            AssetInfo::FutureAsset {
-                contract_addr,
-                collateralizeable,
-                ..
-            } => {
-                if cw20_contract_addr != contract_addr {
-                    return Err(StdError::generic_err(format!("Address on whitelist and sender contract address for cw20 asset {} do not match", asset_name)).into());
-                }
-                if collateralizeable {
-                    if let Some(borrow_amount) =
-                        BORROW.may_load(deps.storage, (&sender, &asset_name))?
-                    {
-                        let excess = amount.checked_sub(borrow_amount);
-                        match excess {
-                            Ok(collateral_amount) => {
-                                //Deposit is greater equal to the current borrow
-                                BORROW.remove(deps.storage, (&sender, &asset_name));
-                                COLLATERAL.update(
-                                    deps.storage,
-                                    (&sender, &asset_name),
-                                    |balance: Option<Uint128>| -> StdResult<_> {
-                                        Ok(balance
-                                            .unwrap_or_default()
-                                            .checked_add(collateral_amount)?)
-                                    },
-                                )?;
-                            }
-                            Err(_) => {
-                                //Deposit is less than current borrow
-                                BORROW.update(
-                                    deps.storage,
-                                    (&sender, &asset_name),
-                                    |balance: Option<Uint128>| -> StdResult<_> {
-                                        Ok(balance.unwrap_or_default().checked_sub(amount)?)
-                                    },
-                                )?;
-                            }
+/// Adds amount of asset_name to the position of address. Deposits of a borrowed future asset
+/// repay the debt first, the repaid tokens are burned. The tokens have to be transferred to the
+/// contract by the caller
+fn apply_deposit(
+    deps: DepsMut,
+    env: &Env,
+    sender: &Addr,
+    asset_name: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    // Part of the amount that repays debt and part that is added as collateral
+    let mut repaid = Uint128::zero();
+    let mut deposited = Uint128::zero();
+    let asset_info = load_supported_asset(&deps, &asset_name)?;
+    match &asset_info {
+        AssetInfo::NormalAsset(..) => {
+            COLLATERAL.update(
+                deps.storage,
+                (sender, &asset_name),
+                |balance: Option<Uint128>| -> StdResult<_> {
+                    Ok(balance.unwrap_or_default().checked_add(amount)?)
+                },
+            )?;
+            deposited = amount;
+        }
+
+        AssetInfo::FutureAsset {
+            collateralizeable, ..
+        } => {
+            if *collateralizeable {
+                if let Some(borrow_amount) = BORROW.may_load(deps.storage, (sender, &asset_name))? {
+                    let excess = amount.checked_sub(borrow_amount);
+                    match excess {
+                        Ok(collateral_amount) => {
+                            //Deposit is greater equal to the current borrow
+                            BORROW.remove(deps.storage, (sender, &asset_name));
+                            COLLATERAL.update(
+                                deps.storage,
+                                (sender, &asset_name),
+                                |balance: Option<Uint128>| -> StdResult<_> {
+                                    Ok(balance
+                                        .unwrap_or_default()
+                                        .checked_add(collateral_amount)?)
+                                },
+                            )?;
+                            repaid = borrow_amount;
+                            deposited = collateral_amount;
                         }
-                    } else {
-                        //Not borrowed
-                        COLLATERAL.update(
-                            deps.storage,
-                            (&sender, &asset_name),
-                            |balance: Option<Uint128>| -> StdResult<_> {
-                                Ok(balance.unwrap_or_default().checked_sub(amount)?)
-                            },
-                        )?;
-                    }
-                } else {
-                    // Asset is not collateralizeable therefore:
-                    // Only accept deposits if it is a borrowed asset and amount deposited is less
-                    // or equal to the amount that is borrowed
-                    if let Some(borrow_amount) =
-                        BORROW.may_load(deps.storage, (&sender, &asset_name))?
-                    {
-                        if borrow_amount <= amount {
-                            return Err(StdError::generic_err("After deposit balance becomes positive and asset can not be used as collateral").into()) ;
-                        } else {
+                        Err(_) => {
+                            //Deposit is less than current borrow
                             BORROW.update(
                                 deps.storage,
-                                (&sender, &asset_name),
+                                (sender, &asset_name),
                                 |balance: Option<Uint128>| -> StdResult<_> {
                                     Ok(balance.unwrap_or_default().checked_sub(amount)?)
                                 },
                             )?;
+                            repaid = amount;
                         }
+                    }
+                } else {
+                    //Not borrowed
+                    COLLATERAL.update(
+                        deps.storage,
+                        (sender, &asset_name),
+                        |balance: Option<Uint128>| -> StdResult<_> {
+                            Ok(balance.unwrap_or_default().checked_add(amount)?)
+                        },
+                    )?;
+                    deposited = amount;
+                }
+            } else {
+                // Asset is not collateralizeable therefore:
+                // Only accept deposits if it is a borrowed asset and amount deposited is less
+                // or equal to the amount that is borrowed
+                if let Some(borrow_amount) = BORROW.may_load(deps.storage, (sender, &asset_name))? {
+                    if borrow_amount < amount {
+                        return Err(StdError::generic_err("After deposit balance becomes positive and asset can not be used as collateral").into());
+                    } else if borrow_amount == amount {
+                        BORROW.remove(deps.storage, (sender, &asset_name));
                     } else {
-                        return Err(ContractError::AssetNotCollaterlizeable {});
+                        BORROW.save(
+                            deps.storage,
+                            (sender, &asset_name),
+                            &(borrow_amount - amount),
+                        )?;
                     }
+                    repaid = amount;
+                } else {
+                    return Err(ContractError::AssetNotCollaterlizeable {});
                 }
             }
         }
-    } else {
-        return Err(ContractError::AssetNotSupported {});
+    }
+
+    let mut burn_msgs = vec![];
+    let mut hooks = vec![];
+    if !repaid.is_zero() {
+        decrease_total_borrowed(deps.storage, &asset_name, repaid)?;
+        burn_msgs = burn_future_msgs(deps.storage, &asset_info, &env.contract.address, repaid)?;
+        hooks.extend(position_changed_hooks(
+            deps.storage,
+            PositionAction::Repay,
+            sender,
+            &asset_name,
+            repaid,
+        )?);
+    }
+    if !deposited.is_zero() {
+        increase_total_held(deps.storage, &asset_name, deposited)?;
+        hooks.extend(position_changed_hooks(
+            deps.storage,
+            PositionAction::Deposit,
+            sender,
+            &asset_name,
+            deposited,
+        )?);
     }
 
     let res = Response::new()
+        .add_messages(burn_msgs)
+        .add_submessages(hooks)
         .add_attribute("action", "add  asset as collateral")
         .add_attribute("from", sender)
         .add_attribute("amount", amount)
@@ -278,16 +696,637 @@ fn execute_cw20_deposit(
     Ok(res)
 }
 
+/// Removes withdraw_amount from the collateral of address without checking the health of the
+/// position. The amount is sent right away unless the asset has a withdraw cooldown, in which case
+/// it is locked in a claim until the cooldown passes
+fn apply_withdraw(
+    deps: DepsMut,
+    env: &Env,
+    address: &Addr,
+    asset_name: String,
+    withdraw_amount: Uint128,
+) -> Result<Response, ContractError> {
+    // Check if asset is supported
+    let asset_info = load_supported_asset(&deps, &asset_name)?;
+    let current_amount = COLLATERAL
+        .may_load(deps.storage, (address, &asset_name))?
+        .unwrap_or_default();
+    // Current amount shouldn't be zero
+    if current_amount.is_zero() {
+        return Err(ContractError::AssetIsZero {});
+    }
+    // Withdraw amount should be less or equal to current amount
+    let remaining_amount = match current_amount.checked_sub(withdraw_amount) {
+        Ok(remaining_amount) => remaining_amount,
+        Err(_) => {
+            return Err(ContractError::NotEnoughAsset {
+                coin_denom: asset_name,
+                current_amount,
+                withdraw_amount,
+            })
+        }
+    };
+    if remaining_amount.is_zero() {
+        COLLATERAL.remove(deps.storage, (address, &asset_name));
+    } else {
+        COLLATERAL.save(deps.storage, (address, &asset_name), &remaining_amount)?;
+    }
+    let hooks = position_changed_hooks(
+        deps.storage,
+        PositionAction::Withdraw,
+        address,
+        &asset_name,
+        withdraw_amount,
+    )?;
+
+    let response = Response::new()
+        .add_submessages(hooks)
+        .add_attribute("action", "withdraw")
+        .add_attribute("from", address.clone())
+        .add_attribute("amount", withdraw_amount)
+        .add_attribute("asset_name", asset_name.clone());
+    match WITHDRAW_COOLDOWN.may_load(deps.storage, &asset_name)? {
+        Some(cooldown) => {
+            let release_at = cooldown.after(&env.block);
+            CLAIMS.create_claim(
+                deps.storage,
+                (address, &asset_name),
+                withdraw_amount,
+                release_at,
+            )?;
+            Ok(response.add_attribute("release_at", release_at.to_string()))
+        }
+        None => {
+            decrease_total_held(deps.storage, &asset_name, withdraw_amount)?;
+            Ok(response.add_message(transfer_asset_msg(&asset_info, address, withdraw_amount)?))
+        }
+    }
+}
+
+/// Adds amount of the future asset asset_name to the debt of address and mints it to address,
+/// without checking the health of the position
+fn apply_borrow(
+    deps: DepsMut,
+    env: &Env,
+    address: &Addr,
+    asset_name: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let asset_info = load_supported_asset(&deps, &asset_name)?;
+    let mint_authority = match &asset_info {
+        AssetInfo::FutureAsset { mint_authority, .. } => mint_authority.clone(),
+        AssetInfo::NormalAsset(..) => return Err(ContractError::AssetNotBorrowable {}),
+    };
+    // New borrows are only possible until the settlement window opens
+    if future_state(deps.storage, &env.block, &asset_info)? != Some(FutureState::Trading) {
+        return Err(ContractError::BorrowingClosed {});
+    }
+    // At most one of collateral and borrow can be non-zero for an asset
+    if !COLLATERAL
+        .may_load(deps.storage, (address, &asset_name))?
+        .unwrap_or_default()
+        .is_zero()
+    {
+        return Err(ContractError::CollateralInBorrowedAsset {});
+    }
+    BORROW.update(
+        deps.storage,
+        (address, &asset_name),
+        |balance: Option<Uint128>| -> StdResult<_> {
+            Ok(balance.unwrap_or_default().checked_add(amount)?)
+        },
+    )?;
+    increase_total_borrowed(deps.storage, &asset_name, amount)?;
+    increase_future_supply(deps.storage, &asset_name, amount)?;
+    let hooks = position_changed_hooks(
+        deps.storage,
+        PositionAction::Borrow,
+        address,
+        &asset_name,
+        amount,
+    )?;
+
+    let mint_msg = WasmMsg::Execute {
+        contract_addr: mint_authority.to_string(),
+        msg: to_binary(&MintAuthorityExecuteMsg::MintAndSend {
+            recipient: address.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    };
+    Ok(Response::new()
+        .add_message(mint_msg)
+        .add_submessages(hooks)
+        .add_attribute("action", "borrow")
+        .add_attribute("from", address.clone())
+        .add_attribute("amount", amount)
+        .add_attribute("asset_name", asset_name))
+}
+
+/// Reduces the debt of address in asset_name by amount. The tokens have to be transferred to the
+/// contract by the caller
+fn apply_repay(
+    deps: DepsMut,
+    address: &Addr,
+    asset_name: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let borrow_amount = BORROW
+        .may_load(deps.storage, (address, &asset_name))?
+        .unwrap_or_default();
+    let remaining_amount = borrow_amount
+        .checked_sub(amount)
+        .map_err(|_| ContractError::RepayExceedsDebt {})?;
+    if remaining_amount.is_zero() {
+        BORROW.remove(deps.storage, (address, &asset_name));
+    } else {
+        BORROW.save(deps.storage, (address, &asset_name), &remaining_amount)?;
+    }
+    decrease_total_borrowed(deps.storage, &asset_name, amount)?;
+    let hooks = position_changed_hooks(
+        deps.storage,
+        PositionAction::Repay,
+        address,
+        &asset_name,
+        amount,
+    )?;
+
+    Ok(Response::new()
+        .add_submessages(hooks)
+        .add_attribute("action", "repay")
+        .add_attribute("from", address.clone())
+        .add_attribute("amount", amount)
+        .add_attribute("asset_name", asset_name))
+}
+
+/// Liquidation of an unhealthy position. The liquidator repays amount of the borrowed future
+/// asset_name, at most the close factor of the debt, and receives collateral_asset worth the same
+/// plus the liquidation bonus at the prices the position was valued with
+fn execute_liquidate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+    asset_name: String,
+    borrower: String,
+    collateral_asset: String,
+) -> Result<Response, ContractError> {
+    let liquidator = deps.api.addr_validate(&cw20_msg.sender)?;
+    let borrower = deps.api.addr_validate(&borrower)?;
+    let cw20_contract_addr = info.sender;
+    let amount = cw20_msg.amount;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let debt_info = load_supported_asset(&deps, &asset_name)?;
+    match &debt_info {
+        AssetInfo::FutureAsset { contract_addr, .. } => {
+            if cw20_contract_addr != *contract_addr {
+                return Err(StdError::generic_err(format!("Address on whitelist and sender contract address for cw20 asset {} do not match", asset_name)).into());
+            }
+        }
+        AssetInfo::NormalAsset(..) => return Err(ContractError::AssetNotBorrowable {}),
+    }
+
+    // Unhealthy positions can always be liquidated, debt that is still unsettled after expiry too
+    // Liquidations seize collateral, so they need fresh prices like borrows and withdrawals
+    let valuation = value_position(
+        deps.as_ref(),
+        &env.block,
+        &borrower,
+        PriceFreshness::Required,
+    )?;
+    let expired = matches!(
+        future_state(deps.storage, &env.block, &debt_info)?,
+        Some(FutureState::Expired) | Some(FutureState::Finalized)
+    );
+    if valuation.is_healthy() && !expired {
+        return Err(ContractError::PositionHealthy {});
+    }
+    let config = CONFIG.load(deps.storage)?;
+    let borrow_amount = BORROW
+        .may_load(deps.storage, (&borrower, &asset_name))?
+        .unwrap_or_default();
+    let remaining_borrow = borrow_amount
+        .checked_sub(amount)
+        .map_err(|_| ContractError::RepayExceedsDebt {})?;
+    if !expired && amount > config.close_factor * borrow_amount {
+        return Err(ContractError::LiquidationExceedsCloseFactor {});
+    }
+
+    let debt_decimals = debt_info.get_decimals();
+    let repaid_value = to_value(amount, debt_decimals, valuation.price(&asset_name)?)?;
+    let seized_value = repaid_value.checked_mul(Decimal::one() + config.liquidation_bonus)?;
+    let collateral = valuation
+        .collateral(&collateral_asset)
+        .ok_or(ContractError::AssetIsZero {})?;
+    let seized_amount = to_amount(
+        seized_value,
+        collateral.asset_info.get_decimals(),
+        collateral.price,
+    )?;
+    let remaining_collateral = collateral
+        .amount
+        .checked_sub(seized_amount)
+        .map_err(|_| ContractError::LiquidationExceedsCollateral {})?;
+
+    if remaining_borrow.is_zero() {
+        BORROW.remove(deps.storage, (&borrower, &asset_name));
+    } else {
+        BORROW.save(deps.storage, (&borrower, &asset_name), &remaining_borrow)?;
+    }
+    decrease_total_borrowed(deps.storage, &asset_name, amount)?;
+    if remaining_collateral.is_zero() {
+        COLLATERAL.remove(deps.storage, (&borrower, &collateral_asset));
+    } else {
+        COLLATERAL.save(
+            deps.storage,
+            (&borrower, &collateral_asset),
+            &remaining_collateral,
+        )?;
+    }
+    decrease_total_held(deps.storage, &collateral_asset, seized_amount)?;
+
+    let action = PositionAction::Liquidate {
+        liquidator: liquidator.to_string(),
+    };
+    let mut hooks =
+        position_changed_hooks(deps.storage, action.clone(), &borrower, &asset_name, amount)?;
+    hooks.extend(position_changed_hooks(
+        deps.storage,
+        action,
+        &borrower,
+        &collateral_asset,
+        seized_amount,
+    )?);
+
+    // Seized collateral is sent right away, withdraw cooldowns only apply to the position owner
+    Ok(Response::new()
+        .add_messages(burn_future_msgs(
+            deps.storage,
+            &debt_info,
+            &env.contract.address,
+            amount,
+        )?)
+        .add_message(transfer_asset_msg(
+            &collateral.asset_info,
+            &liquidator,
+            seized_amount,
+        )?)
+        .add_submessages(hooks)
+        .add_attribute("action", "liquidate")
+        .add_attribute("from", liquidator)
+        .add_attribute("borrower", borrower)
+        .add_attribute("amount", amount)
+        .add_attribute("asset_name", asset_name)
+        .add_attribute("collateral_asset", collateral_asset)
+        .add_attribute("collateral_amount", seized_amount))
+}
+
+/// Settlement of debt in a future whose underlying is a cw20 token, sent along with the message
+fn execute_settle(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+    asset_name: String,
+) -> Result<Response, ContractError> {
+    let borrower = deps.api.addr_validate(&cw20_msg.sender)?;
+    let (asset_info, underlying) = load_future_and_underlying(&deps, &asset_name)?;
+    match &underlying {
+        NormalAssetInfo::CW20Token { contract_addr, .. } if *contract_addr == info.sender => {}
+        _ => {
+            return Err(StdError::generic_err(format!(
+                "Sender contract address is not the underlying of {}",
+                asset_name
+            ))
+            .into())
+        }
+    }
+    apply_settle(
+        deps,
+        &env,
+        &borrower,
+        asset_info,
+        underlying,
+        cw20_msg.amount,
+    )
+}
+
+/// Settlement of debt in a future whose underlying is a native token, sent as the only funds
+fn execute_native_settle(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_name: String,
+) -> Result<Response, ContractError> {
+    let (asset_info, underlying) = load_future_and_underlying(&deps, &asset_name)?;
+    let amount = match (&underlying, info.funds.as_slice()) {
+        (NormalAssetInfo::NativeToken { denom, .. }, [coin]) if coin.denom == *denom => coin.amount,
+        _ => {
+            return Err(StdError::generic_err(format!(
+                "Sent funds are not the underlying of {}",
+                asset_name
+            ))
+            .into())
+        }
+    };
+    apply_settle(deps, &env, &info.sender, asset_info, underlying, amount)
+}
+
+/// Returns the future asset_name along with its validated underlying
+fn load_future_and_underlying(
+    deps: &DepsMut,
+    asset_name: &str,
+) -> Result<(AssetInfo, NormalAssetInfo), ContractError> {
+    let asset_info = load_supported_asset(deps, asset_name)?;
+    let underlying = match &asset_info {
+        AssetInfo::FutureAsset { underlying, .. } => underlying.clone().to_validated(deps.api)?,
+        AssetInfo::NormalAsset(..) => return Err(ContractError::NotAFutureAsset {}),
+    };
+    Ok((asset_info, underlying))
+}
+
+/// Settlement of debt in a future by delivering amount of its underlying. The debt is reduced by the
+/// amount of the future the underlying corresponds to and the underlying is kept for redemptions
+fn apply_settle(
+    mut deps: DepsMut,
+    env: &Env,
+    borrower: &Addr,
+    asset_info: AssetInfo,
+    underlying: NormalAssetInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    if future_state(deps.storage, &env.block, &asset_info)? == Some(FutureState::Trading) {
+        return Err(ContractError::SettlementNotOpen {});
+    }
+
+    let asset_name = asset_info.get_name();
+    let underlying_info = AssetInfo::NormalAsset(underlying);
+    let settled_amount = convert_decimals(
+        amount,
+        underlying_info.get_decimals(),
+        asset_info.get_decimals(),
+    )?;
+    let response = apply_repay(deps.branch(), borrower, asset_name.clone(), settled_amount)?;
+    SETTLEMENT_POOLS.update(
+        deps.storage,
+        &asset_name,
+        |pool: Option<Uint128>| -> StdResult<_> {
+            Ok(pool.unwrap_or_default().checked_add(amount)?)
+        },
+    )?;
+
+    Ok(response
+        .add_attribute("action", "settle")
+        .add_attribute("underlying_amount", amount))
+}
+
+/// Redemption of a finalized future. The tokens received are burned and the sender is paid their
+/// value at the settlement price in the underlying, out of the settlement pool. If the pool can not
+/// cover every outstanding token, each redemption gets the same share of what it is owed
+fn execute_redeem(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+    asset_name: String,
+) -> Result<Response, ContractError> {
+    let holder = deps.api.addr_validate(&cw20_msg.sender)?;
+    let amount = cw20_msg.amount;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let asset_info = load_supported_asset(&deps, &asset_name)?;
+    let (contract_addr, underlying) = match &asset_info {
+        AssetInfo::FutureAsset {
+            contract_addr,
+            underlying,
+            ..
+        } => (
+            contract_addr,
+            AssetInfo::NormalAsset(underlying.clone().to_validated(deps.api)?),
+        ),
+        AssetInfo::NormalAsset(..) => return Err(ContractError::NotAFutureAsset {}),
+    };
+    if info.sender != *contract_addr {
+        return Err(StdError::generic_err(format!(
+            "Address on whitelist and sender contract address for cw20 asset {} do not match",
+            asset_name
+        ))
+        .into());
+    }
+    if future_state(deps.storage, &env.block, &asset_info)? != Some(FutureState::Finalized) {
+        return Err(ContractError::FutureNotFinalized {});
+    }
+    if !SETTLEMENT_PRICES.has(deps.storage, &asset_name) {
+        return Err(ContractError::SettlementPriceNotFixed {});
+    }
+
+    // Every token minted to borrowers and not burned yet has the same claim on the pool, including
+    // tokens deposited as collateral in this contract
+    let outstanding = FUTURE_SUPPLY
+        .may_load(deps.storage, &asset_name)?
+        .unwrap_or_default();
+    if amount > outstanding {
+        return Err(ContractError::RedeemExceedsSupply {});
+    }
+
+    // Each token is owed one unit of the underlying, as settlements deliver it
+    let to_underlying = |amount: Uint128| {
+        convert_decimals(amount, asset_info.get_decimals(), underlying.get_decimals())
+    };
+    let owed = to_underlying(amount)?;
+    let outstanding_owed = to_underlying(outstanding)?;
+    let pool = SETTLEMENT_POOLS
+        .may_load(deps.storage, &asset_name)?
+        .unwrap_or_default();
+    let payout = if pool >= outstanding_owed {
+        owed
+    } else {
+        owed.multiply_ratio(pool, outstanding_owed)
+    };
+    if payout.is_zero() {
+        return Err(ContractError::SettlementPoolEmpty {});
+    }
+    let remaining_pool = pool
+        .checked_sub(payout)
+        .map_err(|_| ContractError::SettlementPoolEmpty {})?;
+    SETTLEMENT_POOLS.save(deps.storage, &asset_name, &remaining_pool)?;
+
+    Ok(Response::new()
+        .add_messages(burn_future_msgs(
+            deps.storage,
+            &asset_info,
+            &env.contract.address,
+            amount,
+        )?)
+        .add_message(transfer_asset_msg(&underlying, &holder, payout)?)
+        .add_attribute("action", "redeem")
+        .add_attribute("from", holder)
+        .add_attribute("amount", amount)
+        .add_attribute("asset_name", asset_name)
+        .add_attribute("payout", payout))
+}
+
+/// Records the settlement price of an expired future. The price of the underlying is the TWAP over
+/// the configured window before expiry if the oracle manager has one, otherwise the spot price
+fn execute_fix_settlement_price(
+    deps: DepsMut,
+    env: Env,
+    asset_name: String,
+) -> Result<Response, ContractError> {
+    let (underlying, expiry) = load_unfixed_expired_future(&deps, &env, &asset_name)?;
+    let config = CONFIG.load(deps.storage)?;
+    // The spot price at the time of the call is only a fair fixing shortly after expiry, and the
+    // history the TWAP is computed from is only guaranteed to cover the window for that long
+    if env.block.time.seconds() > expiry.saturating_add(config.fixing_period) {
+        return Err(ContractError::FixingPeriodOver {});
+    }
+
+    // The spot price is only used when the oracle manager keeps no price history to average the
+    // underlying over. A TWAP that fails with a history is an error rather than a reason to fall back,
+    // the admin sets the price once the fixing period is over
+    let has_price_history = config.fixing_twap_window > 0
+        && get_oracle_price_history_config(deps.querier, &config.oracle_manager_addr, &underlying)?
+            .is_some();
+    let twap_price = if has_price_history {
+        Some(get_oracle_twap_price(
+            deps.querier,
+            &config.oracle_manager_addr,
+            &config.quote_asset,
+            &underlying,
+            expiry.saturating_sub(config.fixing_twap_window),
+            expiry,
+        )?)
+    } else {
+        None
+    };
+    let fixing = match twap_price {
+        Some(price) => SettlementFixing {
+            price,
+            fixed_at: env.block.time.seconds(),
+            twap: true,
+        },
+        None => SettlementFixing {
+            price: get_checked_oracle_price(
+                deps.as_ref(),
+                &env.block,
+                &underlying,
+                PriceFreshness::Required,
+            )?,
+            fixed_at: env.block.time.seconds(),
+            twap: false,
+        },
+    };
+    SETTLEMENT_PRICES.save(deps.storage, &asset_name, &fixing)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "fix_settlement_price")
+        .add_attribute("asset_name", asset_name)
+        .add_attribute("price", fixing.price.to_string())
+        .add_attribute("twap", fixing.twap.to_string()))
+}
+
+/// Fallback for futures whose settlement price could not be fixed within the fixing period, e.g.
+/// because the TWAP of the underlying failed. The fixing is final like any other
+fn execute_set_settlement_price(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_name: String,
+    price: Decimal,
+) -> Result<Response, ContractError> {
+    // Only contract admin can set settlement prices
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let (_, expiry) = load_unfixed_expired_future(&deps, &env, &asset_name)?;
+    let config = CONFIG.load(deps.storage)?;
+    if env.block.time.seconds() <= expiry.saturating_add(config.fixing_period) {
+        return Err(ContractError::FixingPeriodNotOver {});
+    }
+    let fixing = SettlementFixing {
+        price,
+        fixed_at: env.block.time.seconds(),
+        twap: false,
+    };
+    SETTLEMENT_PRICES.save(deps.storage, &asset_name, &fixing)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_settlement_price")
+        .add_attribute("asset_name", asset_name)
+        .add_attribute("price", fixing.price.to_string()))
+}
+
+/// Returns the name of the underlying and the expiry of the future asset_name, if it expired and
+/// its settlement price was not fixed yet
+fn load_unfixed_expired_future(
+    deps: &DepsMut,
+    env: &Env,
+    asset_name: &str,
+) -> Result<(String, u64), ContractError> {
+    let asset_info = load_supported_asset(deps, asset_name)?;
+    let (underlying, expiry) = match &asset_info {
+        AssetInfo::FutureAsset {
+            underlying, expiry, ..
+        } => (underlying.get_name(), *expiry),
+        AssetInfo::NormalAsset(..) => return Err(ContractError::NotAFutureAsset {}),
+    };
+    match future_state(deps.storage, &env.block, &asset_info)? {
+        Some(FutureState::Expired) | Some(FutureState::Finalized) => {}
+        _ => return Err(ContractError::FutureNotExpired {}),
+    }
+    if SETTLEMENT_PRICES.has(deps.storage, asset_name) {
+        return Err(ContractError::SettlementPriceAlreadyFixed {});
+    }
+    Ok((underlying, expiry))
+}
+
+fn load_supported_asset(deps: &DepsMut, asset_name: &str) -> Result<AssetInfo, ContractError> {
+    match SUPPORTED_ASSETS.may_load(deps.storage, asset_name)? {
+        Some(asset_info) => Ok(asset_info),
+        //Asset not supported by contract
+        None => Err(ContractError::AssetNotSupported {}),
+    }
+}
+
+/// Appends the messages, attributes and events of other to response. A response has a single data
+/// field, so the data of other is only kept if response has none
+fn merge_responses(mut response: Response, other: Response) -> Response {
+    if response.data.is_none() {
+        response.data = other.data;
+    }
+    response
+        .add_submessages(other.messages)
+        .add_attributes(other.attributes)
+        .add_events(other.events)
+}
+
 fn execute_add_supported_asset(
     deps: DepsMut,
     info: MessageInfo,
     asset_name: String,
     asset_info_unvalidated: AssetInfoUnvalidated,
 ) -> Result<Response, ContractError> {
-    // Only contract admin can add new supported assets
-    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    // Only contract admin can add new supported assets, the future factory only futures
+    let from_future_factory = FUTURE_FACTORY.may_load(deps.storage)?.as_ref() == Some(&info.sender)
+        && matches!(
+            asset_info_unvalidated,
+            AssetInfoUnvalidated::FutureAsset { .. }
+        );
+    if !from_future_factory {
+        ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    }
 
     let asset_info = asset_info_unvalidated.to_validated(deps.api)?;
+    assert_cw20_decimals(deps.as_ref(), &asset_info)?;
+    assert_fixing_covered(deps.as_ref(), &asset_info)?;
     let check_exists = SUPPORTED_ASSETS.may_load(deps.storage, &asset_name)?;
     match check_exists {
         Some(..) => {
@@ -303,6 +1342,60 @@ fn execute_add_supported_asset(
         .add_attribute("asset_name", asset_name))
 }
 
+/// Checks that the price history the oracle manager keeps of the underlying of a future, if any,
+/// still covers the fixing TWAP window at the end of the fixing period. Observations are at least
+/// min_interval apart, so the history covers at least (length - 1) * min_interval seconds
+fn assert_fixing_covered(deps: Deps, asset_info: &AssetInfo) -> Result<(), ContractError> {
+    let underlying = match asset_info {
+        AssetInfo::FutureAsset { underlying, .. } => underlying.get_name(),
+        AssetInfo::NormalAsset(..) => return Ok(()),
+    };
+    let config = CONFIG.load(deps.storage)?;
+    if config.fixing_twap_window == 0 {
+        return Ok(());
+    }
+    let history_config = match get_oracle_price_history_config(
+        deps.querier,
+        &config.oracle_manager_addr,
+        &underlying,
+    )? {
+        Some(history_config) => history_config,
+        None => return Ok(()),
+    };
+    let covered = u64::from(history_config.length.saturating_sub(1))
+        .saturating_mul(history_config.min_interval);
+    if covered
+        < config
+            .fixing_twap_window
+            .saturating_add(config.fixing_period)
+    {
+        return Err(ContractError::FixingWindowNotCovered {});
+    }
+    Ok(())
+}
+
+/// Checks that cw20 assets point to a cw20 token contract with the same decimals as the asset
+fn assert_cw20_decimals(deps: Deps, asset_info: &AssetInfo) -> Result<(), ContractError> {
+    let contract_addr = match asset_info {
+        AssetInfo::NormalAsset(NormalAssetInfo::NativeToken { .. }) => return Ok(()),
+        AssetInfo::NormalAsset(NormalAssetInfo::CW20Token { contract_addr, .. })
+        | AssetInfo::FutureAsset { contract_addr, .. } => contract_addr,
+    };
+    let token_info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(contract_addr, &Cw20QueryMsg::TokenInfo {})
+        .map_err(|_| ContractError::NotACw20Token {
+            contract_addr: contract_addr.to_string(),
+        })?;
+    if token_info.decimals != asset_info.get_decimals() {
+        return Err(ContractError::DecimalsMismatch {
+            expected: asset_info.get_decimals(),
+            actual: token_info.decimals,
+        });
+    }
+    Ok(())
+}
+
 fn execute_remove_supported_asset(
     deps: DepsMut,
     info: MessageInfo,
@@ -310,23 +1403,137 @@ fn execute_remove_supported_asset(
 ) -> Result<Response, ContractError> {
     // Only contract owner can remove supported assets
     ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    // Removing an asset users still hold, owe or can redeem would strand their funds
+    if TOTAL_HELD.has(deps.storage, &asset_name)
+        || TOTAL_BORROWED.has(deps.storage, &asset_name)
+        || FUTURE_SUPPLY.has(deps.storage, &asset_name)
+        || !SETTLEMENT_POOLS
+            .may_load(deps.storage, &asset_name)?
+            .unwrap_or_default()
+            .is_zero()
+    {
+        return Err(ContractError::AssetInUse {});
+    }
 
     SUPPORTED_ASSETS.remove(deps.storage, &asset_name);
+    WITHDRAW_COOLDOWN.remove(deps.storage, &asset_name);
     Ok(Response::new()
         .add_attribute("action", "remove_supported_asset")
         .add_attribute("asset_name", asset_name))
 }
 
+fn execute_set_withdraw_cooldown(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_name: String,
+    cooldown: Option<Duration>,
+) -> Result<Response, ContractError> {
+    // Only contract admin can change withdraw cooldowns
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if !SUPPORTED_ASSETS.has(deps.storage, &asset_name) {
+        return Err(ContractError::AssetNotSupported {});
+    }
+    let cooldown_attribute = match cooldown {
+        Some(cooldown) => {
+            WITHDRAW_COOLDOWN.save(deps.storage, &asset_name, &cooldown)?;
+            cooldown.to_string()
+        }
+        None => {
+            WITHDRAW_COOLDOWN.remove(deps.storage, &asset_name);
+            "none".to_string()
+        }
+    };
+    Ok(Response::new()
+        .add_attribute("action", "set_withdraw_cooldown")
+        .add_attribute("asset_name", asset_name)
+        .add_attribute("cooldown", cooldown_attribute))
+}
+
+fn execute_set_price_max_age(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_name: String,
+    max_age: Option<u64>,
+) -> Result<Response, ContractError> {
+    // Only contract admin can change price max ages
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if !SUPPORTED_ASSETS.has(deps.storage, &asset_name) {
+        return Err(ContractError::AssetNotSupported {});
+    }
+    let max_age_attribute = match max_age {
+        Some(max_age) => {
+            PRICE_MAX_AGE.save(deps.storage, &asset_name, &max_age)?;
+            max_age.to_string()
+        }
+        None => {
+            PRICE_MAX_AGE.remove(deps.storage, &asset_name);
+            "none".to_string()
+        }
+    };
+    Ok(Response::new()
+        .add_attribute("action", "set_price_max_age")
+        .add_attribute("asset_name", asset_name)
+        .add_attribute("max_age", max_age_attribute))
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//Replies
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        // Hooks are only replied to on error. A failing hook must not revert the position change
+        // that triggered it, so the error is recorded and swallowed
+        HOOK_REPLY_ID => {
+            let error = match msg.result {
+                ContractResult::Err(error) => error,
+                ContractResult::Ok(_) => String::new(),
+            };
+            Ok(Response::new()
+                .add_attribute("action", "hook_failed")
+                .add_attribute("error", error))
+        }
+        id => Err(StdError::generic_err(format!("Unknown reply id {}", id)).into()),
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 //Queries
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Balance {
             address,
             asset_name,
         } => to_binary(&query_balance(deps, address, asset_name)?),
         QueryMsg::AssetInfo { asset_name } => to_binary(&query_asset_info(deps, asset_name)?),
+        QueryMsg::WithdrawCooldown { asset_name } => {
+            to_binary(&query_withdraw_cooldown(deps, asset_name)?)
+        }
+        QueryMsg::PriceMaxAge { asset_name } => {
+            to_binary(&PRICE_MAX_AGE.may_load(deps.storage, &asset_name)?)
+        }
+        QueryMsg::Claims {
+            address,
+            asset_name,
+        } => to_binary(&query_claims(deps, address, asset_name)?),
+        QueryMsg::Hooks {} => to_binary(&HOOKS.query_hooks(deps)?),
+        QueryMsg::FutureState { asset_name } => {
+            to_binary(&query_future_state(deps, env, asset_name)?)
+        }
+        QueryMsg::SettlementPrice { asset_name } => {
+            to_binary(&SETTLEMENT_PRICES.may_load(deps.storage, &asset_name)?)
+        }
+        QueryMsg::SettlementPool { asset_name } => to_binary(
+            &SETTLEMENT_POOLS
+                .may_load(deps.storage, &asset_name)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::TermStructure { underlying } => {
+            to_binary(&query_term_structure(deps, env, underlying)?)
+        }
+        QueryMsg::FutureFactory {} => to_binary(&FUTURE_FACTORY.may_load(deps.storage)?),
     }
 }
 
@@ -348,6 +1555,90 @@ fn query_asset_info(deps: Deps, asset_name: String) -> StdResult<Option<AssetInf
     Ok(asset_info_unvalidated)
 }
 
+fn query_withdraw_cooldown(deps: Deps, asset_name: String) -> StdResult<Option<Duration>> {
+    WITHDRAW_COOLDOWN.may_load(deps.storage, &asset_name)
+}
+
+fn query_claims(deps: Deps, address: String, asset_name: String) -> StdResult<ClaimsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    CLAIMS.query_claims(deps, (&address, &asset_name))
+}
+
+fn query_future_state(deps: Deps, env: Env, asset_name: String) -> StdResult<FutureStateResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    match SUPPORTED_ASSETS.load(deps.storage, &asset_name)? {
+        AssetInfo::FutureAsset { expiry, .. } => Ok(FutureStateResponse {
+            state: FutureState::at(
+                expiry,
+                config.settlement_window,
+                config.finalization_delay,
+                env.block.time.seconds(),
+            ),
+            expiry,
+        }),
+        AssetInfo::NormalAsset(..) => Err(StdError::generic_err(format!(
+            "{} is not a future asset",
+            asset_name
+        ))),
+    }
+}
+
+/// Futures are live until they expire. Prices of all live series and of the underlying are fetched
+/// with a single query to the oracle manager
+fn query_term_structure(
+    deps: Deps,
+    env: Env,
+    underlying: String,
+) -> StdResult<TermStructureResponse> {
+    let now = env.block.time.seconds();
+    let mut series = vec![];
+    for item in SUPPORTED_ASSETS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, asset_info) = item?;
+        if let AssetInfo::FutureAsset {
+            asset_name,
+            underlying: future_underlying,
+            expiry,
+            ..
+        } = asset_info
+        {
+            if future_underlying.get_name() == underlying && expiry > now {
+                series.push((asset_name, expiry));
+            }
+        }
+    }
+    series.sort_by_key(|(_, expiry)| *expiry);
+
+    let mut asset_names: Vec<String> = series.iter().map(|(name, _)| name.clone()).collect();
+    asset_names.push(underlying.clone());
+    let prices =
+        get_checked_oracle_prices(deps, &env.block, &asset_names, PriceFreshness::StaleAllowed)
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let price = |asset_name: &String| {
+        prices.get(asset_name).copied().ok_or_else(|| {
+            StdError::generic_err(format!("No oracle price returned for {}", asset_name))
+        })
+    };
+    let spot_price = price(&underlying)?;
+
+    let mut points = vec![];
+    for (asset_name, expiry) in series {
+        let future_price = price(&asset_name)?;
+        let (implied_rate, negative) = implied_rate(future_price, spot_price, expiry - now)?;
+        points.push(TermStructurePoint {
+            asset_name,
+            expiry,
+            price: future_price,
+            implied_rate,
+            negative,
+        });
+    }
+    Ok(TermStructureResponse {
+        underlying,
+        spot_price,
+        points,
+    })
+}
+
 #[cfg(test)]
 #[path = "./contract_unittests.rs"]
 mod contract_unittests;
@@ -1,51 +1,208 @@
 use super::*;
-use crate::error::ContractError::ControllerError;
+use crate::helper::HOOK_GAS_LIMIT;
+use crate::valuation::{to_value, value_position};
 use cosmwasm_std::testing::{
-    mock_dependencies,
     mock_env,
-    mock_info, //, MockApi, MockStorage, MOCK_CONTRACT_ADDR,
+    mock_info,
+    MOCK_CONTRACT_ADDR, //, MockApi, MockStorage,
 };
-use cosmwasm_std::{Api, Decimal};
-use cw_controllers::AdminError;
+use cosmwasm_std::{
+    attr, coin, BankMsg, Decimal as StdDecimal, Event, Reply, SubMsg, SubMsgExecutionResponse,
+    Timestamp, WasmMsg,
+};
+use cw0::Expiration;
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, TokenInfoResponse};
+use cw_controllers::{AdminError, Claim, HookError};
 use steadifi::asset::{AssetInfoUnvalidated, NormalAssetInfoUnvalidated};
+use steadifi::collateral_manager::hook::PositionChangedHookMsg;
+use steadifi::mars_protocol_math::Decimal;
+use steadifi::oracle_manager::msg::PriceResponse;
+use steadifi::oracle_manager::PriceHistoryConfig;
 //use cosmwasm_std::{attr, coin, from_binary, BankMsg, OwnedDeps, SubMsg};
 
+fn default_instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        max_ltv: StdDecimal::percent(80),
+        oracle_manager_addr: "oracle_manager".to_string(),
+        quote_asset: "uusd".to_string(),
+        settlement_window: SETTLEMENT_WINDOW,
+        finalization_delay: FINALIZATION_DELAY,
+        fixing_twap_window: FIXING_TWAP_WINDOW,
+        fixing_period: FIXING_PERIOD,
+        liquidation_bonus: Decimal::percent(5),
+        close_factor: Decimal::percent(50),
+    }
+}
+
+const SETTLEMENT_WINDOW: u64 = 7 * 24 * 60 * 60;
+const FINALIZATION_DELAY: u64 = 7 * 24 * 60 * 60;
+const FIXING_TWAP_WINDOW: u64 = 60 * 60;
+const FIXING_PERIOD: u64 = 30 * 60;
+
+// Expiry of the futures used in the tests, 30 days after the default mock_env block time
+fn fbtc_expiry() -> u64 {
+    mock_env().block.time.seconds() + 30 * 24 * 60 * 60
+}
+
+// Messages burning amount of fbtc held by the contract through its mint authority
+fn fbtc_burn_msgs(amount: u128) -> Vec<SubMsg> {
+    vec![
+        SubMsg::new(
+            Cw20ExecuteMsg::IncreaseAllowance {
+                spender: "fbtc_mint_authority".to_string(),
+                amount: Uint128::from(amount),
+                expires: None,
+            }
+            .into_cosmos_msg("fbtc_cw20_address")
+            .unwrap(),
+        ),
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "fbtc_mint_authority".to_string(),
+            msg: to_binary(&MintAuthorityExecuteMsg::BurnFrom {
+                owner: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::from(amount),
+            })
+            .unwrap(),
+            funds: vec![],
+        }),
+    ]
+}
+
+fn token_info(decimals: u8) -> TokenInfoResponse {
+    TokenInfoResponse {
+        name: "token".to_string(),
+        symbol: "TKN".to_string(),
+        decimals,
+        total_supply: Uint128::zero(),
+    }
+}
+
 // Test initialization works
 #[test]
 fn test_initialization() {
-    let mut deps = mock_dependencies(&[]);
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
     let env = mock_env();
     let info = mock_info("Admin", &[]);
-    let instantiate_msg = InstantiateMsg {};
+    let instantiate_msg = default_instantiate_msg();
     let contract_result = instantiate(deps.as_mut(), env, info, instantiate_msg);
     assert_eq!(contract_result, Ok(Response::default()));
     // Testing out the controllers
     assert!(ADMIN
-        .is_admin(deps.as_ref(), &deps.api.addr_validate("Admin").unwrap())
+        .is_admin(deps.as_ref(), &Addr::unchecked("Admin"))
         .unwrap());
     assert!(!ADMIN
-        .is_admin(
-            deps.as_ref(),
-            &deps.api.addr_validate("someone_who_is_not_Admin").unwrap()
-        )
+        .is_admin(deps.as_ref(), &Addr::unchecked("someone_who_is_not_Admin"))
         .unwrap());
+
+    // Liquidations have to repay part of the debt and can not seize twice its value
+    for (close_factor, liquidation_bonus) in [(0, 5), (101, 5), (50, 100)] {
+        let instantiate_msg = InstantiateMsg {
+            close_factor: Decimal::percent(close_factor),
+            liquidation_bonus: Decimal::percent(liquidation_bonus),
+            ..default_instantiate_msg()
+        };
+        let contract_result = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("Admin", &[]),
+            instantiate_msg,
+        );
+        assert_eq!(
+            contract_result,
+            Err(ContractError::InvalidLiquidationParams {})
+        );
+    }
+}
+#[test]
+fn test_quote_asset() {
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier.set_oracle_quote_asset("ukrw");
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+
+    // Positions are valued in ukrw, so the oracle manager has to price in ukrw as well
+    let contract_result = instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::QuoteAssetMismatch {
+            quote_asset: "uusd".to_string(),
+            oracle_quote_asset: "ukrw".to_string(),
+        })
+    );
+    let instantiate_msg = InstantiateMsg {
+        quote_asset: "ukrw".to_string(),
+        ..default_instantiate_msg()
+    };
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        instantiate_msg,
+    )
+    .unwrap();
+
+    // The quote asset is worth one without asking the oracle manager
+    let krw_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "ukrw".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        });
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "ukrw".to_string(),
+        asset_info_unvalidated: krw_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info, execute_msg).unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[coin(1000, "ukrw")]),
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+    let valuation = value_position(
+        deps.as_ref(),
+        &env.block,
+        &Addr::unchecked("user"),
+        PriceFreshness::Required,
+    )
+    .unwrap();
+    assert_eq!(valuation.price("ukrw").unwrap(), Decimal::one());
+    assert_eq!(
+        valuation.weighted_collateral_value,
+        to_value(Uint128::from(1000u128), 6, Decimal::one())
+            .unwrap()
+            .checked_mul(Decimal::from_ratio(9u32, 10u32))
+            .unwrap()
+    );
 }
+
 #[test]
 fn test_add_supported_assets() {
     // Initializations
-    let mut deps = mock_dependencies(&[]);
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_token_info("wbtc_cw20_address", token_info(8));
+    deps.querier
+        .set_token_info("fbtc_cw20_address", token_info(8));
     let env = mock_env();
     let info = mock_info("Andmin", &[]);
-    let instantiate_msg = InstantiateMsg {};
+    let instantiate_msg = default_instantiate_msg();
     // Instantiate contract
-    instantiate(deps.as_mut(), env.clone(), info.clone(), instantiate_msg);
+    instantiate(deps.as_mut(), env.clone(), info.clone(), instantiate_msg).unwrap();
     // Add a native asset: "luna"
     let luna_asset_info =
         AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
             denom: "luna".to_string(),
             collateralizeable: true,
             ratio: Decimal::from_ratio(9u32, 10u32),
-            oracle_addr: "luna_oracle_address".to_string(),
+            decimals: 6,
         });
     let execute_msg = ExecuteMsg::AddSupportedAsset {
         asset_name: "luna".to_string(),
@@ -61,10 +218,11 @@ fn test_add_supported_assets() {
     // Add a cw20 asset
     let wbtc_asset_info =
         AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: "wbtc".to_string(),
             contract_addr: "wbtc_cw20_address".to_string(),
             collateralizeable: true,
             ratio: Decimal::from_ratio(8u32, 10u32),
-            oracle_addr: "wbtc_oracle_address".to_string(),
+            decimals: 8,
         });
     let execute_msg = ExecuteMsg::AddSupportedAsset {
         asset_name: "wbtc".to_string(),
@@ -79,16 +237,20 @@ fn test_add_supported_assets() {
     );
     // Add a future asset
     let fbtc_asset_info = AssetInfoUnvalidated::FutureAsset {
+        asset_name: "fbtc".to_string(),
         contract_addr: "fbtc_cw20_address".to_string(),
+        mint_authority: "fbtc_mint_authority".to_string(),
         collateralizeable: false,
         ratio: Default::default(),
-        pool_oracle_addr: "address_to_fbtc/wbtc_pool".to_string(),
         underlying: NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: "wbtc".to_string(),
             contract_addr: "wbtc_cw20_address".to_string(),
             collateralizeable: true,
             ratio: Decimal::from_ratio(9u32, 10u32),
-            oracle_addr: "wbtc_oracle_address".to_string(),
+            decimals: 8,
         },
+        decimals: 8,
+        expiry: fbtc_expiry(),
     };
     let execute_msg = ExecuteMsg::AddSupportedAsset {
         asset_name: "fbtc".to_string(),
@@ -106,6 +268,92 @@ fn test_add_supported_assets() {
             .add_attribute("action", "add_supported_asset")
             .add_attribute("asset_name", "fbtc"))
     );
+    // The future factory can add the futures it deploys once the admin registered it
+    let future_asset_info = AssetInfoUnvalidated::FutureAsset {
+        asset_name: "fbtc2".to_string(),
+        contract_addr: "fbtc_cw20_address".to_string(),
+        mint_authority: "fbtc2_mint_authority".to_string(),
+        collateralizeable: false,
+        ratio: Default::default(),
+        underlying: NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: "wbtc".to_string(),
+            contract_addr: "wbtc_cw20_address".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 8,
+        },
+        decimals: 8,
+        expiry: fbtc_expiry() + 1000,
+    };
+    let factory_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "fbtc2".to_string(),
+        asset_info_unvalidated: future_asset_info,
+    };
+    let factory_info = mock_info("future_factory", &[]);
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        factory_info.clone(),
+        factory_msg.clone(),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::SetFutureFactory {
+            address: Some("future_factory".to_string()),
+        },
+    )
+    .unwrap();
+    let future_factory: Option<Addr> =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::FutureFactory {}).unwrap())
+            .unwrap();
+    assert_eq!(future_factory, Some(Addr::unchecked("future_factory")));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory_info.clone(),
+        factory_msg,
+    )
+    .unwrap();
+    assert!(SUPPORTED_ASSETS.has(deps.as_ref().storage, "fbtc2"));
+    // Other assets still need the admin
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        factory_info.clone(),
+        ExecuteMsg::AddSupportedAsset {
+            asset_name: "uusd".to_string(),
+            asset_info_unvalidated: AssetInfoUnvalidated::NormalAsset(
+                NormalAssetInfoUnvalidated::NativeToken {
+                    denom: "uusd".to_string(),
+                    collateralizeable: true,
+                    ratio: Decimal::from_ratio(9u32, 10u32),
+                    decimals: 6,
+                },
+            ),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+    // Only the admin can set the future factory
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        factory_info,
+        ExecuteMsg::SetFutureFactory { address: None },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+
     // Anyone other than admin can not add any assets
     let info = mock_info("someone_who_is_not_admin", &[]);
     let contract_result = execute(
@@ -118,27 +366,2471 @@ fn test_add_supported_assets() {
         contract_result,
         Err(ContractError::ControllerError(AdminError::NotAdmin {}))
     );
+    // Decimals have to match the ones of the cw20 token
+    let info = mock_info("Andmin", &[]);
+    let weth_asset_info = |contract_addr: &str, decimals: u8| {
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: "weth".to_string(),
+            contract_addr: contract_addr.to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(8u32, 10u32),
+            decimals,
+        })
+    };
+    deps.querier
+        .set_token_info("weth_cw20_address", token_info(18));
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "weth".to_string(),
+        asset_info_unvalidated: weth_asset_info("weth_cw20_address", 8),
+    };
+    let contract_result = execute(deps.as_mut(), env.clone(), info.clone(), execute_msg);
+    assert_eq!(
+        contract_result,
+        Err(ContractError::DecimalsMismatch {
+            expected: 8,
+            actual: 18
+        })
+    );
+    // The address has to be a cw20 token
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "weth".to_string(),
+        asset_info_unvalidated: weth_asset_info("not_a_cw20_address", 18),
+    };
+    let contract_result = execute(deps.as_mut(), env.clone(), info.clone(), execute_msg);
+    assert_eq!(
+        contract_result,
+        Err(ContractError::NotACw20Token {
+            contract_addr: "not_a_cw20_address".to_string()
+        })
+    );
+    // Decimals can not exceed 18
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "weth".to_string(),
+        asset_info_unvalidated: weth_asset_info("weth_cw20_address", 19),
+    };
+    let contract_result = execute(deps.as_mut(), env.clone(), info.clone(), execute_msg);
+    assert_eq!(
+        contract_result,
+        Err(StdError::generic_err("Decimals must not exceed 18").into())
+    );
+    // The underlying of a future is validated as well
+    let feth_asset_info = |contract_addr: &str, decimals: u8| AssetInfoUnvalidated::FutureAsset {
+        asset_name: "feth".to_string(),
+        contract_addr: "fbtc_cw20_address".to_string(),
+        mint_authority: "feth_mint_authority".to_string(),
+        collateralizeable: false,
+        ratio: Default::default(),
+        underlying: NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: "weth".to_string(),
+            contract_addr: contract_addr.to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(8u32, 10u32),
+            decimals,
+        },
+        decimals: 8,
+        expiry: fbtc_expiry(),
+    };
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "feth".to_string(),
+        asset_info_unvalidated: feth_asset_info("weth_cw20_address", 19),
+    };
+    let contract_result = execute(deps.as_mut(), env.clone(), info.clone(), execute_msg);
+    assert_eq!(
+        contract_result,
+        Err(StdError::generic_err("Decimals must not exceed 18").into())
+    );
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "feth".to_string(),
+        asset_info_unvalidated: feth_asset_info("", 18),
+    };
+    let contract_result = execute(deps.as_mut(), env, info, execute_msg);
+    assert!(contract_result.is_err());
+    assert_eq!(
+        query_asset_info(deps.as_ref(), "feth".to_string()).unwrap(),
+        None
+    );
 }
 #[test]
 fn test_query_assetinfo() {
     // Initializations
-    let mut deps = mock_dependencies(&[]);
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
     let env = mock_env();
     let info = mock_info("Andmin", &[]);
-    let instantiate_msg = InstantiateMsg {};
+    let instantiate_msg = default_instantiate_msg();
     // Instantiate contract
-    instantiate(deps.as_mut(), env.clone(), info.clone(), instantiate_msg);
+    instantiate(deps.as_mut(), env.clone(), info.clone(), instantiate_msg).unwrap();
     // Add a native asset: "luna"
     let luna_asset_info =
         AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
             denom: "luna".to_string(),
             collateralizeable: true,
             ratio: Decimal::from_ratio(9u32, 10u32),
-            oracle_addr: "luna_oracle_address".to_string(),
+            decimals: 6,
+        });
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "luna".to_string(),
+        asset_info_unvalidated: luna_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), info.clone(), execute_msg).unwrap();
+    let asset_info = query_asset_info(deps.as_ref(), "luna".to_string()).unwrap();
+    assert_eq!(
+        asset_info.map(|asset_info| asset_info.get_name()),
+        Some("luna".to_string())
+    );
+}
+
+#[test]
+fn test_withdraw_with_cooldown() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    let mut env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        });
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "luna".to_string(),
+        asset_info_unvalidated: luna_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info.clone(), execute_msg).unwrap();
+
+    // Only the admin can set a cooldown
+    let execute_msg = ExecuteMsg::SetWithdrawCooldown {
+        asset_name: "luna".to_string(),
+        cooldown: Some(Duration::Time(100)),
+    };
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone_who_is_not_admin", &[]),
+        execute_msg.clone(),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+    execute(deps.as_mut(), env.clone(), admin_info, execute_msg).unwrap();
+    assert_eq!(
+        query_withdraw_cooldown(deps.as_ref(), "luna".to_string()).unwrap(),
+        Some(Duration::Time(100))
+    );
+
+    // Deposit and withdraw part of it: nothing is sent yet
+    let user_info = mock_info("user", &[coin(1000, "luna")]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info.clone(),
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+    let execute_msg = ExecuteMsg::NativeWithdraw {
+        coin_denom: "luna".to_string(),
+        amount: Uint128::from(400u128),
+    };
+    let response = execute(deps.as_mut(), env.clone(), user_info.clone(), execute_msg).unwrap();
+    assert!(response.messages.is_empty());
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "luna".to_string()).unwrap();
+    assert_eq!(balance.collateral, Uint128::from(600u128));
+    let claims = query_claims(deps.as_ref(), "user".to_string(), "luna".to_string()).unwrap();
+    assert_eq!(
+        claims.claims,
+        vec![Claim::new(
+            400,
+            Expiration::AtTime(env.block.time.plus_seconds(100))
+        )]
+    );
+
+    // Claim can not be released before the cooldown passes
+    let execute_msg = ExecuteMsg::ClaimWithdrawals {
+        asset_name: "luna".to_string(),
+    };
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info.clone(),
+        execute_msg.clone(),
+    );
+    assert_eq!(contract_result, Err(ContractError::NothingToClaim {}));
+
+    // After the cooldown the claimed amount is paid out
+    env.block.time = env.block.time.plus_seconds(100);
+    let response = execute(deps.as_mut(), env.clone(), user_info, execute_msg).unwrap();
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "user".to_string(),
+            amount: vec![coin(400, "luna")],
+        })]
+    );
+    let claims = query_claims(deps.as_ref(), "user".to_string(), "luna".to_string()).unwrap();
+    assert!(claims.claims.is_empty());
+}
+
+#[test]
+fn test_withdraw_without_cooldown() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        });
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "luna".to_string(),
+        asset_info_unvalidated: luna_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info, execute_msg).unwrap();
+
+    let user_info = mock_info("user", &[coin(1000, "luna")]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info.clone(),
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+
+    // Withdrawing a native asset through the cw20 path is rejected
+    let execute_msg = ExecuteMsg::Cw20Withdraw {
+        asset_name: "luna".to_string(),
+        amount: Uint128::from(400u128),
+    };
+    let contract_result = execute(deps.as_mut(), env.clone(), user_info.clone(), execute_msg);
+    assert!(contract_result.is_err());
+
+    let execute_msg = ExecuteMsg::NativeWithdraw {
+        coin_denom: "luna".to_string(),
+        amount: Uint128::from(400u128),
+    };
+    let response = execute(deps.as_mut(), env, user_info, execute_msg).unwrap();
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "user".to_string(),
+            amount: vec![coin(400, "luna")],
+        })]
+    );
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "luna".to_string()).unwrap();
+    assert_eq!(balance.collateral, Uint128::from(600u128));
+}
+
+#[test]
+fn test_remove_supported_asset() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    let mut env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
         });
     let execute_msg = ExecuteMsg::AddSupportedAsset {
         asset_name: "luna".to_string(),
         asset_info_unvalidated: luna_asset_info,
     };
-    execute(deps.as_mut(), env.clone(), info.clone(), execute_msg);
+    execute(deps.as_mut(), env.clone(), admin_info.clone(), execute_msg).unwrap();
+    let execute_msg = ExecuteMsg::SetWithdrawCooldown {
+        asset_name: "luna".to_string(),
+        cooldown: Some(Duration::Time(100)),
+    };
+    execute(deps.as_mut(), env.clone(), admin_info.clone(), execute_msg).unwrap();
+
+    // Asset can not be removed while it is held as collateral
+    let user_info = mock_info("user", &[coin(1000, "luna")]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info.clone(),
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+    let remove_msg = ExecuteMsg::RemoveSupportedAsset {
+        asset_name: "luna".to_string(),
+    };
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        remove_msg.clone(),
+    );
+    assert_eq!(contract_result, Err(ContractError::AssetInUse {}));
+
+    // Withdrawing everything removes the collateral entry, but the claim still holds the asset
+    let execute_msg = ExecuteMsg::NativeWithdraw {
+        coin_denom: "luna".to_string(),
+        amount: Uint128::from(1000u128),
+    };
+    execute(deps.as_mut(), env.clone(), user_info.clone(), execute_msg).unwrap();
+    assert!(!COLLATERAL.has(&deps.storage, (&Addr::unchecked("user"), "luna")));
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        remove_msg.clone(),
+    );
+    assert_eq!(contract_result, Err(ContractError::AssetInUse {}));
+
+    // Once the claim is paid out the asset can be removed
+    env.block.time = env.block.time.plus_seconds(100);
+    let execute_msg = ExecuteMsg::ClaimWithdrawals {
+        asset_name: "luna".to_string(),
+    };
+    execute(deps.as_mut(), env.clone(), user_info, execute_msg).unwrap();
+    execute(deps.as_mut(), env, admin_info, remove_msg).unwrap();
+    assert_eq!(
+        query_asset_info(deps.as_ref(), "luna".to_string()).unwrap(),
+        None
+    );
+    assert_eq!(
+        query_withdraw_cooldown(deps.as_ref(), "luna".to_string()).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_remove_future_asset() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_token_info("fluna_cw20_address", token_info(6));
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    deps.querier
+        .set_oracle_price("fluna", Decimal::from_ratio(50u32, 1u32));
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info = NormalAssetInfoUnvalidated::NativeToken {
+        denom: "luna".to_string(),
+        collateralizeable: true,
+        ratio: Decimal::from_ratio(9u32, 10u32),
+        decimals: 6,
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::AddSupportedAsset {
+            asset_name: "luna".to_string(),
+            asset_info_unvalidated: AssetInfoUnvalidated::NormalAsset(luna_asset_info.clone()),
+        },
+    )
+    .unwrap();
+    let fluna_asset_info = AssetInfoUnvalidated::FutureAsset {
+        asset_name: "fluna".to_string(),
+        contract_addr: "fluna_cw20_address".to_string(),
+        mint_authority: "fluna_mint_authority".to_string(),
+        collateralizeable: false,
+        ratio: Default::default(),
+        underlying: luna_asset_info,
+        decimals: 6,
+        expiry: fbtc_expiry(),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::AddSupportedAsset {
+            asset_name: "fluna".to_string(),
+            asset_info_unvalidated: fluna_asset_info,
+        },
+    )
+    .unwrap();
+    let env_at = |seconds: u64| {
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(seconds);
+        env
+    };
+    let remove_msg = ExecuteMsg::RemoveSupportedAsset {
+        asset_name: "fluna".to_string(),
+    };
+
+    // A future can not be removed while borrowers owe it
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[coin(1000, "luna")]),
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        ExecuteMsg::Borrow {
+            asset_name: "fluna".to_string(),
+            amount: Uint128::from(100u128),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        TOTAL_BORROWED.load(deps.as_ref().storage, "fluna").unwrap(),
+        Uint128::from(100u128)
+    );
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        remove_msg.clone(),
+    );
+    assert_eq!(contract_result, Err(ContractError::AssetInUse {}));
+
+    // Settling the debt leaves the minted tokens redeemable from the settlement pool
+    let expiry = fbtc_expiry();
+    execute(
+        deps.as_mut(),
+        env_at(expiry - 1),
+        mock_info("user", &[coin(100, "luna")]),
+        ExecuteMsg::NativeSettle {
+            asset_name: "fluna".to_string(),
+        },
+    )
+    .unwrap();
+    assert!(!TOTAL_BORROWED.has(deps.as_ref().storage, "fluna"));
+    let contract_result = execute(
+        deps.as_mut(),
+        env_at(expiry - 1),
+        admin_info.clone(),
+        remove_msg.clone(),
+    );
+    assert_eq!(contract_result, Err(ContractError::AssetInUse {}));
+
+    // Once every token is redeemed the pool is empty and the future can be removed
+    let finalized_env = env_at(expiry + FINALIZATION_DELAY);
+    execute(
+        deps.as_mut(),
+        env_at(expiry),
+        mock_info("anyone", &[]),
+        ExecuteMsg::FixSettlementPrice {
+            asset_name: "fluna".to_string(),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        finalized_env.clone(),
+        mock_info("fluna_cw20_address", &[]),
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "holder".to_string(),
+            amount: Uint128::from(100u128),
+            msg: to_binary(&Cw20HookMsg::Redeem {
+                asset_name: "fluna".to_string(),
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+    assert!(!FUTURE_SUPPLY.has(deps.as_ref().storage, "fluna"));
+
+    // Tokens still outstanding or funds left in the pool keep the future in use
+    FUTURE_SUPPLY
+        .save(deps.as_mut().storage, "fluna", &Uint128::from(1u128))
+        .unwrap();
+    let contract_result = execute(
+        deps.as_mut(),
+        finalized_env.clone(),
+        admin_info.clone(),
+        remove_msg.clone(),
+    );
+    assert_eq!(contract_result, Err(ContractError::AssetInUse {}));
+    FUTURE_SUPPLY.remove(deps.as_mut().storage, "fluna");
+    SETTLEMENT_POOLS
+        .save(deps.as_mut().storage, "fluna", &Uint128::from(1u128))
+        .unwrap();
+    let contract_result = execute(
+        deps.as_mut(),
+        finalized_env.clone(),
+        admin_info.clone(),
+        remove_msg.clone(),
+    );
+    assert_eq!(contract_result, Err(ContractError::AssetInUse {}));
+    SETTLEMENT_POOLS
+        .save(deps.as_mut().storage, "fluna", &Uint128::zero())
+        .unwrap();
+    execute(deps.as_mut(), finalized_env, admin_info, remove_msg).unwrap();
+    assert_eq!(
+        query_asset_info(deps.as_ref(), "fluna".to_string()).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_position_changed_hooks() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        });
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "luna".to_string(),
+        asset_info_unvalidated: luna_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info.clone(), execute_msg).unwrap();
+
+    // Only the admin can register hooks
+    let execute_msg = ExecuteMsg::AddHook {
+        addr: "indexer".to_string(),
+    };
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone_who_is_not_admin", &[]),
+        execute_msg.clone(),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::HookError(HookError::Admin(
+            AdminError::NotAdmin {}
+        )))
+    );
+    execute(deps.as_mut(), env.clone(), admin_info, execute_msg).unwrap();
+    assert_eq!(
+        HOOKS.query_hooks(deps.as_ref()).unwrap().hooks,
+        vec!["indexer".to_string()]
+    );
+
+    // Deposits notify the hook with the resulting balances
+    let user_info = mock_info("user", &[coin(1000, "luna")]);
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info.clone(),
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+    let expected_hook = PositionChangedHookMsg {
+        action: PositionAction::Deposit,
+        address: "user".to_string(),
+        asset_name: "luna".to_string(),
+        delta: Uint128::from(1000u128),
+        collateral: Uint128::from(1000u128),
+        borrow: Uint128::zero(),
+    };
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::reply_on_error(
+            expected_hook.into_cosmos_msg("indexer").unwrap(),
+            HOOK_REPLY_ID
+        )
+        .with_gas_limit(HOOK_GAS_LIMIT)]
+    );
+
+    // Withdrawals notify the hook next to paying out
+    let execute_msg = ExecuteMsg::NativeWithdraw {
+        coin_denom: "luna".to_string(),
+        amount: Uint128::from(400u128),
+    };
+    let response = execute(deps.as_mut(), env.clone(), user_info, execute_msg).unwrap();
+    let expected_hook = PositionChangedHookMsg {
+        action: PositionAction::Withdraw,
+        address: "user".to_string(),
+        asset_name: "luna".to_string(),
+        delta: Uint128::from(400u128),
+        collateral: Uint128::from(600u128),
+        borrow: Uint128::zero(),
+    };
+    assert_eq!(
+        response.messages,
+        vec![
+            SubMsg::reply_on_error(
+                expected_hook.into_cosmos_msg("indexer").unwrap(),
+                HOOK_REPLY_ID
+            )
+            .with_gas_limit(HOOK_GAS_LIMIT),
+            SubMsg::new(BankMsg::Send {
+                to_address: "user".to_string(),
+                amount: vec![coin(400, "luna")],
+            })
+        ]
+    );
+
+    // A failing hook is swallowed in the reply instead of reverting the withdrawal
+    let response = reply(
+        deps.as_mut(),
+        env.clone(),
+        Reply {
+            id: HOOK_REPLY_ID,
+            result: ContractResult::Err("hook out of gas".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        response,
+        Response::new()
+            .add_attribute("action", "hook_failed")
+            .add_attribute("error", "hook out of gas")
+    );
+    let contract_result = reply(
+        deps.as_mut(),
+        env.clone(),
+        Reply {
+            id: 1234,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    );
+    assert!(contract_result.is_err());
+
+    // Every position change notifies all hooks, so their number is capped
+    for index in 1..MAX_HOOKS {
+        let execute_msg = ExecuteMsg::AddHook {
+            addr: format!("hook{}", index),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("Admin", &[]),
+            execute_msg,
+        )
+        .unwrap();
+    }
+    let execute_msg = ExecuteMsg::AddHook {
+        addr: "one_hook_too_many".to_string(),
+    };
+    let contract_result = execute(deps.as_mut(), env, mock_info("Admin", &[]), execute_msg);
+    assert_eq!(
+        contract_result,
+        Err(ContractError::TooManyHooks { max: MAX_HOOKS })
+    );
+}
+
+#[test]
+fn test_merge_responses() {
+    let response = Response::new()
+        .add_message(BankMsg::Send {
+            to_address: "user".to_string(),
+            amount: vec![coin(100, "luna")],
+        })
+        .add_attribute("action", "first")
+        .add_event(Event::new("first"));
+    let other = Response::new()
+        .add_attribute("action", "second")
+        .add_event(Event::new("second"))
+        .set_data(Binary::from(b"second".to_vec()));
+    let merged = merge_responses(response.clone(), other);
+    assert_eq!(merged.messages, response.messages);
+    assert_eq!(
+        merged.attributes,
+        vec![attr("action", "first"), attr("action", "second")]
+    );
+    assert_eq!(
+        merged.events,
+        vec![Event::new("first"), Event::new("second")]
+    );
+    assert_eq!(merged.data, Some(Binary::from(b"second".to_vec())));
+
+    // Data of the first response is kept
+    let other = Response::new().set_data(Binary::from(b"third".to_vec()));
+    let merged = merge_responses(merged, other);
+    assert_eq!(merged.data, Some(Binary::from(b"second".to_vec())));
+}
+
+#[test]
+fn test_borrow_and_batch() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_token_info("fbtc_cw20_address", token_info(6));
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    deps.querier
+        .set_oracle_price("fbtc", Decimal::from_ratio(100u32, 1u32));
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        });
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "luna".to_string(),
+        asset_info_unvalidated: luna_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info.clone(), execute_msg).unwrap();
+    let fbtc_asset_info = AssetInfoUnvalidated::FutureAsset {
+        asset_name: "fbtc".to_string(),
+        contract_addr: "fbtc_cw20_address".to_string(),
+        mint_authority: "fbtc_mint_authority".to_string(),
+        collateralizeable: false,
+        ratio: Default::default(),
+        underlying: NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: "wbtc".to_string(),
+            contract_addr: "wbtc_cw20_address".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        },
+        decimals: 6,
+        expiry: fbtc_expiry(),
+    };
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "fbtc".to_string(),
+        asset_info_unvalidated: fbtc_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info, execute_msg).unwrap();
+
+    // Collateral worth 1000 * 50 * 0.9 = 45000 allows borrowing 400 fbtc worth 40000
+    let user_info = mock_info("user", &[coin(1000, "luna")]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info,
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+    let user_info = mock_info("user", &[]);
+    let execute_msg = ExecuteMsg::Borrow {
+        asset_name: "fbtc".to_string(),
+        amount: Uint128::from(400u128),
+    };
+    let response = execute(deps.as_mut(), env.clone(), user_info.clone(), execute_msg).unwrap();
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "fbtc_mint_authority".to_string(),
+            msg: to_binary(&MintAuthorityExecuteMsg::MintAndSend {
+                recipient: "user".to_string(),
+                amount: Uint128::from(400u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "fbtc".to_string()).unwrap();
+    assert_eq!(balance.borrow, Uint128::from(400u128));
+
+    // Only future assets can be borrowed
+    let execute_msg = ExecuteMsg::Borrow {
+        asset_name: "luna".to_string(),
+        amount: Uint128::from(100u128),
+    };
+    let contract_result = execute(deps.as_mut(), env.clone(), user_info, execute_msg);
+    assert_eq!(contract_result, Err(ContractError::AssetNotBorrowable {}));
+
+    // In a batch the position is only checked at the end, so it can pass through an unhealthy state
+    let ops = vec![
+        PositionOp::Borrow {
+            asset_name: "fbtc".to_string(),
+            amount: Uint128::from(100u128),
+        },
+        PositionOp::Deposit {
+            asset_name: "luna".to_string(),
+            amount: Uint128::from(200u128),
+        },
+    ];
+    let user_info = mock_info("user", &[coin(200, "luna")]);
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info,
+        ExecuteMsg::Batch { ops },
+    )
+    .unwrap();
+    assert_eq!(response.messages.len(), 1);
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "fbtc".to_string()).unwrap();
+    assert_eq!(balance.borrow, Uint128::from(500u128));
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "luna".to_string()).unwrap();
+    assert_eq!(balance.collateral, Uint128::from(1200u128));
+
+    // Repay the whole debt and withdraw the collateral in one go
+    let ops = vec![
+        PositionOp::Repay {
+            asset_name: "fbtc".to_string(),
+            amount: Uint128::from(500u128),
+        },
+        PositionOp::Withdraw {
+            asset_name: "luna".to_string(),
+            amount: Uint128::from(1200u128),
+        },
+    ];
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        ExecuteMsg::Batch { ops },
+    )
+    .unwrap();
+    assert_eq!(
+        response.messages,
+        [
+            vec![SubMsg::new(
+                Cw20ExecuteMsg::TransferFrom {
+                    owner: "user".to_string(),
+                    recipient: env.contract.address.to_string(),
+                    amount: Uint128::from(500u128),
+                }
+                .into_cosmos_msg("fbtc_cw20_address")
+                .unwrap()
+            )],
+            fbtc_burn_msgs(500),
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "user".to_string(),
+                amount: vec![coin(1200, "luna")],
+            })]
+        ]
+        .concat()
+    );
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "fbtc".to_string()).unwrap();
+    assert_eq!(balance.borrow, Uint128::zero());
+
+    // NOTE: mock storage is not reverted on errors, so the failing cases come last
+    // A batch that ends unhealthy fails as a whole
+    let ops = vec![
+        PositionOp::Deposit {
+            asset_name: "luna".to_string(),
+            amount: Uint128::from(100u128),
+        },
+        PositionOp::Borrow {
+            asset_name: "fbtc".to_string(),
+            amount: Uint128::from(100u128),
+        },
+    ];
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[coin(100, "luna")]),
+        ExecuteMsg::Batch { ops },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::NotEnoughTotalCollateral {})
+    );
+
+    // Native funds have to match the native deposits of the batch exactly
+    let ops = vec![PositionOp::Deposit {
+        asset_name: "luna".to_string(),
+        amount: Uint128::from(100u128),
+    }];
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[coin(200, "luna")]),
+        ExecuteMsg::Batch { ops },
+    );
+    assert_eq!(contract_result, Err(ContractError::BatchFundsMismatch {}));
+
+    // Operations with a zero amount are rejected
+    for op in vec![
+        PositionOp::Withdraw {
+            asset_name: "luna".to_string(),
+            amount: Uint128::zero(),
+        },
+        PositionOp::Borrow {
+            asset_name: "fbtc".to_string(),
+            amount: Uint128::zero(),
+        },
+    ] {
+        let contract_result = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("user", &[]),
+            ExecuteMsg::Batch { ops: vec![op] },
+        );
+        assert_eq!(contract_result, Err(ContractError::InvalidZeroAmount {}));
+    }
+}
+
+#[test]
+fn test_price_max_age() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_token_info("fbtc_cw20_address", token_info(6));
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    deps.querier
+        .set_oracle_price("fbtc", Decimal::from_ratio(100u32, 1u32));
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        });
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "luna".to_string(),
+        asset_info_unvalidated: luna_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info.clone(), execute_msg).unwrap();
+    let fbtc_asset_info = AssetInfoUnvalidated::FutureAsset {
+        asset_name: "fbtc".to_string(),
+        contract_addr: "fbtc_cw20_address".to_string(),
+        mint_authority: "fbtc_mint_authority".to_string(),
+        collateralizeable: false,
+        ratio: Default::default(),
+        underlying: NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: "wbtc".to_string(),
+            contract_addr: "wbtc_cw20_address".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        },
+        decimals: 6,
+        expiry: fbtc_expiry(),
+    };
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "fbtc".to_string(),
+        asset_info_unvalidated: fbtc_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info.clone(), execute_msg).unwrap();
+    let user_info = mock_info("user", &[coin(1000, "luna")]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info,
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+
+    // Without a maximum age any price is accepted
+    let price_max_age: Option<u64> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::PriceMaxAge {
+                asset_name: "luna".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(price_max_age, None);
+    let stale_luna_price = PriceResponse {
+        price: Decimal::from_ratio(50u32, 1u32),
+        last_updated: env.block.time.seconds() - 600,
+        sources_used: 1,
+        min: Decimal::from_ratio(50u32, 1u32),
+        max: Decimal::from_ratio(50u32, 1u32),
+    };
+    deps.querier
+        .set_oracle_price_response("luna", stale_luna_price.clone());
+    let execute_msg = ExecuteMsg::Borrow {
+        asset_name: "fbtc".to_string(),
+        amount: Uint128::from(100u128),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        execute_msg,
+    )
+    .unwrap();
+
+    // Prices of luna older than 300 seconds are rejected from now on
+    let execute_msg = ExecuteMsg::SetPriceMaxAge {
+        asset_name: "luna".to_string(),
+        max_age: Some(300),
+    };
+    let response = execute(deps.as_mut(), env.clone(), admin_info.clone(), execute_msg).unwrap();
+    assert_eq!(
+        response.attributes,
+        vec![
+            attr("action", "set_price_max_age"),
+            attr("asset_name", "luna"),
+            attr("max_age", "300"),
+        ]
+    );
+    let price_max_age: Option<u64> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::PriceMaxAge {
+                asset_name: "luna".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(price_max_age, Some(300));
+
+    // Repaying does not need prices, so it still goes through
+    let ops = vec![PositionOp::Repay {
+        asset_name: "fbtc".to_string(),
+        amount: Uint128::from(50u128),
+    }];
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        ExecuteMsg::Batch { ops },
+    )
+    .unwrap();
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "fbtc".to_string()).unwrap();
+    assert_eq!(balance.borrow, Uint128::from(50u128));
+
+    // A fresh price makes withdrawals possible again
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    let execute_msg = ExecuteMsg::NativeWithdraw {
+        coin_denom: "luna".to_string(),
+        amount: Uint128::from(100u128),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        execute_msg,
+    )
+    .unwrap();
+
+    // NOTE: mock storage is not reverted on errors, so the failing cases come last
+    // Borrows and withdrawals fail on a stale price
+    deps.querier
+        .set_oracle_price_response("luna", stale_luna_price);
+    let execute_msg = ExecuteMsg::Borrow {
+        asset_name: "fbtc".to_string(),
+        amount: Uint128::from(10u128),
+    };
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        execute_msg,
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::StalePrice {
+            asset_name: "luna".to_string()
+        })
+    );
+    let execute_msg = ExecuteMsg::NativeWithdraw {
+        coin_denom: "luna".to_string(),
+        amount: Uint128::from(100u128),
+    };
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        execute_msg,
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::StalePrice {
+            asset_name: "luna".to_string()
+        })
+    );
+
+    // Only supported assets can have a maximum price age
+    let execute_msg = ExecuteMsg::SetPriceMaxAge {
+        asset_name: "ust".to_string(),
+        max_age: Some(300),
+    };
+    let contract_result = execute(deps.as_mut(), env.clone(), admin_info, execute_msg);
+    assert_eq!(contract_result, Err(ContractError::AssetNotSupported {}));
+
+    // Only the admin can set maximum price ages
+    let execute_msg = ExecuteMsg::SetPriceMaxAge {
+        asset_name: "luna".to_string(),
+        max_age: None,
+    };
+    let contract_result = execute(deps.as_mut(), env, mock_info("user", &[]), execute_msg);
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+}
+
+#[test]
+fn test_valuation_and_liquidation() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_token_info("fbtc_cw20_address", token_info(6));
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    deps.querier
+        .set_oracle_price("fbtc", Decimal::from_ratio(100u32, 1u32));
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        });
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "luna".to_string(),
+        asset_info_unvalidated: luna_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info.clone(), execute_msg).unwrap();
+    let fbtc_asset_info = AssetInfoUnvalidated::FutureAsset {
+        asset_name: "fbtc".to_string(),
+        contract_addr: "fbtc_cw20_address".to_string(),
+        mint_authority: "fbtc_mint_authority".to_string(),
+        collateralizeable: false,
+        ratio: Default::default(),
+        underlying: NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: "wbtc".to_string(),
+            contract_addr: "wbtc_cw20_address".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        },
+        decimals: 6,
+        expiry: fbtc_expiry(),
+    };
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "fbtc".to_string(),
+        asset_info_unvalidated: fbtc_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info, execute_msg).unwrap();
+
+    // Deposit 1000 luna and borrow 400 fbtc
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[coin(1000, "luna")]),
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        ExecuteMsg::Borrow {
+            asset_name: "fbtc".to_string(),
+            amount: Uint128::from(400u128),
+        },
+    )
+    .unwrap();
+
+    let valuation = value_position(
+        deps.as_ref(),
+        &env.block,
+        &Addr::unchecked("user"),
+        PriceFreshness::Required,
+    )
+    .unwrap();
+    assert_eq!(valuation.collateral.len(), 1);
+    assert_eq!(valuation.borrow.len(), 1);
+    assert_eq!(
+        valuation.price("fbtc").unwrap(),
+        Decimal::from_ratio(100u32, 1u32)
+    );
+    let luna_value =
+        to_value(Uint128::from(1000u128), 6, Decimal::from_ratio(50u32, 1u32)).unwrap();
+    assert_eq!(
+        valuation.weighted_collateral_value,
+        luna_value
+            .checked_mul(Decimal::from_ratio(9u32, 10u32))
+            .unwrap()
+    );
+    assert_eq!(
+        valuation.borrow_value,
+        to_value(Uint128::from(400u128), 6, Decimal::from_ratio(100u32, 1u32)).unwrap()
+    );
+    assert!(valuation.is_healthy());
+
+    let liquidate_msg = |amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "liquidator".to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::Liquidate {
+                asset_name: "fbtc".to_string(),
+                borrower: "user".to_string(),
+                collateral_asset: "luna".to_string(),
+            })
+            .unwrap(),
+        })
+    };
+    let cw20_info = mock_info("fbtc_cw20_address", &[]);
+
+    // A healthy position can not be liquidated
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        cw20_info.clone(),
+        liquidate_msg(100),
+    );
+    assert_eq!(contract_result, Err(ContractError::PositionHealthy {}));
+
+    // Debt is now worth 400 * 120 = 48000 against weighted collateral of 45000
+    deps.querier
+        .set_oracle_price("fbtc", Decimal::from_ratio(120u32, 1u32));
+    assert!(!value_position(
+        deps.as_ref(),
+        &env.block,
+        &Addr::unchecked("user"),
+        PriceFreshness::Required
+    )
+    .unwrap()
+    .is_healthy());
+
+    // Repaying 100 fbtc worth 12000 seizes 12600 worth of luna with the 5% bonus, 252 luna
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        cw20_info.clone(),
+        liquidate_msg(100),
+    )
+    .unwrap();
+    assert_eq!(
+        response.messages,
+        [
+            fbtc_burn_msgs(100),
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "liquidator".to_string(),
+                amount: vec![coin(252, "luna")],
+            })]
+        ]
+        .concat()
+    );
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "fbtc".to_string()).unwrap();
+    assert_eq!(balance.borrow, Uint128::from(300u128));
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "luna".to_string()).unwrap();
+    assert_eq!(balance.collateral, Uint128::from(748u128));
+
+    // NOTE: mock storage is not reverted on errors, so the failing cases come last
+    // With the close factor of 50% at most 150 of the 300 fbtc left can be repaid at once
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        cw20_info.clone(),
+        liquidate_msg(200),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::LiquidationExceedsCloseFactor {})
+    );
+
+    // At a price of 300, repaying 150 fbtc would need 945 luna but only 748 are left
+    deps.querier
+        .set_oracle_price("fbtc", Decimal::from_ratio(300u32, 1u32));
+    let contract_result = execute(deps.as_mut(), env.clone(), cw20_info, liquidate_msg(150));
+    assert_eq!(
+        contract_result,
+        Err(ContractError::LiquidationExceedsCollateral {})
+    );
+
+    // The debt has to be repaid with the borrowed future token
+    let contract_result = execute(
+        deps.as_mut(),
+        env,
+        mock_info("someone_else_cw20_address", &[]),
+        liquidate_msg(100),
+    );
+    assert!(contract_result.is_err());
+}
+
+#[test]
+fn test_future_lifecycle() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_token_info("fbtc_cw20_address", token_info(6));
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    deps.querier
+        .set_oracle_price("fbtc", Decimal::from_ratio(100u32, 1u32));
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        });
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "luna".to_string(),
+        asset_info_unvalidated: luna_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info.clone(), execute_msg).unwrap();
+    let fbtc_asset_info = AssetInfoUnvalidated::FutureAsset {
+        asset_name: "fbtc".to_string(),
+        contract_addr: "fbtc_cw20_address".to_string(),
+        mint_authority: "fbtc_mint_authority".to_string(),
+        collateralizeable: false,
+        ratio: Default::default(),
+        underlying: NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: "wbtc".to_string(),
+            contract_addr: "wbtc_cw20_address".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        },
+        decimals: 6,
+        expiry: fbtc_expiry(),
+    };
+    let execute_msg = ExecuteMsg::AddSupportedAsset {
+        asset_name: "fbtc".to_string(),
+        asset_info_unvalidated: fbtc_asset_info,
+    };
+    execute(deps.as_mut(), env.clone(), admin_info, execute_msg).unwrap();
+
+    let env_at = |seconds: u64| {
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(seconds);
+        env
+    };
+    let state_at = |deps: Deps, seconds: u64| {
+        query_future_state(deps, env_at(seconds), "fbtc".to_string())
+            .unwrap()
+            .state
+    };
+    let borrow_msg = ExecuteMsg::Borrow {
+        asset_name: "fbtc".to_string(),
+        amount: Uint128::from(100u128),
+    };
+    let liquidate_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "liquidator".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Liquidate {
+            asset_name: "fbtc".to_string(),
+            borrower: "user".to_string(),
+            collateral_asset: "luna".to_string(),
+        })
+        .unwrap(),
+    });
+    let expiry = fbtc_expiry();
+
+    // Trading until the settlement window opens
+    assert_eq!(
+        query_future_state(deps.as_ref(), env.clone(), "fbtc".to_string()).unwrap(),
+        FutureStateResponse {
+            state: FutureState::Trading,
+            expiry,
+        }
+    );
+    assert_eq!(
+        state_at(deps.as_ref(), expiry - SETTLEMENT_WINDOW - 1),
+        FutureState::Trading
+    );
+    assert_eq!(
+        state_at(deps.as_ref(), expiry - SETTLEMENT_WINDOW),
+        FutureState::SettlementWindow
+    );
+    assert_eq!(
+        state_at(deps.as_ref(), expiry - 1),
+        FutureState::SettlementWindow
+    );
+    assert_eq!(state_at(deps.as_ref(), expiry), FutureState::Expired);
+    assert_eq!(
+        state_at(deps.as_ref(), expiry + FINALIZATION_DELAY - 1),
+        FutureState::Expired
+    );
+    assert_eq!(
+        state_at(deps.as_ref(), expiry + FINALIZATION_DELAY),
+        FutureState::Finalized
+    );
+
+    // Borrow while trading
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[coin(1000, "luna")]),
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env_at(expiry - SETTLEMENT_WINDOW - 1),
+        mock_info("user", &[]),
+        borrow_msg.clone(),
+    )
+    .unwrap();
+
+    // No new borrows once the settlement window opens
+    let contract_result = execute(
+        deps.as_mut(),
+        env_at(expiry - SETTLEMENT_WINDOW),
+        mock_info("user", &[]),
+        borrow_msg,
+    );
+    assert_eq!(contract_result, Err(ContractError::BorrowingClosed {}));
+
+    // The healthy position can not be liquidated before expiry
+    let contract_result = execute(
+        deps.as_mut(),
+        env_at(expiry - 1),
+        mock_info("fbtc_cw20_address", &[]),
+        liquidate_msg.clone(),
+    );
+    assert_eq!(contract_result, Err(ContractError::PositionHealthy {}));
+
+    // After expiry the unsettled debt can be liquidated in full at the settlement price, repaying
+    // 100 fbtc seizes 200 luna plus the 5% bonus
+    deps.querier
+        .set_oracle_price("wbtc", Decimal::from_ratio(100u32, 1u32));
+    execute(
+        deps.as_mut(),
+        env_at(expiry),
+        mock_info("anyone", &[]),
+        ExecuteMsg::FixSettlementPrice {
+            asset_name: "fbtc".to_string(),
+        },
+    )
+    .unwrap();
+    let response = execute(
+        deps.as_mut(),
+        env_at(expiry),
+        mock_info("fbtc_cw20_address", &[]),
+        liquidate_msg,
+    )
+    .unwrap();
+    assert_eq!(
+        response.messages,
+        [
+            fbtc_burn_msgs(100),
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "liquidator".to_string(),
+                amount: vec![coin(210, "luna")],
+            })]
+        ]
+        .concat()
+    );
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "fbtc".to_string()).unwrap();
+    assert_eq!(balance.borrow, Uint128::zero());
+
+    // Only futures have a lifecycle
+    assert!(query_future_state(deps.as_ref(), env, "luna".to_string()).is_err());
+}
+
+#[test]
+fn test_settlement_fixing() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_token_info("fbtc_cw20_address", token_info(6));
+    deps.querier
+        .set_token_info("feth_cw20_address", token_info(6));
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    deps.querier
+        .set_oracle_price("fbtc", Decimal::from_ratio(100u32, 1u32));
+    deps.querier
+        .set_oracle_price("wbtc", Decimal::from_ratio(110u32, 1u32));
+    deps.querier
+        .set_oracle_price("weth", Decimal::from_ratio(20u32, 1u32));
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        });
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::AddSupportedAsset {
+            asset_name: "luna".to_string(),
+            asset_info_unvalidated: luna_asset_info,
+        },
+    )
+    .unwrap();
+    let future_asset_info = |name: &str, underlying: &str| AssetInfoUnvalidated::FutureAsset {
+        asset_name: name.to_string(),
+        contract_addr: format!("{}_cw20_address", name),
+        mint_authority: format!("{}_mint_authority", name),
+        collateralizeable: false,
+        ratio: Default::default(),
+        underlying: NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: underlying.to_string(),
+            contract_addr: format!("{}_cw20_address", underlying),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        },
+        decimals: 6,
+        expiry: fbtc_expiry(),
+    };
+    for (name, underlying) in [("fbtc", "wbtc"), ("feth", "weth")].iter() {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            ExecuteMsg::AddSupportedAsset {
+                asset_name: name.to_string(),
+                asset_info_unvalidated: future_asset_info(name, underlying),
+            },
+        )
+        .unwrap();
+    }
+
+    // Borrow fbtc while trading
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[coin(1000, "luna")]),
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        ExecuteMsg::Borrow {
+            asset_name: "fbtc".to_string(),
+            amount: Uint128::from(100u128),
+        },
+    )
+    .unwrap();
+
+    let expiry = fbtc_expiry();
+    let mut expired_env = mock_env();
+    expired_env.block.time = Timestamp::from_seconds(expiry + 10);
+
+    // An expired position can not be valued before its debt is fixed
+    assert_eq!(
+        value_position(
+            deps.as_ref(),
+            &expired_env.block,
+            &Addr::unchecked("user"),
+            PriceFreshness::Required
+        ),
+        Err(ContractError::SettlementPriceNotFixed {})
+    );
+
+    // Without a TWAP of the underlying the spot price is fixed
+    let response = execute(
+        deps.as_mut(),
+        expired_env.clone(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::FixSettlementPrice {
+            asset_name: "fbtc".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        response.attributes,
+        vec![
+            attr("action", "fix_settlement_price"),
+            attr("asset_name", "fbtc"),
+            attr("price", "110"),
+            attr("twap", "false"),
+        ]
+    );
+    let fixing: Option<SettlementFixing> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::SettlementPrice {
+                asset_name: "fbtc".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        fixing,
+        Some(SettlementFixing {
+            price: Decimal::from_ratio(110u32, 1u32),
+            fixed_at: expiry + 10,
+            twap: false,
+        })
+    );
+
+    // The TWAP over the window before expiry is used when the oracle manager keeps a history
+    deps.querier.set_oracle_price_history(
+        "weth",
+        PriceHistoryConfig {
+            length: 100,
+            min_interval: 60,
+        },
+    );
+    deps.querier
+        .set_oracle_twap_price("weth", Decimal::from_ratio(18u32, 1u32));
+    execute(
+        deps.as_mut(),
+        expired_env.clone(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::FixSettlementPrice {
+            asset_name: "feth".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        SETTLEMENT_PRICES
+            .load(deps.as_ref().storage, "feth")
+            .unwrap(),
+        SettlementFixing {
+            price: Decimal::from_ratio(18u32, 1u32),
+            fixed_at: expiry + 10,
+            twap: true,
+        }
+    );
+
+    // After expiry the debt is valued at the fixing instead of the live price
+    let valuation = value_position(
+        deps.as_ref(),
+        &expired_env.block,
+        &Addr::unchecked("user"),
+        PriceFreshness::Required,
+    )
+    .unwrap();
+    assert_eq!(
+        valuation.borrow_value,
+        to_value(Uint128::from(100u128), 6, Decimal::from_ratio(110u32, 1u32)).unwrap()
+    );
+
+    // A fixing is final
+    let contract_result = execute(
+        deps.as_mut(),
+        expired_env,
+        mock_info("anyone", &[]),
+        ExecuteMsg::FixSettlementPrice {
+            asset_name: "fbtc".to_string(),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::SettlementPriceAlreadyFixed {})
+    );
+
+    // Only expired futures can be fixed
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::FixSettlementPrice {
+            asset_name: "feth".to_string(),
+        },
+    );
+    assert_eq!(contract_result, Err(ContractError::FutureNotExpired {}));
+    let contract_result = execute(
+        deps.as_mut(),
+        env,
+        mock_info("anyone", &[]),
+        ExecuteMsg::FixSettlementPrice {
+            asset_name: "luna".to_string(),
+        },
+    );
+    assert_eq!(contract_result, Err(ContractError::NotAFutureAsset {}));
+
+    // A failing TWAP does not fall back to the spot price when the underlying has a history
+    deps.querier
+        .set_token_info("fluna_cw20_address", token_info(6));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        admin_info.clone(),
+        ExecuteMsg::AddSupportedAsset {
+            asset_name: "fluna".to_string(),
+            asset_info_unvalidated: future_asset_info("fluna", "wluna"),
+        },
+    )
+    .unwrap();
+    deps.querier
+        .set_oracle_price("wluna", Decimal::from_ratio(50u32, 1u32));
+    deps.querier.set_oracle_price_history(
+        "wluna",
+        PriceHistoryConfig {
+            length: 100,
+            min_interval: 60,
+        },
+    );
+    let mut expired_env = mock_env();
+    expired_env.block.time = Timestamp::from_seconds(expiry + 10);
+    let contract_result = execute(
+        deps.as_mut(),
+        expired_env.clone(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::FixSettlementPrice {
+            asset_name: "fluna".to_string(),
+        },
+    );
+    assert!(contract_result.is_err());
+    assert!(!SETTLEMENT_PRICES.has(deps.as_ref().storage, "fluna"));
+
+    // After the fixing period only the admin can fix the price, and only then
+    let set_price_msg = ExecuteMsg::SetSettlementPrice {
+        asset_name: "fluna".to_string(),
+        price: Decimal::from_ratio(49u32, 1u32),
+    };
+    let contract_result = execute(
+        deps.as_mut(),
+        expired_env,
+        admin_info.clone(),
+        set_price_msg.clone(),
+    );
+    assert_eq!(contract_result, Err(ContractError::FixingPeriodNotOver {}));
+    let mut late_env = mock_env();
+    late_env.block.time = Timestamp::from_seconds(expiry + FIXING_PERIOD + 1);
+    let contract_result = execute(
+        deps.as_mut(),
+        late_env.clone(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::FixSettlementPrice {
+            asset_name: "fluna".to_string(),
+        },
+    );
+    assert_eq!(contract_result, Err(ContractError::FixingPeriodOver {}));
+    let contract_result = execute(
+        deps.as_mut(),
+        late_env.clone(),
+        mock_info("anyone", &[]),
+        set_price_msg.clone(),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+    execute(
+        deps.as_mut(),
+        late_env.clone(),
+        admin_info.clone(),
+        set_price_msg,
+    )
+    .unwrap();
+    assert_eq!(
+        SETTLEMENT_PRICES
+            .load(deps.as_ref().storage, "fluna")
+            .unwrap(),
+        SettlementFixing {
+            price: Decimal::from_ratio(49u32, 1u32),
+            fixed_at: expiry + FIXING_PERIOD + 1,
+            twap: false,
+        }
+    );
+
+    // Futures can not be added if the history of their underlying is too short for the fixing
+    deps.querier
+        .set_token_info("fatom_cw20_address", token_info(6));
+    deps.querier.set_oracle_price_history(
+        "watom",
+        PriceHistoryConfig {
+            length: 10,
+            min_interval: 60,
+        },
+    );
+    let contract_result = execute(
+        deps.as_mut(),
+        mock_env(),
+        admin_info,
+        ExecuteMsg::AddSupportedAsset {
+            asset_name: "fatom".to_string(),
+            asset_info_unvalidated: future_asset_info("fatom", "watom"),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::FixingWindowNotCovered {})
+    );
+}
+
+#[test]
+fn test_settlement_and_redemption() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_token_info("fbtc_cw20_address", token_info(6));
+    deps.querier
+        .set_token_info("wbtc_cw20_address", token_info(8));
+    deps.querier
+        .set_token_info("fluna_cw20_address", token_info(6));
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    deps.querier
+        .set_oracle_price("fbtc", Decimal::from_ratio(100u32, 1u32));
+    deps.querier
+        .set_oracle_price("wbtc", Decimal::from_ratio(100u32, 1u32));
+    deps.querier
+        .set_oracle_price("fluna", Decimal::from_ratio(50u32, 1u32));
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        });
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::AddSupportedAsset {
+            asset_name: "luna".to_string(),
+            asset_info_unvalidated: luna_asset_info,
+        },
+    )
+    .unwrap();
+    let wbtc_asset_info = NormalAssetInfoUnvalidated::CW20Token {
+        asset_name: "wbtc".to_string(),
+        contract_addr: "wbtc_cw20_address".to_string(),
+        collateralizeable: true,
+        ratio: Decimal::from_ratio(9u32, 10u32),
+        decimals: 8,
+    };
+    let fbtc_asset_info = AssetInfoUnvalidated::FutureAsset {
+        asset_name: "fbtc".to_string(),
+        contract_addr: "fbtc_cw20_address".to_string(),
+        mint_authority: "fbtc_mint_authority".to_string(),
+        collateralizeable: true,
+        ratio: Decimal::from_ratio(8u32, 10u32),
+        underlying: wbtc_asset_info,
+        decimals: 6,
+        expiry: fbtc_expiry(),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::AddSupportedAsset {
+            asset_name: "fbtc".to_string(),
+            asset_info_unvalidated: fbtc_asset_info,
+        },
+    )
+    .unwrap();
+    let fluna_asset_info = AssetInfoUnvalidated::FutureAsset {
+        asset_name: "fluna".to_string(),
+        contract_addr: "fluna_cw20_address".to_string(),
+        mint_authority: "fluna_mint_authority".to_string(),
+        collateralizeable: false,
+        ratio: Default::default(),
+        underlying: NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        },
+        decimals: 6,
+        expiry: fbtc_expiry(),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info,
+        ExecuteMsg::AddSupportedAsset {
+            asset_name: "fluna".to_string(),
+            asset_info_unvalidated: fluna_asset_info,
+        },
+    )
+    .unwrap();
+
+    let expiry = fbtc_expiry();
+    let env_at = |seconds: u64| {
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(seconds);
+        env
+    };
+    let settle_msg = |amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "user".to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::Settle {
+                asset_name: "fbtc".to_string(),
+            })
+            .unwrap(),
+        })
+    };
+    let redeem_msg = |amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "holder".to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::Redeem {
+                asset_name: "fbtc".to_string(),
+            })
+            .unwrap(),
+        })
+    };
+
+    // Borrow 100 fbtc and 100 fluna while trading
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[coin(1000, "luna")]),
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+    for asset_name in ["fbtc", "fluna"].iter() {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("user", &[]),
+            ExecuteMsg::Borrow {
+                asset_name: asset_name.to_string(),
+                amount: Uint128::from(100u128),
+            },
+        )
+        .unwrap();
+    }
+
+    // 50 of the fbtc end up as collateral of another position
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("fbtc_cw20_address", &[]),
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "depositor".to_string(),
+            amount: Uint128::from(50u128),
+            msg: to_binary(&Cw20HookMsg::Deposit {
+                asset_name: "fbtc".to_string(),
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    // Debt can not be settled while the future is trading
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("wbtc_cw20_address", &[]),
+        settle_msg(6000),
+    );
+    assert_eq!(contract_result, Err(ContractError::SettlementNotOpen {}));
+
+    // Once the settlement window opened, delivering 6000 wbtc base units settles 60 fbtc of debt
+    execute(
+        deps.as_mut(),
+        env_at(expiry - 1),
+        mock_info("wbtc_cw20_address", &[]),
+        settle_msg(6000),
+    )
+    .unwrap();
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "fbtc".to_string()).unwrap();
+    assert_eq!(balance.borrow, Uint128::from(40u128));
+    let pool: Uint128 = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::SettlementPool {
+                asset_name: "fbtc".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(pool, Uint128::from(6000u128));
+
+    // Tokens can not be redeemed before the future is finalized
+    let finalized_env = env_at(expiry + FINALIZATION_DELAY);
+    let contract_result = execute(
+        deps.as_mut(),
+        env_at(expiry),
+        mock_info("fbtc_cw20_address", &[]),
+        redeem_msg(30),
+    );
+    assert_eq!(contract_result, Err(ContractError::FutureNotFinalized {}));
+    execute(
+        deps.as_mut(),
+        env_at(expiry),
+        mock_info("anyone", &[]),
+        ExecuteMsg::FixSettlementPrice {
+            asset_name: "fbtc".to_string(),
+        },
+    )
+    .unwrap();
+
+    // 100 fbtc are outstanding but the pool only holds 60% of the wbtc owed for them, so
+    // redeeming 30 fbtc burns them through the mint authority and pays out 1800 instead of 3000
+    // wbtc base units. The fbtc held as collateral keep their claim on the pool
+    let response = execute(
+        deps.as_mut(),
+        finalized_env.clone(),
+        mock_info("fbtc_cw20_address", &[]),
+        redeem_msg(30),
+    )
+    .unwrap();
+    assert_eq!(
+        response.messages,
+        [
+            fbtc_burn_msgs(30),
+            vec![SubMsg::new(WasmMsg::Execute {
+                contract_addr: "wbtc_cw20_address".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "holder".to_string(),
+                    amount: Uint128::from(1800u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            })],
+        ]
+        .concat()
+    );
+    assert_eq!(
+        SETTLEMENT_POOLS
+            .load(deps.as_ref().storage, "fbtc")
+            .unwrap(),
+        Uint128::from(4200u128)
+    );
+    assert_eq!(
+        FUTURE_SUPPLY.load(deps.as_ref().storage, "fbtc").unwrap(),
+        Uint128::from(70u128)
+    );
+
+    // Once the rest of the debt is settled the pool covers every outstanding token, so
+    // redemptions are paid in full
+    execute(
+        deps.as_mut(),
+        finalized_env.clone(),
+        mock_info("wbtc_cw20_address", &[]),
+        settle_msg(4000),
+    )
+    .unwrap();
+    let response = execute(
+        deps.as_mut(),
+        finalized_env.clone(),
+        mock_info("fbtc_cw20_address", &[]),
+        redeem_msg(40),
+    )
+    .unwrap();
+    assert!(response
+        .attributes
+        .contains(&attr("payout", Uint128::from(4000u128))));
+    assert_eq!(
+        SETTLEMENT_POOLS
+            .load(deps.as_ref().storage, "fbtc")
+            .unwrap(),
+        Uint128::from(4200u128)
+    );
+
+    // Futures with a native underlying are settled by sending it
+    execute(
+        deps.as_mut(),
+        finalized_env.clone(),
+        mock_info("user", &[coin(60, "luna")]),
+        ExecuteMsg::NativeSettle {
+            asset_name: "fluna".to_string(),
+        },
+    )
+    .unwrap();
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "fluna".to_string()).unwrap();
+    assert_eq!(balance.borrow, Uint128::from(40u128));
+    assert_eq!(
+        SETTLEMENT_POOLS
+            .load(deps.as_ref().storage, "fluna")
+            .unwrap(),
+        Uint128::from(60u128)
+    );
+
+    // NOTE: mock storage is not reverted on errors, so the failing cases come last
+    // Native settlements have to send only the underlying, and only futures with a native underlying
+    let contract_result = execute(
+        deps.as_mut(),
+        finalized_env.clone(),
+        mock_info("user", &[coin(60, "uusd")]),
+        ExecuteMsg::NativeSettle {
+            asset_name: "fluna".to_string(),
+        },
+    );
+    assert!(contract_result.is_err());
+    let contract_result = execute(
+        deps.as_mut(),
+        finalized_env.clone(),
+        mock_info("user", &[coin(60, "luna"), coin(60, "uusd")]),
+        ExecuteMsg::NativeSettle {
+            asset_name: "fluna".to_string(),
+        },
+    );
+    assert!(contract_result.is_err());
+    let contract_result = execute(
+        deps.as_mut(),
+        finalized_env.clone(),
+        mock_info("user", &[coin(60, "luna")]),
+        ExecuteMsg::NativeSettle {
+            asset_name: "fbtc".to_string(),
+        },
+    );
+    assert!(contract_result.is_err());
+
+    // Only the token of the future can be redeemed
+    let contract_result = execute(
+        deps.as_mut(),
+        finalized_env.clone(),
+        mock_info("wbtc_cw20_address", &[]),
+        redeem_msg(40),
+    );
+    assert!(contract_result.is_err());
+
+    // At most the outstanding supply can be redeemed
+    let contract_result = execute(
+        deps.as_mut(),
+        finalized_env,
+        mock_info("fbtc_cw20_address", &[]),
+        redeem_msg(31),
+    );
+    assert_eq!(contract_result, Err(ContractError::RedeemExceedsSupply {}));
+}
+
+#[test]
+fn test_roll() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    deps.querier
+        .set_token_info("fbtc_cw20_address", token_info(6));
+    deps.querier
+        .set_token_info("fbtc2_cw20_address", token_info(6));
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(50u32, 1u32));
+    deps.querier
+        .set_oracle_price("fbtc", Decimal::from_ratio(100u32, 1u32));
+    deps.querier
+        .set_oracle_price("fbtc2", Decimal::from_ratio(125u32, 1u32));
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let luna_asset_info =
+        AssetInfoUnvalidated::NormalAsset(NormalAssetInfoUnvalidated::NativeToken {
+            denom: "luna".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        });
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::AddSupportedAsset {
+            asset_name: "luna".to_string(),
+            asset_info_unvalidated: luna_asset_info,
+        },
+    )
+    .unwrap();
+    let expiry = fbtc_expiry();
+    let future_asset_info = |name: &str, expiry: u64| AssetInfoUnvalidated::FutureAsset {
+        asset_name: name.to_string(),
+        contract_addr: format!("{}_cw20_address", name),
+        mint_authority: format!("{}_mint_authority", name),
+        collateralizeable: false,
+        ratio: Default::default(),
+        underlying: NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: "wbtc".to_string(),
+            contract_addr: "wbtc_cw20_address".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 6,
+        },
+        decimals: 6,
+        expiry,
+    };
+    for (name, expiry) in [("fbtc", expiry), ("fbtc2", expiry + 30 * 24 * 60 * 60)].iter() {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            ExecuteMsg::AddSupportedAsset {
+                asset_name: name.to_string(),
+                asset_info_unvalidated: future_asset_info(name, *expiry),
+            },
+        )
+        .unwrap();
+    }
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[coin(1000, "luna")]),
+        ExecuteMsg::NativeDeposit {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        ExecuteMsg::Borrow {
+            asset_name: "fbtc".to_string(),
+            amount: Uint128::from(200u128),
+        },
+    )
+    .unwrap();
+    let roll_msg = |from_asset: &str, to_asset: &str| ExecuteMsg::Roll {
+        from_asset: from_asset.to_string(),
+        to_asset: to_asset.to_string(),
+        amount: Uint128::from(100u128),
+    };
+
+    // Rolling 100 fbtc worth 100 each into fbtc2 worth 125 each opens 80 fbtc2 of debt
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        roll_msg("fbtc", "fbtc2"),
+    )
+    .unwrap();
+    assert_eq!(
+        response.messages,
+        [
+            vec![SubMsg::new(WasmMsg::Execute {
+                contract_addr: "fbtc_cw20_address".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                    owner: "user".to_string(),
+                    recipient: MOCK_CONTRACT_ADDR.to_string(),
+                    amount: Uint128::from(100u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            })],
+            fbtc_burn_msgs(100),
+            vec![SubMsg::new(WasmMsg::Execute {
+                contract_addr: "fbtc2_mint_authority".to_string(),
+                msg: to_binary(&MintAuthorityExecuteMsg::MintAndSend {
+                    recipient: "user".to_string(),
+                    amount: Uint128::from(80u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            })],
+        ]
+        .concat()
+    );
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "fbtc".to_string()).unwrap();
+    assert_eq!(balance.borrow, Uint128::from(100u128));
+    let balance = query_balance(deps.as_ref(), "user".to_string(), "fbtc2".to_string()).unwrap();
+    assert_eq!(balance.borrow, Uint128::from(80u128));
+
+    // Debt can only be rolled into a later series of a future
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        roll_msg("fbtc2", "fbtc"),
+    );
+    assert_eq!(contract_result, Err(ContractError::InvalidRollTarget {}));
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        roll_msg("fbtc", "luna"),
+    );
+    assert_eq!(contract_result, Err(ContractError::NotAFutureAsset {}));
+
+    // Debt can not be rolled once the future expired
+    let mut expired_env = mock_env();
+    expired_env.block.time = Timestamp::from_seconds(expiry);
+    let contract_result = execute(
+        deps.as_mut(),
+        expired_env,
+        mock_info("user", &[]),
+        roll_msg("fbtc", "fbtc2"),
+    );
+    assert_eq!(contract_result, Err(ContractError::RollAfterExpiry {}));
+
+    // The position has to be healthy after the roll
+    deps.querier
+        .set_oracle_price("luna", Decimal::from_ratio(10u32, 1u32));
+    let contract_result = execute(
+        deps.as_mut(),
+        env,
+        mock_info("user", &[]),
+        roll_msg("fbtc", "fbtc2"),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::NotEnoughTotalCollateral {})
+    );
+}
+
+#[test]
+fn test_term_structure() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    let env = mock_env();
+    let admin_info = mock_info("Admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    // A fifth and two fifths of a year from now
+    let now = env.block.time.seconds();
+    let short_expiry = now + 73 * 24 * 60 * 60;
+    let long_expiry = now + 146 * 24 * 60 * 60;
+    let futures = [
+        ("fbtc_long", "wbtc", long_expiry, 98u32),
+        ("fbtc_short", "wbtc", short_expiry, 102u32),
+        ("feth", "weth", short_expiry, 30u32),
+    ];
+    for (name, underlying, expiry, price) in futures.iter() {
+        deps.querier
+            .set_token_info(&format!("{}_cw20_address", name), token_info(6));
+        deps.querier
+            .set_oracle_price(name, Decimal::from_ratio(*price, 1u32));
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            ExecuteMsg::AddSupportedAsset {
+                asset_name: name.to_string(),
+                asset_info_unvalidated: AssetInfoUnvalidated::FutureAsset {
+                    asset_name: name.to_string(),
+                    contract_addr: format!("{}_cw20_address", name),
+                    mint_authority: format!("{}_mint_authority", name),
+                    collateralizeable: false,
+                    ratio: Default::default(),
+                    underlying: NormalAssetInfoUnvalidated::CW20Token {
+                        asset_name: underlying.to_string(),
+                        contract_addr: format!("{}_cw20_address", underlying),
+                        collateralizeable: true,
+                        ratio: Decimal::from_ratio(9u32, 10u32),
+                        decimals: 6,
+                    },
+                    decimals: 6,
+                    expiry: *expiry,
+                },
+            },
+        )
+        .unwrap();
+    }
+    deps.querier
+        .set_oracle_price("wbtc", Decimal::from_ratio(100u32, 1u32));
+
+    // A 2% premium over a fifth of a year is 10% a year, a 2% discount over two fifths is -5%
+    let term_structure: TermStructureResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::TermStructure {
+                underlying: "wbtc".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        term_structure,
+        TermStructureResponse {
+            underlying: "wbtc".to_string(),
+            spot_price: Decimal::from_ratio(100u32, 1u32),
+            points: vec![
+                TermStructurePoint {
+                    asset_name: "fbtc_short".to_string(),
+                    expiry: short_expiry,
+                    price: Decimal::from_ratio(102u32, 1u32),
+                    implied_rate: Decimal::percent(10),
+                    negative: false,
+                },
+                TermStructurePoint {
+                    asset_name: "fbtc_long".to_string(),
+                    expiry: long_expiry,
+                    price: Decimal::from_ratio(98u32, 1u32),
+                    implied_rate: Decimal::percent(5),
+                    negative: true,
+                },
+            ],
+        }
+    );
+
+    // Expired series are no longer part of the curve
+    let mut later_env = mock_env();
+    later_env.block.time = Timestamp::from_seconds(short_expiry);
+    let term_structure =
+        query_term_structure(deps.as_ref(), later_env, "wbtc".to_string()).unwrap();
+    assert_eq!(term_structure.points.len(), 1);
+    assert_eq!(term_structure.points[0].implied_rate, Decimal::percent(10));
+    assert!(term_structure.points[0].negative);
+
+    // The spot price of the underlying is needed
+    let contract_result = query_term_structure(deps.as_ref(), mock_env(), "weth".to_string());
+    assert!(contract_result.is_err());
 }
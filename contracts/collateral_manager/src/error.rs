@@ -1,5 +1,5 @@
 use cosmwasm_std::{StdError, Uint128};
-pub use cw_controllers::AdminError;
+pub use cw_controllers::{AdminError, HookError};
 use std::str::Utf8Error;
 use thiserror::Error;
 
@@ -11,6 +11,9 @@ pub enum ContractError {
     #[error("{0}")]
     ControllerError(#[from] AdminError),
 
+    #[error("{0}")]
+    HookError(#[from] HookError),
+
     #[error("{0}")]
     UTF8Error(#[from] Utf8Error),
 
@@ -38,6 +41,99 @@ pub enum ContractError {
         withdraw_amount: Uint128,
     },
 
-    #[error("You do not have anough collateral to withdraw this asset")]
+    #[error("You do not have enough collateral to withdraw or borrow this asset")]
     NotEnoughTotalCollateral {},
+
+    #[error("Invalid zero amount")]
+    InvalidZeroAmount {},
+
+    #[error("Only future assets can be borrowed")]
+    AssetNotBorrowable {},
+
+    #[error("Withdraw your collateral in this asset before borrowing it")]
+    CollateralInBorrowedAsset {},
+
+    #[error("Repay amount is larger than the borrowed amount")]
+    RepayExceedsDebt {},
+
+    #[error("Native funds sent do not match the deposits of the batch")]
+    BatchFundsMismatch {},
+
+    #[error("No withdrawals of this asset are ready to be claimed")]
+    NothingToClaim {},
+
+    #[error("Asset is still held for users, owed by borrowers or redeemable from its settlement")]
+    AssetInUse {},
+
+    #[error("At most {max} hooks can be registered")]
+    TooManyHooks { max: usize },
+
+    #[error("Borrowing is closed once the settlement window of the future opened")]
+    BorrowingClosed {},
+
+    #[error("Asset is not a future")]
+    NotAFutureAsset {},
+
+    #[error("Future has not expired yet")]
+    FutureNotExpired {},
+
+    #[error("Debt can only be rolled into a later series of the same underlying")]
+    InvalidRollTarget {},
+
+    #[error("Debt can only be rolled before the future expires")]
+    RollAfterExpiry {},
+
+    #[error("Future has not been finalized yet")]
+    FutureNotFinalized {},
+
+    #[error("Debt can only be settled once the settlement window of the future opened")]
+    SettlementNotOpen {},
+
+    #[error("Nothing is left in the settlement pool of this future")]
+    SettlementPoolEmpty {},
+
+    #[error("Redeem amount is larger than the outstanding supply of the future")]
+    RedeemExceedsSupply {},
+
+    #[error("Settlement price of this future was already fixed")]
+    SettlementPriceAlreadyFixed {},
+
+    #[error("Fixing period of this future is over, only the admin can fix its settlement price")]
+    FixingPeriodOver {},
+
+    #[error("Fixing period of this future is not over yet")]
+    FixingPeriodNotOver {},
+
+    #[error("Price history of the underlying does not cover the fixing TWAP window and period")]
+    FixingWindowNotCovered {},
+
+    #[error("Settlement price of an expired future in the position has not been fixed yet")]
+    SettlementPriceNotFixed {},
+
+    #[error("Position is healthy and can not be liquidated")]
+    PositionHealthy {},
+
+    #[error("{contract_addr} is not a cw20 token contract")]
+    NotACw20Token { contract_addr: String },
+
+    #[error("Asset has {expected} decimals but its cw20 token has {actual}")]
+    DecimalsMismatch { expected: u8, actual: u8 },
+
+    #[error("Position does not hold enough collateral to cover the liquidated debt")]
+    LiquidationExceedsCollateral {},
+
+    #[error("A liquidation can repay at most the close factor of the debt")]
+    LiquidationExceedsCloseFactor {},
+
+    #[error("Close factor has to be above zero and at most one, the liquidation bonus below one")]
+    InvalidLiquidationParams {},
+
+    #[error("Price of {asset_name} is older than its maximum age")]
+    StalePrice { asset_name: String },
+
+    #[error("Quote asset {quote_asset} does not match {oracle_quote_asset} of the oracle manager")]
+    QuoteAssetMismatch {
+        quote_asset: String,
+        oracle_quote_asset: String,
+    },
 }
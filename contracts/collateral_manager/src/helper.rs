@@ -1,66 +1,257 @@
-use std::str;
-
-use cosmwasm_std::{Addr, DepsMut, Order, Pair, StdResult, Uint128};
+use cosmwasm_std::{
+    to_binary, Addr, BankMsg, BlockInfo, Coin, CosmosMsg, Deps, StdError, StdResult, Storage,
+    SubMsg, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use cw_storage_plus::Map;
+use mint_authority::msg::ExecuteMsg as MintAuthorityExecuteMsg;
 
 use crate::error::ContractError;
-use crate::state::{BORROW, COLLATERAL, CONFIG, SUPPORTED_ASSETS};
-use steadifi::asset::AssetInfo;
-use steadifi::mars_protocol_math::Decimal;
-use steadifi::oracle_manager::get_oracle_price;
-
-///Returns Ok(true) if address has enough collateral to withdraw amount value of the given asset
-/// and Ok(false) otherwise
-pub fn can_withdraw(
-    deps: &DepsMut,
-    withdrawer_address: &Addr,
-    withdraw_asset: AssetInfo,
-    withdraw_amount: Uint128,
-) -> Result<bool, ContractError> {
-    let oracle_manager_addr = CONFIG.load(deps.storage)?.oracle_manager_addr;
-
-    // Compute total collateral denominated in USD
-    let mut total_collateral_value = Decimal::zero();
-    let all_collateral: StdResult<Vec<Pair<Uint128>>> = COLLATERAL
-        .prefix(withdrawer_address)
-        .range(deps.storage, None, None, Order::Ascending)
-        .collect();
-    for (asset_name_bytes, amount) in all_collateral? {
-        let asset_name: &str = str::from_utf8(&asset_name_bytes)?;
-        let asset_info = SUPPORTED_ASSETS.load(deps.storage, asset_name)?;
-        let ratio = asset_info.get_ratio();
-        let price = get_oracle_price(deps.querier, &oracle_manager_addr, asset_name)?;
-
-        total_collateral_value = total_collateral_value
-            + Decimal::from_ratio(amount, asset_info.get_decimals())
-                .checked_mul(price)?
-                .checked_mul(ratio)?;
+use crate::state::{BORROW, COLLATERAL, CONFIG, FUTURE_SUPPLY, HOOKS, TOTAL_BORROWED, TOTAL_HELD};
+use crate::valuation::{value_position, PriceFreshness};
+use steadifi::asset::{AssetInfo, NormalAssetInfo};
+use steadifi::collateral_manager::hook::{PositionAction, PositionChangedHookMsg};
+use steadifi::collateral_manager::msg::FutureState;
+
+///Returns an error if the position of address is not healthy. Called after borrows and withdrawals,
+/// so prices older than their maximum age are rejected
+pub fn assert_healthy(deps: Deps, block: &BlockInfo, address: &Addr) -> Result<(), ContractError> {
+    if !value_position(deps, block, address, PriceFreshness::Required)?.is_healthy() {
+        return Err(ContractError::NotEnoughTotalCollateral {});
+    }
+    Ok(())
+}
+
+///Adds amount to the total of asset_name the contract holds for users
+pub fn increase_total_held(
+    storage: &mut dyn Storage,
+    asset_name: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    increase_total(&TOTAL_HELD, storage, asset_name, amount)
+}
+
+///Removes amount from the total of asset_name the contract holds for users, once it is paid out
+pub fn decrease_total_held(
+    storage: &mut dyn Storage,
+    asset_name: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    decrease_total(&TOTAL_HELD, storage, asset_name, amount)
+}
+
+///Adds amount to the total debt of borrowers in the future asset_name
+pub fn increase_total_borrowed(
+    storage: &mut dyn Storage,
+    asset_name: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    increase_total(&TOTAL_BORROWED, storage, asset_name, amount)
+}
+
+///Removes amount from the total debt of borrowers in the future asset_name, once it is repaid
+pub fn decrease_total_borrowed(
+    storage: &mut dyn Storage,
+    asset_name: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    decrease_total(&TOTAL_BORROWED, storage, asset_name, amount)
+}
+
+fn increase_total(
+    totals: &Map<&str, Uint128>,
+    storage: &mut dyn Storage,
+    asset_name: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    totals.update(storage, asset_name, |total| -> StdResult<_> {
+        Ok(total.unwrap_or_default().checked_add(amount)?)
+    })?;
+    Ok(())
+}
+
+/// Totals that reach zero are removed, so a present entry means the asset is still in use
+fn decrease_total(
+    totals: &Map<&str, Uint128>,
+    storage: &mut dyn Storage,
+    asset_name: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    let total = totals
+        .may_load(storage, asset_name)?
+        .unwrap_or_default()
+        .checked_sub(amount)?;
+    if total.is_zero() {
+        totals.remove(storage, asset_name);
+    } else {
+        totals.save(storage, asset_name, &total)?;
     }
+    Ok(())
+}
 
-    // Compute total debt denominated in USD
-    let mut total_borrow_value = Decimal::zero();
-    let all_borrows: StdResult<Vec<Pair<Uint128>>> = BORROW
-        .prefix(withdrawer_address)
-        .range(deps.storage, None, None, Order::Ascending)
-        .collect();
-    for (asset_name_bytes, amount) in all_borrows? {
-        let asset_name: &str = str::from_utf8(&asset_name_bytes)?;
-        let asset_info = SUPPORTED_ASSETS.load(deps.storage, asset_name)?;
-        let price = get_oracle_price(deps.querier, &oracle_manager_addr, asset_name)?;
-
-        total_borrow_value = total_borrow_value
-            + Decimal::from_ratio(amount, asset_info.get_decimals()).checked_mul(price)?;
+///Returns the lifecycle state of asset_info at the time of block, None if it is not a future
+pub fn future_state(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    asset_info: &AssetInfo,
+) -> StdResult<Option<FutureState>> {
+    match asset_info {
+        AssetInfo::FutureAsset { expiry, .. } => {
+            let config = CONFIG.load(storage)?;
+            Ok(Some(FutureState::at(
+                *expiry,
+                config.settlement_window,
+                config.finalization_delay,
+                block.time.seconds(),
+            )))
+        }
+        AssetInfo::NormalAsset(..) => Ok(None),
     }
+}
+
+///Returns the message that sends amount of the given asset from this contract to recipient
+pub fn transfer_asset_msg(
+    asset_info: &AssetInfo,
+    recipient: &Addr,
+    amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    match asset_info {
+        AssetInfo::NormalAsset(NormalAssetInfo::NativeToken { denom, .. }) => {
+            Ok(CosmosMsg::Bank(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![Coin {
+                    denom: denom.clone(),
+                    amount,
+                }],
+            }))
+        }
+        AssetInfo::NormalAsset(NormalAssetInfo::CW20Token { contract_addr, .. })
+        | AssetInfo::FutureAsset { contract_addr, .. } => Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+        }
+        .into_cosmos_msg(contract_addr),
+    }
+}
+
+///Returns the message that moves amount of the given cw20 asset from owner to recipient.
+/// The owner has to give this contract an allowance first
+pub fn transfer_from_asset_msg(
+    asset_info: &AssetInfo,
+    owner: &Addr,
+    recipient: &Addr,
+    amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    match asset_info {
+        AssetInfo::NormalAsset(NormalAssetInfo::NativeToken { denom, .. }) => Err(
+            StdError::generic_err(format!("{} corresponds to a native token", denom)),
+        ),
+        AssetInfo::NormalAsset(NormalAssetInfo::CW20Token { contract_addr, .. })
+        | AssetInfo::FutureAsset { contract_addr, .. } => Cw20ExecuteMsg::TransferFrom {
+            owner: owner.to_string(),
+            recipient: recipient.to_string(),
+            amount,
+        }
+        .into_cosmos_msg(contract_addr),
+    }
+}
+
+///Adds amount to the supply of the future asset_name, once it is minted to a borrower
+pub fn increase_future_supply(
+    storage: &mut dyn Storage,
+    asset_name: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    FUTURE_SUPPLY.update(storage, asset_name, |supply| -> StdResult<_> {
+        Ok(supply.unwrap_or_default().checked_add(amount)?)
+    })?;
+    Ok(())
+}
+
+///Returns the messages that burn amount of the future asset_info held by this contract and removes
+/// it from the supply of the future. The burn goes through the mint authority of the future, so it
+/// has to be allowed to burn the tokens first
+pub fn burn_future_msgs(
+    storage: &mut dyn Storage,
+    asset_info: &AssetInfo,
+    contract_addr: &Addr,
+    amount: Uint128,
+) -> StdResult<Vec<CosmosMsg>> {
+    match asset_info {
+        AssetInfo::FutureAsset {
+            asset_name,
+            contract_addr: token_addr,
+            mint_authority,
+            ..
+        } => {
+            let supply = FUTURE_SUPPLY
+                .may_load(storage, asset_name)?
+                .unwrap_or_default()
+                .checked_sub(amount)?;
+            if supply.is_zero() {
+                FUTURE_SUPPLY.remove(storage, asset_name);
+            } else {
+                FUTURE_SUPPLY.save(storage, asset_name, &supply)?;
+            }
+            Ok(vec![
+                Cw20ExecuteMsg::IncreaseAllowance {
+                    spender: mint_authority.to_string(),
+                    amount,
+                    expires: None,
+                }
+                .into_cosmos_msg(token_addr)?,
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: mint_authority.to_string(),
+                    msg: to_binary(&MintAuthorityExecuteMsg::BurnFrom {
+                        owner: contract_addr.to_string(),
+                        amount,
+                    })?,
+                    funds: vec![],
+                }),
+            ])
+        }
+        AssetInfo::NormalAsset(..) => Err(StdError::generic_err(format!(
+            "{} is not a future asset",
+            asset_info.get_name()
+        ))),
+    }
+}
+
+/// Reply id of the submessages sent to hook contracts
+pub const HOOK_REPLY_ID: u64 = 1;
+
+/// Gas every hook contract gets to handle a notification, so a hook can not use up the gas of the
+/// position change that notified it
+pub const HOOK_GAS_LIMIT: u64 = 300_000;
+
+/// Maximum number of hooks, every position change sends a submessage to each of them
+pub const MAX_HOOKS: usize = 10;
 
-    //Add withdrawn amount to total debt
-    let price_withdraw_asset = get_oracle_price(
-        deps.querier,
-        &oracle_manager_addr,
-        &withdraw_asset.get_name(),
-    )?;
-    total_borrow_value = total_borrow_value
-        + Decimal::from_ratio(withdraw_amount, withdraw_asset.get_decimals())
-            .checked_mul(price_withdraw_asset)?;
-
-    // Return true if collateral value is greater or equal to borrow value
-    return Ok(total_collateral_value >= total_borrow_value);
+///Returns the submessages notifying every registered hook that the position of address in
+/// asset_name changed by delta. The balances in the message are read after the change was stored
+pub fn position_changed_hooks(
+    storage: &dyn Storage,
+    action: PositionAction,
+    address: &Addr,
+    asset_name: &str,
+    delta: Uint128,
+) -> StdResult<Vec<SubMsg>> {
+    let collateral = COLLATERAL
+        .may_load(storage, (address, asset_name))?
+        .unwrap_or_default();
+    let borrow = BORROW
+        .may_load(storage, (address, asset_name))?
+        .unwrap_or_default();
+    HOOKS.prepare_hooks(storage, |hook_addr| {
+        let msg = PositionChangedHookMsg {
+            action: action.clone(),
+            address: address.to_string(),
+            asset_name: asset_name.to_string(),
+            delta,
+            collateral,
+            borrow,
+        }
+        .into_cosmos_msg(hook_addr)?;
+        Ok(SubMsg::reply_on_error(msg, HOOK_REPLY_ID).with_gas_limit(HOOK_GAS_LIMIT))
+    })
 }
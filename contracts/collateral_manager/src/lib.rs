@@ -3,3 +3,4 @@ pub mod error;
 pub mod state;
 
 mod helper;
+mod valuation;
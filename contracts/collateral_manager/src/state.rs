@@ -1,9 +1,12 @@
 use cosmwasm_std::{Addr, Decimal, Uint128};
-use cw_controllers::Admin;
+use cw0::Duration;
+use cw_controllers::{Admin, Claims, Hooks};
 use cw_storage_plus::{Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use steadifi::asset::AssetInfo;
+use steadifi::collateral_manager::msg::SettlementFixing;
+use steadifi::mars_protocol_math::Decimal as PriceDecimal;
 
 // Maps string containing name of asset to the AssetInfo struct which contains its information
 pub const SUPPORTED_ASSETS: Map<&str, AssetInfo> = Map::new("supported_assets");
@@ -15,15 +18,67 @@ pub const COLLATERAL: Map<(&Addr, &str), Uint128> = Map::new("collateral");
 // Only future assets can be borrowed
 pub const BORROW: Map<(&Addr, &str), Uint128> = Map::new("borrow");
 
+// Maps string containing name of asset to the cooldown withdrawals of that asset have to wait
+// before they are paid out. Assets without a cooldown are paid out immediately
+pub const WITHDRAW_COOLDOWN: Map<&str, Duration> = Map::new("withdraw_cooldown");
+
+// Maps string containing name of asset to the maximum age in seconds of its prices when they decide
+// whether a position stays healthy. Prices of assets without a maximum age are used at any age
+pub const PRICE_MAX_AGE: Map<&str, u64> = Map::new("price_max_age");
+
+// Maps a user address and string containing name of asset to withdrawals waiting for their cooldown
+// Claimed amounts are no longer collateral but stay locked in the contract until released
+pub const CLAIMS: Claims<(&Addr, &str)> = Claims::new("claims");
+
+// Maps string containing name of asset to how much of it the contract holds for users, as collateral
+// or in withdrawals waiting for their cooldown. Supported assets can only be removed while it is zero
+pub const TOTAL_HELD: Map<&str, Uint128> = Map::new("total_held");
+
+// Maps string containing name of a future to how much of it borrowers owe in total
+pub const TOTAL_BORROWED: Map<&str, Uint128> = Map::new("total_borrowed");
+
 // Admin of contract can add or remove supported assets
 // Eventually the admin will be the governance contract
 pub const ADMIN: Admin = Admin::new("admin");
 
+// Future factory that can add the futures it deploys as supported assets, next to the admin
+pub const FUTURE_FACTORY: Item<Addr> = Item::new("future_factory");
+
+// Contracts that are notified of every deposit, withdrawal, borrow, repayment and liquidation
+// Only the admin can add or remove hooks
+pub const HOOKS: Hooks = Hooks::new("hooks");
+
 //Contract config
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct Config {
     pub max_ltv: Decimal,
     pub oracle_manager_addr: Addr,
+    // Asset positions are valued in. Has to match the quote asset of the oracle manager
+    pub quote_asset: String,
+    // Seconds before expiry at which no new borrows of a future are allowed
+    pub settlement_window: u64,
+    // Seconds after expiry at which a future is finalized
+    pub finalization_delay: u64,
+    // Length in seconds of the TWAP before expiry used for settlement prices, zero or an underlying
+    // without a price history in the oracle manager uses the spot price
+    pub fixing_twap_window: u64,
+    // Seconds after expiry in which anyone can fix the settlement price, afterwards only the admin
+    pub fixing_period: u64,
+    // Share of the repaid value liquidators receive on top in collateral
+    pub liquidation_bonus: PriceDecimal,
+    // Largest share of a debt a single liquidation can repay, unless the future expired
+    pub close_factor: PriceDecimal,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
+
+// Maps the name of an expired future to its settlement price. Entries are never changed or removed
+pub const SETTLEMENT_PRICES: Map<&str, SettlementFixing> = Map::new("settlement_prices");
+
+// Maps the name of a future to the amount of its underlying delivered by borrowers that settled
+// their debt. Redemptions of the future are paid out of it
+pub const SETTLEMENT_POOLS: Map<&str, Uint128> = Map::new("settlement_pools");
+
+// Maps the name of a future to how much of it was minted to borrowers and not burned yet. Every
+// token of it has the same claim on the settlement pool, wherever it is held
+pub const FUTURE_SUPPLY: Map<&str, Uint128> = Map::new("future_supply");
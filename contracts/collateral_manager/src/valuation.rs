@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::str;
+
+use cosmwasm_std::{Addr, BlockInfo, Deps, Order, StdError, StdResult, Storage, Uint128};
+use cw_storage_plus::Map;
+
+use crate::error::ContractError;
+use crate::helper::future_state;
+use crate::state::{
+    BORROW, COLLATERAL, CONFIG, PRICE_MAX_AGE, SETTLEMENT_PRICES, SUPPORTED_ASSETS,
+};
+use steadifi::asset::{decimals_to_unit, AssetInfo};
+use steadifi::collateral_manager::msg::FutureState;
+use steadifi::mars_protocol_math::Decimal;
+use steadifi::oracle_manager::msg::PriceResponse;
+use steadifi::oracle_manager::{get_oracle_price, get_oracle_prices};
+
+/// Whether prices older than the maximum age of their asset may be used. Borrows and withdrawals,
+/// and everything else that can leave a position unhealthy, need fresh prices. Repayments only
+/// reduce risk and may use stale ones
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PriceFreshness {
+    Required,
+    StaleAllowed,
+}
+
+/// Value of a single collateral or debt entry of a position, denominated in the quote asset
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetValuation {
+    pub asset_name: String,
+    pub asset_info: AssetInfo,
+    pub amount: Uint128,
+    pub price: Decimal,
+    pub value: Decimal,
+}
+
+/// Snapshot of a position valued with one set of oracle prices
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionValuation {
+    pub collateral: Vec<AssetValuation>,
+    pub borrow: Vec<AssetValuation>,
+    /// Sum of the collateral values, each weighted by the ratio of its asset
+    pub weighted_collateral_value: Decimal,
+    pub borrow_value: Decimal,
+    prices: HashMap<String, Decimal>,
+}
+
+impl PositionValuation {
+    ///Returns true if the weighted collateral is worth at least as much as the debt
+    pub fn is_healthy(&self) -> bool {
+        self.weighted_collateral_value >= self.borrow_value
+    }
+
+    ///Returns the price used for asset_name when the position was valued
+    pub fn price(&self, asset_name: &str) -> StdResult<Decimal> {
+        self.prices.get(asset_name).copied().ok_or_else(|| {
+            StdError::generic_err(format!("{} is not part of the position", asset_name))
+        })
+    }
+
+    ///Returns the collateral entry of asset_name, if the position holds any
+    pub fn collateral(&self, asset_name: &str) -> Option<&AssetValuation> {
+        self.collateral
+            .iter()
+            .find(|asset| asset.asset_name == asset_name)
+    }
+}
+
+///Returns the value in the quote asset of amount base units of an asset with the given decimals
+pub fn to_value(amount: Uint128, decimals: u8, price: Decimal) -> StdResult<Decimal> {
+    Decimal::from_ratio(amount, decimals_to_unit(decimals)?).checked_mul(price)
+}
+
+///Returns how many base units of an asset with the given decimals are worth value, rounded down
+pub fn to_amount(value: Decimal, decimals: u8, price: Decimal) -> StdResult<Uint128> {
+    Ok(value.checked_div(price)? * decimals_to_unit(decimals)?)
+}
+
+///Returns amount base units of an asset with from_decimals in base units of an asset with
+/// to_decimals, rounded down
+pub fn convert_decimals(amount: Uint128, from_decimals: u8, to_decimals: u8) -> StdResult<Uint128> {
+    Ok(amount.multiply_ratio(
+        decimals_to_unit(to_decimals)?,
+        decimals_to_unit(from_decimals)?,
+    ))
+}
+
+/// Seconds in a year of 365 days, the basis implied rates are annualized with
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+///Returns the simple annualized rate implied by a future trading at future_price with
+/// time_to_expiry seconds left while its underlying trades at spot_price. The rate is returned as
+/// its absolute value together with whether it is negative
+pub fn implied_rate(
+    future_price: Decimal,
+    spot_price: Decimal,
+    time_to_expiry: u64,
+) -> StdResult<(Decimal, bool)> {
+    if time_to_expiry == 0 {
+        return Err(StdError::generic_err(
+            "Implied rate needs time left to expiry",
+        ));
+    }
+    let basis = future_price.checked_div(spot_price)?;
+    let (premium, negative) = if basis >= Decimal::one() {
+        (basis.checked_sub(Decimal::one())?, false)
+    } else {
+        (Decimal::one().checked_sub(basis)?, true)
+    };
+    let rate = premium.checked_mul(Decimal::from_ratio(SECONDS_PER_YEAR, time_to_expiry))?;
+    Ok((rate, negative))
+}
+
+/// Returns the price of asset_name in price_response. Fails if freshness is required and the price
+/// is older than the maximum age set for asset_name
+fn checked_price(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    asset_name: &str,
+    price_response: &PriceResponse,
+    freshness: PriceFreshness,
+) -> Result<Decimal, ContractError> {
+    if freshness == PriceFreshness::Required {
+        if let Some(max_age) = PRICE_MAX_AGE.may_load(storage, asset_name)? {
+            if price_response.is_stale(block.time.seconds(), max_age) {
+                return Err(ContractError::StalePrice {
+                    asset_name: asset_name.to_string(),
+                });
+            }
+        }
+    }
+    Ok(price_response.price)
+}
+
+///Get the price of asset_name from the oracle manager, checking its age if freshness is required
+pub fn get_checked_oracle_price(
+    deps: Deps,
+    block: &BlockInfo,
+    asset_name: &str,
+    freshness: PriceFreshness,
+) -> Result<Decimal, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let price_response = get_oracle_price(
+        deps.querier,
+        &config.oracle_manager_addr,
+        &config.quote_asset,
+        asset_name,
+        block,
+    )?;
+    checked_price(deps.storage, block, asset_name, &price_response, freshness)
+}
+
+///Get the prices of asset_names from the oracle manager with a single query, checking their ages if
+/// freshness is required. Returns a map from asset name to price
+pub fn get_checked_oracle_prices(
+    deps: Deps,
+    block: &BlockInfo,
+    asset_names: &[String],
+    freshness: PriceFreshness,
+) -> Result<HashMap<String, Decimal>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let price_responses = get_oracle_prices(
+        deps.querier,
+        &config.oracle_manager_addr,
+        &config.quote_asset,
+        asset_names,
+        block,
+    )?;
+    let mut prices = HashMap::new();
+    for (asset_name, price_response) in price_responses {
+        let price = checked_price(deps.storage, block, &asset_name, &price_response, freshness)?;
+        prices.insert(asset_name, price);
+    }
+    Ok(prices)
+}
+
+///Values the whole position of address at the time of block. Every live price is fetched once,
+/// with a single batched query to the oracle manager. Expired futures are valued at their
+/// settlement price
+pub fn value_position(
+    deps: Deps,
+    block: &BlockInfo,
+    address: &Addr,
+    freshness: PriceFreshness,
+) -> Result<PositionValuation, ContractError> {
+    let collateral = load_entries(deps, &COLLATERAL, address)?;
+    let borrow = load_entries(deps, &BORROW, address)?;
+
+    let mut prices = HashMap::new();
+    let mut asset_names = vec![];
+    for (asset_name, asset_info, _) in collateral.iter().chain(borrow.iter()) {
+        match future_state(deps.storage, block, asset_info)? {
+            Some(FutureState::Expired) | Some(FutureState::Finalized) => {
+                let fixing = SETTLEMENT_PRICES
+                    .may_load(deps.storage, asset_name)?
+                    .ok_or(ContractError::SettlementPriceNotFixed {})?;
+                prices.insert(asset_name.clone(), fixing.price);
+            }
+            _ => asset_names.push(asset_name.clone()),
+        }
+    }
+    if !asset_names.is_empty() {
+        prices.extend(get_checked_oracle_prices(
+            deps,
+            block,
+            &asset_names,
+            freshness,
+        )?);
+    }
+
+    let collateral = value_entries(collateral, &prices)?;
+    let borrow = value_entries(borrow, &prices)?;
+
+    let mut weighted_collateral_value = Decimal::zero();
+    for asset in &collateral {
+        weighted_collateral_value =
+            weighted_collateral_value + asset.value.checked_mul(asset.asset_info.get_ratio())?;
+    }
+    let mut borrow_value = Decimal::zero();
+    for asset in &borrow {
+        borrow_value = borrow_value + asset.value;
+    }
+
+    Ok(PositionValuation {
+        collateral,
+        borrow,
+        weighted_collateral_value,
+        borrow_value,
+        prices,
+    })
+}
+
+fn load_entries(
+    deps: Deps,
+    balances: &Map<(&Addr, &str), Uint128>,
+    address: &Addr,
+) -> Result<Vec<(String, AssetInfo, Uint128)>, ContractError> {
+    let mut entries = vec![];
+    for item in balances
+        .prefix(address)
+        .range(deps.storage, None, None, Order::Ascending)
+    {
+        let (asset_name_bytes, amount) = item?;
+        let asset_name = str::from_utf8(&asset_name_bytes)?.to_string();
+        let asset_info = SUPPORTED_ASSETS.load(deps.storage, &asset_name)?;
+        entries.push((asset_name, asset_info, amount));
+    }
+    Ok(entries)
+}
+
+fn value_entries(
+    entries: Vec<(String, AssetInfo, Uint128)>,
+    prices: &HashMap<String, Decimal>,
+) -> StdResult<Vec<AssetValuation>> {
+    entries
+        .into_iter()
+        .map(|(asset_name, asset_info, amount)| {
+            let price = *prices.get(&asset_name).ok_or_else(|| {
+                StdError::generic_err(format!("No oracle price returned for {}", asset_name))
+            })?;
+            let value = to_value(amount, asset_info.get_decimals(), price)?;
+            Ok(AssetValuation {
+                asset_name,
+                asset_info,
+                amount,
+                price,
+                value,
+            })
+        })
+        .collect()
+}
@@ -179,6 +179,9 @@ pub fn execute(
             msg,
         } => execute_send(deps, env, info, contract, amount, msg),
         ExecuteMsg::Mint { recipient, amount } => execute_mint(deps, env, info, recipient, amount),
+        ExecuteMsg::UpdateMinter { new_minter } => {
+            execute_update_minter(deps, env, info, new_minter)
+        }
         ExecuteMsg::IncreaseAllowance {
             spender,
             amount,
@@ -315,6 +318,37 @@ pub fn execute_mint(
     Ok(res)
 }
 
+pub fn execute_update_minter(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    new_minter: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut config = TOKEN_INFO.load(deps.storage)?;
+    let mint = match config.mint {
+        Some(ref mint) if mint.minter == info.sender => mint.clone(),
+        _ => return Err(ContractError::Unauthorized {}),
+    };
+
+    config.mint = match new_minter {
+        Some(new_minter) => Some(MinterData {
+            minter: deps.api.addr_validate(&new_minter)?,
+            cap: mint.cap,
+        }),
+        None => None,
+    };
+    TOKEN_INFO.save(deps.storage, &config)?;
+
+    let minter = config
+        .mint
+        .map(|mint| mint.minter.to_string())
+        .unwrap_or_else(|| "none".to_string());
+    let res = Response::new()
+        .add_attribute("action", "update_minter")
+        .add_attribute("new_minter", minter);
+    Ok(res)
+}
+
 pub fn execute_send(
     deps: DepsMut,
     _env: Env,
@@ -851,6 +885,55 @@ mod tests {
         assert_eq!(err, ContractError::Unauthorized {});
     }
 
+    #[test]
+    fn minter_can_update_minter() {
+        let mut deps = mock_dependencies(&[]);
+        let minter = String::from("minter");
+        let new_minter = String::from("new_minter");
+        do_instantiate_with_minter(
+            deps.as_mut(),
+            &String::from("genesis"),
+            Uint128::new(1234),
+            &minter,
+            Some(Uint128::new(5000)),
+        );
+
+        // only the minter can hand over minting rights
+        let msg = ExecuteMsg::UpdateMinter {
+            new_minter: Some(new_minter.clone()),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone else", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        execute(deps.as_mut(), mock_env(), mock_info(&minter, &[]), msg).unwrap();
+        assert_eq!(
+            query_minter(deps.as_ref()).unwrap(),
+            Some(MinterResponse {
+                minter: new_minter.clone(),
+                cap: Some(Uint128::new(5000)),
+            })
+        );
+
+        // the old minter can not mint anymore
+        let msg = ExecuteMsg::Mint {
+            recipient: String::from("lucky"),
+            amount: Uint128::new(222),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(&minter, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // removing the minter disables minting forever
+        let msg = ExecuteMsg::UpdateMinter { new_minter: None };
+        execute(deps.as_mut(), mock_env(), mock_info(&new_minter, &[]), msg).unwrap();
+        assert_eq!(query_minter(deps.as_ref()).unwrap(), None);
+    }
+
     #[test]
     fn no_one_mints_if_minter_unset() {
         let mut deps = mock_dependencies(&[]);
@@ -0,0 +1,262 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Deps, DepsMut, Env, Event, MessageInfo, Order, Reply, Response,
+    StdError, StdResult, SubMsg, WasmMsg,
+};
+use cw0::maybe_addr;
+use cw_storage_plus::{Bound, U64Key};
+
+use crate::error::ContractError;
+use crate::state::{Config, ADMIN, CONFIG, PENDING_SERIES, SERIES};
+use mint_authority::msg::{Cw20Token, InstantiateMsg as MintAuthorityInstantiateMsg};
+use steadifi::asset::AssetInfoUnvalidated;
+use steadifi::collateral_manager::msg::ExecuteMsg as CollateralManagerExecuteMsg;
+use steadifi::future_factory::msg::{
+    ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, SeriesListResponse,
+};
+use steadifi::future_factory::{SeriesDefinition, SeriesInfo};
+
+// Reply id of the submessage instantiating the contracts of a series
+pub const INSTANTIATE_MINT_AUTHORITY_REPLY_ID: u64 = 1;
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//Instantiates
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    mut deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    ADMIN.set(deps.branch(), Some(info.sender))?;
+    let config = Config {
+        collateral_manager_addr: deps.api.addr_validate(&msg.collateral_manager_addr)?,
+        cw20_code_id: msg.cw20_code_id,
+        mint_authority_code_id: msg.mint_authority_code_id,
+    };
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::default())
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//Executes
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::CreateSeries { series } => execute_create_series(deps, env, info, series),
+        ExecuteMsg::UpdateAdmin { new_admin } => execute_update_admin(deps, info, new_admin),
+    }
+}
+
+fn execute_update_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_admin: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let new_admin = maybe_addr(deps.api, Some(new_admin))?;
+    Ok(ADMIN.execute_update_admin(deps, info, new_admin)?)
+}
+
+/// Starts the deployment of a new series by instantiating its mint authority, which instantiates
+/// the cw20 token of the series with itself as the only minter
+fn execute_create_series(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    series: SeriesDefinition,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if series.expiry <= env.block.time.seconds() {
+        return Err(ContractError::ExpiryInPast {});
+    }
+    // Check the underlying is valid before deploying anything
+    series.underlying.clone().to_validated(deps.api)?;
+    let underlying_name = series.underlying.get_name();
+    if SERIES
+        .may_load(deps.storage, (&underlying_name, U64Key::new(series.expiry)))?
+        .is_some()
+    {
+        return Err(ContractError::SeriesAlreadyExists {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let asset_name = series.asset_name();
+    // The admin of the factory administers the contracts of the series, not the factory itself
+    let admin = ADMIN.get(deps.as_ref())?.map(String::from);
+    let instantiate_mint_authority = WasmMsg::Instantiate {
+        admin: admin.clone(),
+        code_id: config.mint_authority_code_id,
+        msg: to_binary(&MintAuthorityInstantiateMsg {
+            name: asset_name.clone(),
+            symbol: series.symbol.clone(),
+            decimals: series.decimals,
+            cw20: Cw20Token::Instantiate {
+                code_id: config.cw20_code_id,
+            },
+            address_collateral_manager: config.collateral_manager_addr.to_string(),
+            epoch_limit: None,
+            cap: None,
+            admin,
+        })?,
+        funds: vec![],
+        label: format!("{} mint authority", asset_name),
+    };
+    PENDING_SERIES.save(deps.storage, &series)?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
+            instantiate_mint_authority,
+            INSTANTIATE_MINT_AUTHORITY_REPLY_ID,
+        ))
+        .add_attribute("action", "create_series")
+        .add_attribute("asset_name", asset_name))
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//Replies
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        INSTANTIATE_MINT_AUTHORITY_REPLY_ID => reply_instantiate_mint_authority(deps, msg),
+        id => Err(StdError::generic_err(format!("Unknown reply id {}", id)).into()),
+    }
+}
+
+/// The mint authority of the pending series and its cw20 token exist. Registers the series in the
+/// collateral manager and in the registry
+fn reply_instantiate_mint_authority(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let response = msg.result.into_result().map_err(StdError::generic_err)?;
+    let mint_authority = parse_instantiated_address(deps.as_ref(), &response.events)?;
+    let contract_addr = response
+        .events
+        .iter()
+        .filter(|event| event.ty == "wasm")
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "address_cw20")
+        .map(|attr| attr.value.clone())
+        .ok_or_else(|| StdError::generic_err("cw20 token of the series was not instantiated"))?;
+    let contract_addr = deps.api.addr_validate(&contract_addr)?;
+    let series = PENDING_SERIES.load(deps.storage)?;
+    PENDING_SERIES.remove(deps.storage);
+    let asset_name = series.asset_name();
+
+    let series_info = SeriesInfo {
+        asset_name: asset_name.clone(),
+        underlying: series.underlying.clone(),
+        expiry: series.expiry,
+        ratio: series.ratio,
+        decimals: series.decimals,
+        collateralizeable: series.collateralizeable,
+        contract_addr: contract_addr.clone(),
+        mint_authority: mint_authority.clone(),
+    };
+    SERIES.save(
+        deps.storage,
+        (&series.underlying.get_name(), U64Key::new(series.expiry)),
+        &series_info,
+    )?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let add_supported_asset = WasmMsg::Execute {
+        contract_addr: config.collateral_manager_addr.to_string(),
+        msg: to_binary(&CollateralManagerExecuteMsg::AddSupportedAsset {
+            asset_name: asset_name.clone(),
+            asset_info_unvalidated: AssetInfoUnvalidated::FutureAsset {
+                asset_name: asset_name.clone(),
+                contract_addr: contract_addr.to_string(),
+                mint_authority: mint_authority.to_string(),
+                collateralizeable: series.collateralizeable,
+                ratio: series.ratio,
+                underlying: series.underlying,
+                decimals: series.decimals,
+                expiry: series.expiry,
+            },
+        })?,
+        funds: vec![],
+    };
+
+    Ok(Response::new()
+        .add_message(add_supported_asset)
+        .add_attribute("mint_authority", mint_authority)
+        .add_attribute("contract_addr", contract_addr)
+        .add_attribute("asset_name", asset_name))
+}
+
+/// Returns the address of the contract instantiated first in the events of a submessage. Contracts
+/// instantiated by that contract in turn come after it
+fn parse_instantiated_address(deps: Deps, events: &[Event]) -> StdResult<Addr> {
+    let contract_addr = events
+        .iter()
+        .filter(|event| event.ty == "instantiate_contract" || event.ty == "instantiate")
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "contract_address" || attr.key == "_contract_address")
+        .map(|attr| attr.value.clone())
+        .ok_or_else(|| StdError::generic_err("No contract address in instantiate reply"))?;
+    deps.api.addr_validate(&contract_addr)
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//Queries
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::Series { underlying, expiry } => {
+            to_binary(&query_series(deps, underlying, expiry)?)
+        }
+        QueryMsg::SeriesByUnderlying {
+            underlying,
+            start_after,
+            limit,
+        } => to_binary(&query_series_by_underlying(
+            deps,
+            underlying,
+            start_after,
+            limit,
+        )?),
+    }
+}
+
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse {
+        collateral_manager_addr: config.collateral_manager_addr.to_string(),
+        cw20_code_id: config.cw20_code_id,
+        mint_authority_code_id: config.mint_authority_code_id,
+    })
+}
+
+fn query_series(deps: Deps, underlying: String, expiry: u64) -> StdResult<SeriesInfo> {
+    SERIES.load(deps.storage, (&underlying, U64Key::new(expiry)))
+}
+
+fn query_series_by_underlying(
+    deps: Deps,
+    underlying: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SeriesListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+    let series: StdResult<Vec<SeriesInfo>> = SERIES
+        .prefix(&underlying)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, series_info)| series_info))
+        .collect();
+    Ok(SeriesListResponse { series: series? })
+}
+
+#[cfg(test)]
+#[path = "./contract_unittests.rs"]
+mod contract_unittests;
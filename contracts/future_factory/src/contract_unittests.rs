@@ -0,0 +1,213 @@
+use super::*;
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, ContractResult, Event, SubMsgExecutionResponse};
+use cw_controllers::AdminError;
+use steadifi::asset::NormalAssetInfoUnvalidated;
+use steadifi::mars_protocol_math::Decimal;
+
+fn default_instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        collateral_manager_addr: "collateral_manager".to_string(),
+        cw20_code_id: 10,
+        mint_authority_code_id: 11,
+    }
+}
+
+fn wbtc_series(expiry: u64) -> SeriesDefinition {
+    SeriesDefinition {
+        underlying: NormalAssetInfoUnvalidated::CW20Token {
+            asset_name: "wbtc".to_string(),
+            contract_addr: "wbtc_cw20_address".to_string(),
+            collateralizeable: true,
+            ratio: Decimal::from_ratio(9u32, 10u32),
+            decimals: 8,
+        },
+        expiry,
+        ratio: Decimal::from_ratio(8u32, 10u32),
+        decimals: 8,
+        collateralizeable: false,
+        symbol: "fBTC".to_string(),
+    }
+}
+
+fn instantiate_reply(mint_authority: &str, contract_addr: &str) -> Reply {
+    Reply {
+        id: INSTANTIATE_MINT_AUTHORITY_REPLY_ID,
+        result: ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![
+                Event::new("instantiate").add_attribute("_contract_address", mint_authority),
+                Event::new("instantiate").add_attribute("_contract_address", contract_addr),
+                Event::new("wasm")
+                    .add_attribute("_contract_address", mint_authority)
+                    .add_attribute("action", "instantiate_cw20")
+                    .add_attribute("address_cw20", contract_addr),
+            ],
+            data: None,
+        }),
+    }
+}
+
+#[test]
+fn test_create_series() {
+    // Initializations
+    let mut deps = mock_dependencies(&[]);
+    let env = mock_env();
+    let admin_info = mock_info("admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let expiry = env.block.time.seconds() + 1000;
+
+    // The mint authority is instantiated, it instantiates the cw20 token of the series
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::CreateSeries {
+            series: wbtc_series(expiry),
+        },
+    )
+    .unwrap();
+    let asset_name = format!("wbtc-{}", expiry);
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::reply_on_success(
+            WasmMsg::Instantiate {
+                admin: Some("admin".to_string()),
+                code_id: 11,
+                msg: to_binary(&MintAuthorityInstantiateMsg {
+                    name: asset_name.clone(),
+                    symbol: "fBTC".to_string(),
+                    decimals: 8,
+                    cw20: Cw20Token::Instantiate { code_id: 10 },
+                    address_collateral_manager: "collateral_manager".to_string(),
+                    epoch_limit: None,
+                    cap: None,
+                    admin: Some("admin".to_string()),
+                })
+                .unwrap(),
+                funds: vec![],
+                label: format!("{} mint authority", asset_name),
+            },
+            INSTANTIATE_MINT_AUTHORITY_REPLY_ID
+        )]
+    );
+
+    // Then the series is registered
+    let response = reply(
+        deps.as_mut(),
+        env.clone(),
+        instantiate_reply("fbtc_mint_authority", "fbtc_cw20_address"),
+    )
+    .unwrap();
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "collateral_manager".to_string(),
+            msg: to_binary(&CollateralManagerExecuteMsg::AddSupportedAsset {
+                asset_name: asset_name.clone(),
+                asset_info_unvalidated: AssetInfoUnvalidated::FutureAsset {
+                    asset_name: asset_name.clone(),
+                    contract_addr: "fbtc_cw20_address".to_string(),
+                    mint_authority: "fbtc_mint_authority".to_string(),
+                    collateralizeable: false,
+                    ratio: Decimal::from_ratio(8u32, 10u32),
+                    underlying: wbtc_series(expiry).underlying,
+                    decimals: 8,
+                    expiry,
+                },
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+
+    // The series can be found in the registry
+    let series_info: SeriesInfo = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Series {
+                underlying: "wbtc".to_string(),
+                expiry,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(series_info.asset_name, asset_name);
+    assert_eq!(
+        series_info.contract_addr,
+        Addr::unchecked("fbtc_cw20_address")
+    );
+    assert_eq!(
+        series_info.mint_authority,
+        Addr::unchecked("fbtc_mint_authority")
+    );
+
+    // Register a second, earlier series and list them by expiry
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::CreateSeries {
+            series: wbtc_series(expiry - 500),
+        },
+    )
+    .unwrap();
+    reply(
+        deps.as_mut(),
+        env.clone(),
+        instantiate_reply("fbtc2_mint_authority", "fbtc2_cw20_address"),
+    )
+    .unwrap();
+    let list = query_series_by_underlying(deps.as_ref(), "wbtc".to_string(), None, None).unwrap();
+    let expiries: Vec<u64> = list.series.iter().map(|series| series.expiry).collect();
+    assert_eq!(expiries, vec![expiry - 500, expiry]);
+    let list =
+        query_series_by_underlying(deps.as_ref(), "wbtc".to_string(), Some(expiry - 500), None)
+            .unwrap();
+    assert_eq!(list.series, vec![series_info]);
+    let list = query_series_by_underlying(deps.as_ref(), "weth".to_string(), None, None).unwrap();
+    assert!(list.series.is_empty());
+
+    // A series can only be created once
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::CreateSeries {
+            series: wbtc_series(expiry),
+        },
+    );
+    assert_eq!(contract_result, Err(ContractError::SeriesAlreadyExists {}));
+
+    // Expiry has to be in the future
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info,
+        ExecuteMsg::CreateSeries {
+            series: wbtc_series(env.block.time.seconds()),
+        },
+    );
+    assert_eq!(contract_result, Err(ContractError::ExpiryInPast {}));
+
+    // Only the admin can create series
+    let contract_result = execute(
+        deps.as_mut(),
+        env,
+        mock_info("someone_who_is_not_admin", &[]),
+        ExecuteMsg::CreateSeries {
+            series: wbtc_series(expiry + 1000),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+}
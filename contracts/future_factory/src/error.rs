@@ -0,0 +1,18 @@
+use cosmwasm_std::StdError;
+pub use cw_controllers::AdminError;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    ControllerError(#[from] AdminError),
+
+    #[error("A series of this underlying with this expiry already exists")]
+    SeriesAlreadyExists {},
+
+    #[error("Expiry of the series has to be in the future")]
+    ExpiryInPast {},
+}
@@ -0,0 +1,26 @@
+use cosmwasm_std::Addr;
+use cw_controllers::Admin;
+use cw_storage_plus::{Item, Map, U64Key};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use steadifi::future_factory::{SeriesDefinition, SeriesInfo};
+
+// Admin of contract can create new series
+// Eventually the admin will be the governance contract
+pub const ADMIN: Admin = Admin::new("admin");
+
+//Contract config
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Config {
+    pub collateral_manager_addr: Addr,
+    pub cw20_code_id: u64,
+    pub mint_authority_code_id: u64,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
+// Series whose contracts are being instantiated. Only lives during the submessage of CreateSeries
+pub const PENDING_SERIES: Item<SeriesDefinition> = Item::new("pending_series");
+
+// Maps (underlying name, expiry) to the deployed series
+pub const SERIES: Map<(&str, U64Key), SeriesInfo> = Map::new("series");
@@ -3,7 +3,10 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use mint_authority::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use mint_authority::msg::{
+    ExecuteMsg, FlashMintCallbackMsg, FlashMintConfig, InstantiateMsg, MintUsageResponse,
+    MintersResponse, QueryMsg, ReconciliationResponse,
+};
 use mint_authority::state::MintAuthorityInfo;
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -15,4 +18,9 @@ fn main() {
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(MintAuthorityInfo), &out_dir);
+    export_schema(&schema_for!(MintersResponse), &out_dir);
+    export_schema(&schema_for!(ReconciliationResponse), &out_dir);
+    export_schema(&schema_for!(MintUsageResponse), &out_dir);
+    export_schema(&schema_for!(FlashMintConfig), &out_dir);
+    export_schema(&schema_for!(FlashMintCallbackMsg), &out_dir);
 }
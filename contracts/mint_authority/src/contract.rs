@@ -1,80 +1,520 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
+    to_binary, Addr, Binary, BlockInfo, Decimal, Deps, DepsMut, Empty, Env, MessageInfo, Order,
+    Reply, Response, StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg,
 };
+use cw0::maybe_addr;
+use cw20::{BalanceResponse, Cw20QueryMsg, MinterResponse, TokenInfoResponse};
+use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{MintAuthorityInfo, MINT_AUTHORITY_INFO};
+use crate::msg::{
+    Cw20Token, EpochLimit, ExecuteMsg, FlashMintCallbackMsg, FlashMintConfig, InstantiateMsg,
+    MintUsageResponse, MintersResponse, QueryMsg, ReconciliationResponse,
+};
+use crate::state::{
+    EpochUsage, FlashMintSettings, MintAuthorityInfo, MintLimits, PendingFlashMint, ADMIN,
+    EPOCH_USAGE, FLASH_MINT_SETTINGS, MINTERS, MINT_AUTHORITY_INFO, MINT_LIMITS,
+    PENDING_FLASH_MINT,
+};
+
+// Reply id of the callback to the receiver of a flash mint
+pub const FLASH_MINT_REPLY_ID: u64 = 1;
+// Reply id of the instantiation of the cw20 token in instantiate
+pub const INSTANTIATE_CW20_REPLY_ID: u64 = 2;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     // check valid token info
     msg.validate()?;
+    let admin = match &msg.admin {
+        Some(admin) => deps.api.addr_validate(admin)?,
+        None => info.sender,
+    };
+    ADMIN.set(deps.branch(), Some(admin.clone()))?;
+
+    // Either record the token or instantiate it, its address is then recorded in the reply
+    let (address_cw20, submessages) = match msg.cw20 {
+        Cw20Token::Existing { address } => (Some(deps.api.addr_validate(&address)?), vec![]),
+        Cw20Token::Instantiate { code_id } => {
+            let instantiate_cw20 = WasmMsg::Instantiate {
+                admin: Some(admin.to_string()),
+                code_id,
+                msg: to_binary(&Cw20InstantiateMsg {
+                    name: msg.name.clone(),
+                    symbol: msg.symbol.clone(),
+                    decimals: msg.decimals,
+                    initial_balances: vec![],
+                    mint: Some(MinterResponse {
+                        minter: env.contract.address.to_string(),
+                        cap: None,
+                    }),
+                    marketing: None,
+                })?,
+                funds: vec![],
+                label: msg.name.clone(),
+            };
+            (
+                None,
+                vec![SubMsg::reply_on_success(
+                    instantiate_cw20,
+                    INSTANTIATE_CW20_REPLY_ID,
+                )],
+            )
+        }
+    };
 
     // store token info
     let data = MintAuthorityInfo {
         name: msg.name,
         symbol: msg.symbol,
         decimals: msg.decimals,
-        address_cw20: deps.api.addr_validate(&msg.address_cw20)?,
+        address_cw20,
         address_collateral_manager: deps.api.addr_validate(&msg.address_collateral_manager)?,
-        total_mint: Uint128::zero(),
+        total_minted: Uint128::zero(),
+        total_burned: Uint128::zero(),
     };
     MINT_AUTHORITY_INFO.save(deps.storage, &data)?;
-    Ok(Response::default())
+    MINT_LIMITS.save(
+        deps.storage,
+        &MintLimits {
+            epoch_limit: msg.epoch_limit,
+            cap: msg.cap,
+        },
+    )?;
+    Ok(Response::new().add_submessages(submessages))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
+    env: Env,
+    info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    let res = match msg {
+    match msg {
         ExecuteMsg::MintAndSend { recipient, amount } => {
-            if amount == Uint128::zero() {
-                return Err(ContractError::InvalidZeroAmount {});
+            execute_mint_and_send(deps, env, info, recipient, amount)
+        }
+        ExecuteMsg::BurnFrom { owner, amount } => execute_burn_from(deps, info, owner, amount),
+        ExecuteMsg::FlashMint {
+            receiver,
+            amount,
+            msg,
+        } => execute_flash_mint(deps, env, info, receiver, amount, msg),
+        ExecuteMsg::UpdateFlashMint { fee_rate, treasury } => {
+            execute_update_flash_mint(deps, info, fee_rate, treasury)
+        }
+        ExecuteMsg::UpdateMintLimits { epoch_limit, cap } => {
+            execute_update_mint_limits(deps, info, epoch_limit, cap)
+        }
+        ExecuteMsg::AddMinter { addr } => execute_add_minter(deps, info, addr),
+        ExecuteMsg::RemoveMinter { addr } => execute_remove_minter(deps, info, addr),
+        ExecuteMsg::UpdateAdmin { new_admin } => execute_update_admin(deps, info, new_admin),
+    }
+}
+
+fn execute_mint_and_send(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    assert_minter(deps.as_ref(), &info.sender)?;
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let rcpt_addr = deps.api.addr_validate(&recipient)?;
+    let mut info = MINT_AUTHORITY_INFO.load(deps.storage)?;
+    apply_mint_limits(deps.storage, &env.block, &info, amount)?;
+    info.total_minted = info
+        .total_minted
+        .checked_add(amount)
+        .map_err(StdError::from)?;
+    MINT_AUTHORITY_INFO.save(deps.storage, &info)?;
+    //Execute Mint
+    let cw20_contract_address: Addr = get_cw20_address(&deps)?;
+    let response = Response::new()
+        .add_attribute("recipient", &recipient)
+        .add_attribute("amount minted", amount.to_string())
+        .add_message(
+            cw20::Cw20ExecuteMsg::Mint {
+                recipient: rcpt_addr.to_string(),
+                amount,
             }
-            let rcpt_addr = deps.api.addr_validate(&recipient)?;
-            //Execute Mint
-            let cw20_contract_address: Addr = get_cw20_address(&deps)?;
-            let response = Response::new()
-                .add_attribute("recipient", &recipient)
-                .add_attribute("amount minted", amount.to_string())
-                .add_message(
-                    cw20::Cw20ExecuteMsg::Mint {
-                        recipient: rcpt_addr.to_string(),
-                        amount,
-                    }
-                    .into_cosmos_msg(cw20_contract_address)?,
-                );
-            Ok(response)
+            .into_cosmos_msg(cw20_contract_address)?,
+        );
+    Ok(response)
+}
+
+///Returns an error if minting amount more would exceed the cap or the limit of the current epoch,
+/// otherwise counts amount towards the current epoch
+fn apply_mint_limits(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    info: &MintAuthorityInfo,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let limits = MINT_LIMITS.load(storage)?;
+    if let Some(cap) = limits.cap {
+        let net_minted = info
+            .total_minted
+            .checked_sub(info.total_burned)
+            .and_then(|net_minted| net_minted.checked_add(amount))
+            .map_err(StdError::from)?;
+        if net_minted > cap {
+            return Err(ContractError::CannotExceedCap {});
         }
+    }
+    if let Some(epoch_limit) = limits.epoch_limit {
+        let mut usage = match EPOCH_USAGE.may_load(storage)? {
+            Some(usage) if !usage.ends.is_expired(block) => usage,
+            _ => EpochUsage {
+                minted: Uint128::zero(),
+                ends: epoch_limit.epoch.after(block),
+            },
+        };
+        usage.minted = usage.minted.checked_add(amount).map_err(StdError::from)?;
+        if usage.minted > epoch_limit.amount {
+            return Err(ContractError::CannotExceedCap {});
+        }
+        EPOCH_USAGE.save(storage, &usage)?;
+    }
+    Ok(())
+}
+
+/// Mints amount to receiver and calls it back. The reply to the callback checks that amount plus the
+/// fee came back, burns amount and sends the fee to the treasury. Flash mints do not count towards
+/// the mint limits since they are burned within the same transaction
+fn execute_flash_mint(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    receiver: String,
+    amount: Uint128,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let settings = FLASH_MINT_SETTINGS
+        .may_load(deps.storage)?
+        .ok_or(ContractError::FlashMintDisabled {})?;
+    if PENDING_FLASH_MINT.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::FlashMintInProgress {});
+    }
+    let receiver_addr = deps.api.addr_validate(&receiver)?;
+    let cw20_contract_address = get_cw20_address(&deps)?;
+    let fee = amount * settings.fee_rate;
+    let balance_before =
+        query_token_balance(deps.as_ref(), &cw20_contract_address, &env.contract.address)?;
+    PENDING_FLASH_MINT.save(
+        deps.storage,
+        &PendingFlashMint {
+            amount,
+            fee,
+            balance_before,
+        },
+    )?;
+
+    let callback = FlashMintCallbackMsg {
+        initiator: info.sender.to_string(),
+        amount,
+        fee,
+        msg,
+    }
+    .into_cosmos_msg(receiver_addr.clone())?;
+    Ok(Response::new()
+        .add_message(
+            cw20::Cw20ExecuteMsg::Mint {
+                recipient: receiver_addr.to_string(),
+                amount,
+            }
+            .into_cosmos_msg(cw20_contract_address)?,
+        )
+        .add_submessage(SubMsg::reply_on_success(callback, FLASH_MINT_REPLY_ID))
+        .add_attribute("action", "flash_mint")
+        .add_attribute("receiver", receiver_addr)
+        .add_attribute("amount", amount)
+        .add_attribute("fee", fee))
+}
+
+fn execute_update_flash_mint(
+    deps: DepsMut,
+    info: MessageInfo,
+    fee_rate: Decimal,
+    treasury: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if fee_rate > Decimal::one() {
+        return Err(ContractError::InvalidFeeRate {});
+    }
+    let settings = FlashMintSettings {
+        fee_rate,
+        treasury: deps.api.addr_validate(&treasury)?,
     };
-    res
+    FLASH_MINT_SETTINGS.save(deps.storage, &settings)?;
+    Ok(Response::new()
+        .add_attribute("action", "update_flash_mint")
+        .add_attribute("fee_rate", fee_rate.to_string())
+        .add_attribute("treasury", settings.treasury))
+}
+
+fn execute_update_mint_limits(
+    deps: DepsMut,
+    info: MessageInfo,
+    epoch_limit: Option<EpochLimit>,
+    cap: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    MINT_LIMITS.save(deps.storage, &MintLimits { epoch_limit, cap })?;
+    EPOCH_USAGE.remove(deps.storage);
+    Ok(Response::new().add_attribute("action", "update_mint_limits"))
+}
+
+fn execute_burn_from(
+    deps: DepsMut,
+    info: MessageInfo,
+    owner: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    assert_minter(deps.as_ref(), &info.sender)?;
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let owner_addr = deps.api.addr_validate(&owner)?;
+    MINT_AUTHORITY_INFO.update(deps.storage, |mut info| -> StdResult<_> {
+        info.total_burned = info.total_burned.checked_add(amount)?;
+        Ok(info)
+    })?;
+    //Execute Burn
+    let cw20_contract_address: Addr = get_cw20_address(&deps)?;
+    let response = Response::new()
+        .add_attribute("owner", &owner)
+        .add_attribute("amount burned", amount.to_string())
+        .add_message(
+            cw20::Cw20ExecuteMsg::BurnFrom {
+                owner: owner_addr.to_string(),
+                amount,
+            }
+            .into_cosmos_msg(cw20_contract_address)?,
+        );
+    Ok(response)
+}
+
+fn execute_add_minter(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let minter = deps.api.addr_validate(&addr)?;
+    MINTERS.save(deps.storage, &minter, &Empty {})?;
+    Ok(Response::new()
+        .add_attribute("action", "add_minter")
+        .add_attribute("minter", minter))
+}
+
+fn execute_remove_minter(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let minter = deps.api.addr_validate(&addr)?;
+    MINTERS.remove(deps.storage, &minter);
+    Ok(Response::new()
+        .add_attribute("action", "remove_minter")
+        .add_attribute("minter", minter))
+}
+
+fn execute_update_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_admin: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let new_admin = maybe_addr(deps.api, Some(new_admin))?;
+    Ok(ADMIN.execute_update_admin(deps, info, new_admin)?)
+}
+
+///Returns an error unless sender is the collateral manager or an approved minter
+fn assert_minter(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    let info = MINT_AUTHORITY_INFO.load(deps.storage)?;
+    if *sender != info.address_collateral_manager && !MINTERS.has(deps.storage, sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        FLASH_MINT_REPLY_ID => reply_flash_mint(deps, env),
+        INSTANTIATE_CW20_REPLY_ID => reply_instantiate_cw20(deps, msg),
+        id => Err(StdError::generic_err(format!("Unknown reply id {}", id)).into()),
+    }
+}
+
+/// The cw20 token instantiated in instantiate exists, record its address
+fn reply_instantiate_cw20(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let response = msg.result.into_result().map_err(StdError::generic_err)?;
+    let contract_addr = response
+        .events
+        .iter()
+        .filter(|event| event.ty == "instantiate_contract" || event.ty == "instantiate")
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "contract_address" || attr.key == "_contract_address")
+        .map(|attr| attr.value.clone())
+        .ok_or_else(|| StdError::generic_err("No contract address in instantiate reply"))?;
+    let contract_addr = deps.api.addr_validate(&contract_addr)?;
+    MINT_AUTHORITY_INFO.update(deps.storage, |mut info| -> StdResult<_> {
+        info.address_cw20 = Some(contract_addr.clone());
+        Ok(info)
+    })?;
+    Ok(Response::new()
+        .add_attribute("action", "instantiate_cw20")
+        .add_attribute("address_cw20", contract_addr))
+}
+
+/// The receiver of the pending flash mint was called back, it must have returned amount plus the fee
+fn reply_flash_mint(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let pending = PENDING_FLASH_MINT.load(deps.storage)?;
+    PENDING_FLASH_MINT.remove(deps.storage);
+    let cw20_contract_address = get_cw20_address(&deps)?;
+    let balance_after =
+        query_token_balance(deps.as_ref(), &cw20_contract_address, &env.contract.address)?;
+    let returned = balance_after
+        .checked_sub(pending.balance_before)
+        .unwrap_or_default();
+    let expected = pending
+        .amount
+        .checked_add(pending.fee)
+        .map_err(StdError::from)?;
+    if returned < expected {
+        return Err(ContractError::FlashMintNotRepaid { expected, returned });
+    }
+    MINT_AUTHORITY_INFO.update(deps.storage, |mut info| -> StdResult<_> {
+        info.total_minted = info.total_minted.checked_add(pending.amount)?;
+        info.total_burned = info.total_burned.checked_add(pending.amount)?;
+        Ok(info)
+    })?;
+
+    let mut response = Response::new().add_message(
+        cw20::Cw20ExecuteMsg::Burn {
+            amount: pending.amount,
+        }
+        .into_cosmos_msg(&cw20_contract_address)?,
+    );
+    if !pending.fee.is_zero() {
+        let settings = FLASH_MINT_SETTINGS.load(deps.storage)?;
+        response = response.add_message(
+            cw20::Cw20ExecuteMsg::Transfer {
+                recipient: settings.treasury.to_string(),
+                amount: pending.fee,
+            }
+            .into_cosmos_msg(&cw20_contract_address)?,
+        );
+    }
+    Ok(response
+        .add_attribute("action", "flash_mint_repaid")
+        .add_attribute("amount", pending.amount)
+        .add_attribute("fee", pending.fee))
+}
+
+fn query_token_balance(
+    deps: Deps,
+    cw20_contract_address: &Addr,
+    address: &Addr,
+) -> StdResult<Uint128> {
+    let balance: BalanceResponse = deps.querier.query_wasm_smart(
+        cw20_contract_address,
+        &Cw20QueryMsg::Balance {
+            address: address.to_string(),
+        },
+    )?;
+    Ok(balance.balance)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::MintAuthorityInfo {} => to_binary(&query_mint_authority_info(deps)?),
+        QueryMsg::Admin {} => to_binary(&ADMIN.query_admin(deps)?),
+        QueryMsg::Minters {} => to_binary(&query_minters(deps)?),
+        QueryMsg::Reconciliation {} => to_binary(&query_reconciliation(deps)?),
+        QueryMsg::MintUsage {} => to_binary(&query_mint_usage(deps, env)?),
+        QueryMsg::FlashMintConfig {} => to_binary(&query_flash_mint_config(deps)?),
     }
 }
 
 fn get_cw20_address(deps: &DepsMut) -> StdResult<Addr> {
     let info = MINT_AUTHORITY_INFO.load(deps.storage)?;
-    Ok(info.address_cw20)
+    info.address_cw20
+        .ok_or_else(|| StdError::generic_err("CW20 token has not been instantiated"))
 }
 
 fn query_mint_authority_info(deps: Deps) -> StdResult<MintAuthorityInfo> {
     let info = MINT_AUTHORITY_INFO.load(deps.storage)?;
     Ok(info)
 }
+
+fn query_minters(deps: Deps) -> StdResult<MintersResponse> {
+    let minters: StdResult<Vec<String>> = MINTERS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|key| String::from_utf8(key).map_err(Into::into))
+        .collect();
+    Ok(MintersResponse { minters: minters? })
+}
+
+fn query_reconciliation(deps: Deps) -> StdResult<ReconciliationResponse> {
+    let info = MINT_AUTHORITY_INFO.load(deps.storage)?;
+    let cw20_contract_address = info
+        .address_cw20
+        .ok_or_else(|| StdError::generic_err("CW20 token has not been instantiated"))?;
+    let token_info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(cw20_contract_address, &Cw20QueryMsg::TokenInfo {})?;
+    let net_minted = info.total_minted.checked_sub(info.total_burned)?;
+    Ok(ReconciliationResponse {
+        total_minted: info.total_minted,
+        total_burned: info.total_burned,
+        net_minted,
+        total_supply: token_info.total_supply,
+        reconciled: net_minted == token_info.total_supply,
+    })
+}
+
+fn query_flash_mint_config(deps: Deps) -> StdResult<Option<FlashMintConfig>> {
+    let settings = FLASH_MINT_SETTINGS.may_load(deps.storage)?;
+    Ok(settings.map(|settings| FlashMintConfig {
+        fee_rate: settings.fee_rate,
+        treasury: settings.treasury.to_string(),
+    }))
+}
+
+fn query_mint_usage(deps: Deps, env: Env) -> StdResult<MintUsageResponse> {
+    let info = MINT_AUTHORITY_INFO.load(deps.storage)?;
+    let limits = MINT_LIMITS.load(deps.storage)?;
+    let usage = EPOCH_USAGE
+        .may_load(deps.storage)?
+        .filter(|usage| !usage.ends.is_expired(&env.block));
+    Ok(MintUsageResponse {
+        epoch_limit: limits.epoch_limit,
+        epoch_minted: usage.as_ref().map(|usage| usage.minted).unwrap_or_default(),
+        epoch_ends: usage.map(|usage| usage.ends),
+        cap: limits.cap,
+        net_minted: info.total_minted.checked_sub(info.total_burned)?,
+    })
+}
+
+#[cfg(test)]
+#[path = "./contract_unittests.rs"]
+mod contract_unittests;
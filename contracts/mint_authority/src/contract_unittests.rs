@@ -0,0 +1,732 @@
+use super::*;
+use crate::error::AdminError;
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_binary, ContractResult, CosmosMsg, Event, ReplyOn, SubMsg, SubMsgExecutionResponse,
+};
+use cw0::{Duration, Expiration};
+use cw20::{Cw20ExecuteMsg, TokenInfoResponse};
+use cw_controllers::AdminResponse;
+use steadifi::testing::WasmMockQuerier;
+
+fn default_instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        name: "fbtc".to_string(),
+        symbol: "fBTC".to_string(),
+        decimals: 6,
+        cw20: Cw20Token::Existing {
+            address: "fbtc_cw20_address".to_string(),
+        },
+        address_collateral_manager: "collateral_manager".to_string(),
+        epoch_limit: None,
+        cap: None,
+        admin: None,
+    }
+}
+
+fn mint_msg(amount: u128) -> ExecuteMsg {
+    ExecuteMsg::MintAndSend {
+        recipient: "user".to_string(),
+        amount: Uint128::from(amount),
+    }
+}
+
+#[test]
+fn test_mint_access_control() {
+    // Initializations
+    let mut deps = mock_dependencies(&[]);
+    let env = mock_env();
+    let admin_info = mock_info("admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+
+    // The collateral manager can mint
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("collateral_manager", &[]),
+        mint_msg(100),
+    )
+    .unwrap();
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(
+            Cw20ExecuteMsg::Mint {
+                recipient: "user".to_string(),
+                amount: Uint128::from(100u128),
+            }
+            .into_cosmos_msg("fbtc_cw20_address")
+            .unwrap()
+        )]
+    );
+
+    // Third parties can not
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        mint_msg(100),
+    );
+    assert_eq!(contract_result, Err(ContractError::Unauthorized {}));
+
+    // Only the admin can approve minters
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        ExecuteMsg::AddMinter {
+            addr: "someone".to_string(),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+
+    // Approved minters can mint until they are removed
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::AddMinter {
+            addr: "someone".to_string(),
+        },
+    )
+    .unwrap();
+    let minters: MintersResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Minters {}).unwrap()).unwrap();
+    assert_eq!(minters.minters, vec!["someone".to_string()]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        mint_msg(100),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::RemoveMinter {
+            addr: "someone".to_string(),
+        },
+    )
+    .unwrap();
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        mint_msg(100),
+    );
+    assert_eq!(contract_result, Err(ContractError::Unauthorized {}));
+
+    // The admin can hand over administration
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::UpdateAdmin {
+            new_admin: "new_admin".to_string(),
+        },
+    )
+    .unwrap();
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info,
+        ExecuteMsg::AddMinter {
+            addr: "someone".to_string(),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+
+    // Zero amounts are rejected
+    let contract_result = execute(
+        deps.as_mut(),
+        env,
+        mock_info("collateral_manager", &[]),
+        mint_msg(0),
+    );
+    assert_eq!(contract_result, Err(ContractError::InvalidZeroAmount {}));
+}
+
+#[test]
+fn test_instantiate_cw20() {
+    // Initializations
+    let mut deps = mock_dependencies(&[]);
+    let env = mock_env();
+    let msg = InstantiateMsg {
+        cw20: Cw20Token::Instantiate { code_id: 7 },
+        admin: Some("admin".to_string()),
+        ..default_instantiate_msg()
+    };
+
+    // The token is instantiated with this contract as its only minter. Both are administered by
+    // the given admin rather than by the instantiating factory
+    let response = instantiate(deps.as_mut(), env.clone(), mock_info("factory", &[]), msg).unwrap();
+    let admin: AdminResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Admin {}).unwrap()).unwrap();
+    assert_eq!(admin.admin, Some("admin".to_string()));
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::reply_on_success(
+            WasmMsg::Instantiate {
+                admin: Some("admin".to_string()),
+                code_id: 7,
+                msg: to_binary(&Cw20InstantiateMsg {
+                    name: "fbtc".to_string(),
+                    symbol: "fBTC".to_string(),
+                    decimals: 6,
+                    initial_balances: vec![],
+                    mint: Some(MinterResponse {
+                        minter: MOCK_CONTRACT_ADDR.to_string(),
+                        cap: None,
+                    }),
+                    marketing: None,
+                })
+                .unwrap(),
+                funds: vec![],
+                label: "fbtc".to_string(),
+            },
+            INSTANTIATE_CW20_REPLY_ID
+        )]
+    );
+
+    // Nothing can be minted before the token exists
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("collateral_manager", &[]),
+        mint_msg(100),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::Std(StdError::generic_err(
+            "CW20 token has not been instantiated"
+        )))
+    );
+
+    // The address of the token is recorded in the reply
+    let reply_msg = Reply {
+        id: INSTANTIATE_CW20_REPLY_ID,
+        result: ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![Event::new("instantiate_contract")
+                .add_attribute("contract_address", "fbtc_cw20_address")],
+            data: None,
+        }),
+    };
+    reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+    let info = query_mint_authority_info(deps.as_ref()).unwrap();
+    assert_eq!(
+        info.address_cw20,
+        Some(Addr::unchecked("fbtc_cw20_address"))
+    );
+    let response = execute(
+        deps.as_mut(),
+        env,
+        mock_info("collateral_manager", &[]),
+        mint_msg(100),
+    )
+    .unwrap();
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(
+            Cw20ExecuteMsg::Mint {
+                recipient: "user".to_string(),
+                amount: Uint128::from(100u128),
+            }
+            .into_cosmos_msg("fbtc_cw20_address")
+            .unwrap()
+        )]
+    );
+}
+
+#[test]
+fn test_mint_burn_accounting() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    let env = mock_env();
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    let manager_info = mock_info("collateral_manager", &[]);
+    let burn_msg = |amount: u128| ExecuteMsg::BurnFrom {
+        owner: "collateral_manager".to_string(),
+        amount: Uint128::from(amount),
+    };
+
+    // Mints and burns are tracked
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        manager_info.clone(),
+        mint_msg(100),
+    )
+    .unwrap();
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        manager_info.clone(),
+        burn_msg(30),
+    )
+    .unwrap();
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(
+            Cw20ExecuteMsg::BurnFrom {
+                owner: "collateral_manager".to_string(),
+                amount: Uint128::from(30u128),
+            }
+            .into_cosmos_msg("fbtc_cw20_address")
+            .unwrap()
+        )]
+    );
+    let info = query_mint_authority_info(deps.as_ref()).unwrap();
+    assert_eq!(info.total_minted, Uint128::from(100u128));
+    assert_eq!(info.total_burned, Uint128::from(30u128));
+
+    // The net amount minted is compared with the supply of the token
+    let token_info = |total_supply: u128| TokenInfoResponse {
+        name: "fbtc".to_string(),
+        symbol: "fBTC".to_string(),
+        decimals: 6,
+        total_supply: Uint128::from(total_supply),
+    };
+    deps.querier
+        .set_token_info("fbtc_cw20_address", token_info(70));
+    let reconciliation: ReconciliationResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Reconciliation {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        reconciliation,
+        ReconciliationResponse {
+            total_minted: Uint128::from(100u128),
+            total_burned: Uint128::from(30u128),
+            net_minted: Uint128::from(70u128),
+            total_supply: Uint128::from(70u128),
+            reconciled: true,
+        }
+    );
+    deps.querier
+        .set_token_info("fbtc_cw20_address", token_info(80));
+    let reconciliation = query_reconciliation(deps.as_ref()).unwrap();
+    assert!(!reconciliation.reconciled);
+
+    // Third parties can not burn
+    let contract_result = execute(deps.as_mut(), env, mock_info("someone", &[]), burn_msg(10));
+    assert_eq!(contract_result, Err(ContractError::Unauthorized {}));
+}
+
+#[test]
+fn test_mint_limits() {
+    // Initializations, at most 100 tokens per hour and 250 tokens outstanding
+    let mut deps = mock_dependencies(&[]);
+    let mut env = mock_env();
+    let epoch_limit = EpochLimit {
+        amount: Uint128::from(100u128),
+        epoch: Duration::Time(3600),
+    };
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        InstantiateMsg {
+            epoch_limit: Some(epoch_limit.clone()),
+            cap: Some(Uint128::from(250u128)),
+            ..default_instantiate_msg()
+        },
+    )
+    .unwrap();
+    let manager_info = mock_info("collateral_manager", &[]);
+
+    // Mints count towards the epoch until its limit is reached
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        manager_info.clone(),
+        mint_msg(60),
+    )
+    .unwrap();
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        manager_info.clone(),
+        mint_msg(50),
+    );
+    assert_eq!(contract_result, Err(ContractError::CannotExceedCap {}));
+    let usage: MintUsageResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::MintUsage {}).unwrap()).unwrap();
+    assert_eq!(
+        usage,
+        MintUsageResponse {
+            epoch_limit: Some(epoch_limit),
+            epoch_minted: Uint128::from(60u128),
+            epoch_ends: Some(Expiration::AtTime(env.block.time.plus_seconds(3600))),
+            cap: Some(Uint128::from(250u128)),
+            net_minted: Uint128::from(60u128),
+        }
+    );
+
+    // A new epoch starts once the current one ended
+    env.block.time = env.block.time.plus_seconds(3600);
+    let usage = query_mint_usage(deps.as_ref(), env.clone()).unwrap();
+    assert_eq!(usage.epoch_minted, Uint128::zero());
+    assert_eq!(usage.epoch_ends, None);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        manager_info.clone(),
+        mint_msg(100),
+    )
+    .unwrap();
+
+    // The cap limits the tokens outstanding, whatever the epoch
+    env.block.time = env.block.time.plus_seconds(3600);
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        manager_info.clone(),
+        mint_msg(100),
+    );
+    assert_eq!(contract_result, Err(ContractError::CannotExceedCap {}));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        manager_info.clone(),
+        ExecuteMsg::BurnFrom {
+            owner: "collateral_manager".to_string(),
+            amount: Uint128::from(10u128),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        manager_info.clone(),
+        mint_msg(100),
+    )
+    .unwrap();
+    let usage = query_mint_usage(deps.as_ref(), env.clone()).unwrap();
+    assert_eq!(usage.net_minted, Uint128::from(250u128));
+
+    // Only the admin can change the limits
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        manager_info.clone(),
+        ExecuteMsg::UpdateMintLimits {
+            epoch_limit: None,
+            cap: None,
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateMintLimits {
+            epoch_limit: None,
+            cap: None,
+        },
+    )
+    .unwrap();
+    execute(deps.as_mut(), env, manager_info, mint_msg(1000)).unwrap();
+}
+
+// Stands in for a flash mint receiver contract, returns the messages it sends on the callback
+mod mock_receiver {
+    use super::*;
+    use crate::msg::FlashMintReceiverMsg;
+
+    pub fn execute(msg: &Binary, mint_authority: &str, shortfall: u128) -> Vec<CosmosMsg> {
+        match from_binary(msg).unwrap() {
+            FlashMintReceiverMsg::FlashMintCallback(callback) => {
+                let repay = (callback.amount + callback.fee).u128() - shortfall;
+                vec![Cw20ExecuteMsg::Transfer {
+                    recipient: mint_authority.to_string(),
+                    amount: Uint128::from(repay),
+                }
+                .into_cosmos_msg("fbtc_cw20_address")
+                .unwrap()]
+            }
+        }
+    }
+}
+
+// Applies the messages sender sends to the fbtc token to its mocked balances, the way the token
+// contract would
+fn apply_cw20_msgs(querier: &mut WasmMockQuerier, sender: &str, msgs: &[CosmosMsg]) {
+    let mut add = |address: &str, amount: Uint128, increase: bool| {
+        let balance = querier.token_balance("fbtc_cw20_address", address);
+        let balance = if increase {
+            balance + amount
+        } else {
+            balance.checked_sub(amount).unwrap()
+        };
+        querier.set_token_balance("fbtc_cw20_address", address, balance);
+    };
+    for msg in msgs {
+        let msg = match msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) if contract_addr == "fbtc_cw20_address" => msg,
+            _ => panic!("unexpected message {:?}", msg),
+        };
+        match from_binary(msg).unwrap() {
+            Cw20ExecuteMsg::Mint { recipient, amount } => add(&recipient, amount, true),
+            Cw20ExecuteMsg::Transfer { recipient, amount } => {
+                add(sender, amount, false);
+                add(&recipient, amount, true);
+            }
+            Cw20ExecuteMsg::Burn { amount } => add(sender, amount, false),
+            msg => panic!("unexpected cw20 message {:?}", msg),
+        }
+    }
+}
+
+// Returns the messages of sub_msgs that are not replied to, those are executed right away
+fn plain_msgs(sub_msgs: &[SubMsg]) -> Vec<CosmosMsg> {
+    sub_msgs
+        .iter()
+        .filter(|sub_msg| sub_msg.reply_on == ReplyOn::Never)
+        .map(|sub_msg| sub_msg.msg.clone())
+        .collect()
+}
+
+// Returns the message of the callback to the flash mint receiver in the submessages of response
+fn flash_mint_callback(response: &Response) -> Binary {
+    response
+        .messages
+        .iter()
+        .find_map(|sub_msg| match &sub_msg.msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) if contract_addr == "receiver" => Some(msg.clone()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn flash_mint_reply() -> Reply {
+    Reply {
+        id: FLASH_MINT_REPLY_ID,
+        result: ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    }
+}
+
+#[test]
+fn test_flash_mint() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    let env = mock_env();
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        default_instantiate_msg(),
+    )
+    .unwrap();
+    deps.querier.set_token_info(
+        "fbtc_cw20_address",
+        TokenInfoResponse {
+            name: "fbtc".to_string(),
+            symbol: "fBTC".to_string(),
+            decimals: 6,
+            total_supply: Uint128::zero(),
+        },
+    );
+    deps.querier.set_token_balance(
+        "fbtc_cw20_address",
+        MOCK_CONTRACT_ADDR,
+        Uint128::from(5u128),
+    );
+    // The receiver pays the fee out of its own tokens
+    deps.querier
+        .set_token_balance("fbtc_cw20_address", "receiver", Uint128::from(3u128));
+    let flash_mint_msg = |amount: u128| ExecuteMsg::FlashMint {
+        receiver: "receiver".to_string(),
+        amount: Uint128::from(amount),
+        msg: Binary::from(b"arbitrage".to_vec()),
+    };
+
+    // Flash mints are disabled until the admin enables them
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        flash_mint_msg(1000),
+    );
+    assert_eq!(contract_result, Err(ContractError::FlashMintDisabled {}));
+    let update_msg = ExecuteMsg::UpdateFlashMint {
+        fee_rate: Decimal::permille(3),
+        treasury: "treasury".to_string(),
+    };
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        update_msg.clone(),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateFlashMint {
+            fee_rate: Decimal::percent(101),
+            treasury: "treasury".to_string(),
+        },
+    );
+    assert_eq!(contract_result, Err(ContractError::InvalidFeeRate {}));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        update_msg,
+    )
+    .unwrap();
+    let config: Option<FlashMintConfig> =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::FlashMintConfig {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        config,
+        Some(FlashMintConfig {
+            fee_rate: Decimal::permille(3),
+            treasury: "treasury".to_string(),
+        })
+    );
+
+    // Tokens are minted to the receiver, which is then called back
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        flash_mint_msg(1000),
+    )
+    .unwrap();
+    let callback_msg = FlashMintCallbackMsg {
+        initiator: "user".to_string(),
+        amount: Uint128::from(1000u128),
+        fee: Uint128::from(3u128),
+        msg: Binary::from(b"arbitrage".to_vec()),
+    };
+    assert_eq!(
+        response.messages,
+        vec![
+            SubMsg::new(
+                Cw20ExecuteMsg::Mint {
+                    recipient: "receiver".to_string(),
+                    amount: Uint128::from(1000u128),
+                }
+                .into_cosmos_msg("fbtc_cw20_address")
+                .unwrap()
+            ),
+            SubMsg::reply_on_success(
+                callback_msg.clone().into_cosmos_msg("receiver").unwrap(),
+                FLASH_MINT_REPLY_ID
+            )
+        ]
+    );
+
+    // Only one flash mint can be in progress
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        flash_mint_msg(10),
+    );
+    assert_eq!(contract_result, Err(ContractError::FlashMintInProgress {}));
+
+    // The receiver returns amount plus the fee, which is burned and sent to the treasury
+    apply_cw20_msgs(
+        &mut deps.querier,
+        MOCK_CONTRACT_ADDR,
+        &plain_msgs(&response.messages),
+    );
+    let receiver_msgs =
+        mock_receiver::execute(&flash_mint_callback(&response), MOCK_CONTRACT_ADDR, 0);
+    apply_cw20_msgs(&mut deps.querier, "receiver", &receiver_msgs);
+    let response = reply(deps.as_mut(), env.clone(), flash_mint_reply()).unwrap();
+    assert_eq!(
+        response.messages,
+        vec![
+            SubMsg::new(
+                Cw20ExecuteMsg::Burn {
+                    amount: Uint128::from(1000u128),
+                }
+                .into_cosmos_msg("fbtc_cw20_address")
+                .unwrap()
+            ),
+            SubMsg::new(
+                Cw20ExecuteMsg::Transfer {
+                    recipient: "treasury".to_string(),
+                    amount: Uint128::from(3u128),
+                }
+                .into_cosmos_msg("fbtc_cw20_address")
+                .unwrap()
+            )
+        ]
+    );
+    apply_cw20_msgs(
+        &mut deps.querier,
+        MOCK_CONTRACT_ADDR,
+        &plain_msgs(&response.messages),
+    );
+    let balance = |querier: &WasmMockQuerier, address: &str| {
+        querier.token_balance("fbtc_cw20_address", address).u128()
+    };
+    assert_eq!(balance(&deps.querier, MOCK_CONTRACT_ADDR), 5);
+    assert_eq!(balance(&deps.querier, "receiver"), 0);
+    assert_eq!(balance(&deps.querier, "treasury"), 3);
+    let info = query_mint_authority_info(deps.as_ref()).unwrap();
+    assert_eq!(info.total_minted, Uint128::from(1000u128));
+    assert_eq!(info.total_burned, Uint128::from(1000u128));
+
+    // A receiver that does not return amount plus the fee fails the flash mint
+    deps.querier
+        .set_token_balance("fbtc_cw20_address", "receiver", Uint128::from(3u128));
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        flash_mint_msg(1000),
+    )
+    .unwrap();
+    apply_cw20_msgs(
+        &mut deps.querier,
+        MOCK_CONTRACT_ADDR,
+        &plain_msgs(&response.messages),
+    );
+    let receiver_msgs =
+        mock_receiver::execute(&flash_mint_callback(&response), MOCK_CONTRACT_ADDR, 1);
+    apply_cw20_msgs(&mut deps.querier, "receiver", &receiver_msgs);
+    let contract_result = reply(deps.as_mut(), env, flash_mint_reply());
+    assert_eq!(
+        contract_result,
+        Err(ContractError::FlashMintNotRepaid {
+            expected: Uint128::from(1003u128),
+            returned: Uint128::from(1002u128),
+        })
+    );
+}
@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
+pub use cw_controllers::AdminError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,6 +7,9 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    ControllerError(#[from] AdminError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -23,4 +27,19 @@ pub enum ContractError {
 
     #[error("Minting cannot exceed the cap")]
     CannotExceedCap {},
+
+    #[error("Flash mints are not enabled")]
+    FlashMintDisabled {},
+
+    #[error("Flash mint fee rate can not exceed one")]
+    InvalidFeeRate {},
+
+    #[error("Another flash mint is in progress")]
+    FlashMintInProgress {},
+
+    #[error("Flash mint was not repaid, expected {expected} tokens back but got {returned}")]
+    FlashMintNotRepaid {
+        expected: Uint128,
+        returned: Uint128,
+    },
 }
@@ -1,15 +1,37 @@
-use cosmwasm_std::{StdError, StdResult, Uint128};
+use cosmwasm_std::{to_binary, Binary, CosmosMsg, Decimal, StdError, StdResult, Uint128, WasmMsg};
+use cw0::{Duration, Expiration};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+
+/// At most amount tokens can be minted in every window of length epoch
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct EpochLimit {
+    pub amount: Uint128,
+    pub epoch: Duration,
+}
+/// Where the CW20 token this contract mints comes from
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20Token {
+    Existing {
+        address: String, //Address of a deployed CW20 contract whose minter is this contract
+    },
+    Instantiate {
+        code_id: u64, //Code of cw20-base, instantiated with name, symbol and decimals and this contract as its only minter
+    },
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
 pub struct InstantiateMsg {
-    pub name: String,   //Name of CW20 asset that this contract is the mint authortiy of
-    pub symbol: String, //Symbol of CW20 asset that this contract is the mint authority of
-    pub decimals: u8,   //Number of decimals in the CW20 contract
-    pub address_cw20: String, //Address of CW20 contract
+    pub name: String,    //Name of CW20 asset that this contract is the mint authortiy of
+    pub symbol: String,  //Symbol of CW20 asset that this contract is the mint authority of
+    pub decimals: u8,    //Number of decimals in the CW20 contract
+    pub cw20: Cw20Token, //CW20 contract to mint
     pub address_collateral_manager: String, //Address of the collateral manager contract that requests mints
-    pub total_mint: Uint128,                //Total number of tokens minted so far
+    pub epoch_limit: Option<EpochLimit>, //Limit on the tokens minted per epoch, None for no limit
+    pub cap: Option<Uint128>, //Limit on the tokens minted minus the tokens burned, None for no limit
+    pub admin: Option<String>, //Admin of this contract and of the CW20 contract it instantiates, the sender if None
 }
 
 impl InstantiateMsg {
@@ -59,11 +81,122 @@ pub enum ExecuteMsg {
     MintAndSend {
         recipient: String, //Address where minted tokens are sent to
         amount: Uint128,   //Amount of tokens to mint and send
+    }, // Only the collateral manager and approved minters can mint
+    BurnFrom {
+        owner: String, //Address the tokens are burned from, it has to give this contract an allowance first
+        amount: Uint128, //Amount of tokens to burn
+    }, // Only the collateral manager and approved minters can burn
+    FlashMint {
+        receiver: String, //Contract the tokens are minted to and that is called back
+        amount: Uint128,  //Amount of tokens to mint
+        msg: Binary,      //Passed on to the receiver in the callback
+    }, // Mint to receiver and call it back. It has to return amount plus the fee to this contract in the callback
+    UpdateFlashMint {
+        fee_rate: Decimal,
+        treasury: String,
+    }, // Enable flash mints, their fee is fee_rate (at most one) of the amount and is sent to treasury
+    UpdateMintLimits {
+        epoch_limit: Option<EpochLimit>,
+        cap: Option<Uint128>,
+    }, // Replace both mint limits, None removes a limit. The current epoch is restarted
+    AddMinter {
+        addr: String,
+    }, // Approve addr to mint
+    RemoveMinter {
+        addr: String,
+    },
+    UpdateAdmin {
+        new_admin: String,
     },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
+    /// Return type: MintAuthorityInfo
     MintAuthorityInfo {},
+    /// Return type: AdminResponse
+    Admin {},
+    /// Returns the approved minters, the collateral manager can always mint
+    /// Return type: MintersResponse
+    Minters {},
+    /// Compares the tokens minted minus the tokens burned through this contract with the total
+    /// supply of the cw20 token
+    /// Return type: ReconciliationResponse
+    Reconciliation {},
+    /// Returns the flash mint fee and treasury, None if flash mints are not enabled
+    /// Return type: Option<FlashMintConfig>
+    FlashMintConfig {},
+    /// Returns the mint limits and how much of them is used
+    /// Return type: MintUsageResponse
+    MintUsage {},
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MintersResponse {
+    pub minters: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ReconciliationResponse {
+    pub total_minted: Uint128,
+    pub total_burned: Uint128,
+    pub net_minted: Uint128,
+    pub total_supply: Uint128,
+    /// True if the net amount minted through this contract equals the total supply
+    pub reconciled: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MintUsageResponse {
+    pub epoch_limit: Option<EpochLimit>,
+    /// Tokens minted in the current epoch
+    pub epoch_minted: Uint128,
+    /// End of the current epoch, None if no epoch is running
+    pub epoch_ends: Option<Expiration>,
+    pub cap: Option<Uint128>,
+    pub net_minted: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct FlashMintConfig {
+    pub fee_rate: Decimal,
+    pub treasury: String,
+}
+
+/// FlashMintCallbackMsg should be de/serialized under `FlashMintCallback()` variant in the
+/// ExecuteMsg of the receiver of a flash mint. By the end of the callback, the receiver has to have
+/// transferred amount plus fee of the token back to the mint authority
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub struct FlashMintCallbackMsg {
+    pub initiator: String,
+    pub amount: Uint128,
+    pub fee: Uint128,
+    pub msg: Binary,
+}
+
+impl FlashMintCallbackMsg {
+    /// serializes the message
+    pub fn into_binary(self) -> StdResult<Binary> {
+        let msg = FlashMintReceiverMsg::FlashMintCallback(self);
+        to_binary(&msg)
+    }
+
+    /// creates a cosmos_msg sending this struct to the named contract
+    pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+        let msg = self.into_binary()?;
+        let execute = WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg,
+            funds: vec![],
+        };
+        Ok(execute.into())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashMintReceiverMsg {
+    FlashMintCallback(FlashMintCallbackMsg),
 }
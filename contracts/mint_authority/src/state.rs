@@ -1,8 +1,12 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::Item;
+use crate::msg::EpochLimit;
+
+use cosmwasm_std::{Addr, Decimal, Empty, Uint128};
+use cw0::Expiration;
+use cw_controllers::Admin;
+use cw_storage_plus::{Item, Map};
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
@@ -10,9 +14,52 @@ pub struct MintAuthorityInfo {
     pub name: String,   //Name of CW20 asset that this contract is the mint authority of
     pub symbol: String, //Symbol of CW20 asset that this contract is the mint authority of
     pub decimals: u8,   //Number of decimals in the CW20 contract
-    pub address_cw20: Addr, //Address of CW20 contract
+    pub address_cw20: Option<Addr>, //Address of CW20 contract, None until the token instantiated by this contract replies
     pub address_collateral_manager: Addr, //Address of the collateral manager contract that requests mints
-    pub total_mint: Uint128,              //Total number of tokens minted so far by this contract
+    pub total_minted: Uint128,            //Total number of tokens minted so far by this contract
+    pub total_burned: Uint128,            //Total number of tokens burned so far by this contract
 }
 
 pub const MINT_AUTHORITY_INFO: Item<MintAuthorityInfo> = Item::new("mint_authority_info");
+
+// Admin of contract can approve or revoke minters
+pub const ADMIN: Admin = Admin::new("admin");
+
+// Addresses approved by the admin to mint, besides the collateral manager
+pub const MINTERS: Map<&Addr, Empty> = Map::new("minters");
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MintLimits {
+    pub epoch_limit: Option<EpochLimit>, //Limit on the tokens minted per epoch
+    pub cap: Option<Uint128>,            //Limit on the tokens minted minus the tokens burned
+}
+
+pub const MINT_LIMITS: Item<MintLimits> = Item::new("mint_limits");
+
+// Tokens minted in the epoch that ends at ends. A mint after the end starts a new epoch
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct EpochUsage {
+    pub minted: Uint128,
+    pub ends: Expiration,
+}
+
+pub const EPOCH_USAGE: Item<EpochUsage> = Item::new("epoch_usage");
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct FlashMintSettings {
+    pub fee_rate: Decimal, //Share of a flash mint charged as fee
+    pub treasury: Addr,    //Address the fees are sent to
+}
+
+// Flash mints are disabled until the admin sets their fee
+pub const FLASH_MINT_SETTINGS: Item<FlashMintSettings> = Item::new("flash_mint_settings");
+
+// Flash mint waiting for its callback to return
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PendingFlashMint {
+    pub amount: Uint128,
+    pub fee: Uint128,
+    pub balance_before: Uint128, //Balance of this contract in the token before the flash mint
+}
+
+pub const PENDING_FLASH_MINT: Item<PendingFlashMint> = Item::new("pending_flash_mint");
@@ -3,7 +3,12 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use steadifi::oracle_manager::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use steadifi::oracle_manager::msg::{
+    AssetsResponse, CircuitBreakerResponse, ConfigResponse, ExecuteMsg, FeederReport,
+    FeedersResponse, InstantiateMsg, OraclesResponse, PriceObservation, PriceResponse,
+    PricesResponse, QueryMsg,
+};
+use steadifi::oracle_manager::{AggregationPolicy, PriceHistoryConfig};
 fn main() {
     let mut out_dir = current_dir().unwrap();
     out_dir.push("schema");
@@ -12,4 +17,15 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(PriceResponse), &out_dir);
+    export_schema(&schema_for!(PricesResponse), &out_dir);
+    export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(OraclesResponse), &out_dir);
+    export_schema(&schema_for!(AssetsResponse), &out_dir);
+    export_schema(&schema_for!(AggregationPolicy), &out_dir);
+    export_schema(&schema_for!(FeedersResponse), &out_dir);
+    export_schema(&schema_for!(FeederReport), &out_dir);
+    export_schema(&schema_for!(CircuitBreakerResponse), &out_dir);
+    export_schema(&schema_for!(PriceHistoryConfig), &out_dir);
+    export_schema(&schema_for!(PriceObservation), &out_dir);
 }
@@ -0,0 +1,69 @@
+use cosmwasm_std::Uint128;
+
+use crate::error::ContractError;
+use steadifi::mars_protocol_math::Decimal;
+use steadifi::oracle_manager::msg::PriceResponse;
+use steadifi::oracle_manager::AggregationPolicy;
+
+/// Price reported by a single source and the time, in seconds since epoch, it was last updated
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourcePrice {
+    pub price: Decimal,
+    pub last_updated: u64,
+}
+
+/// Returns the median of prices, the mean of the two middle prices for an even count. None if
+/// prices is empty
+pub fn median(prices: &mut [Decimal]) -> Option<Decimal> {
+    if prices.is_empty() {
+        return None;
+    }
+    prices.sort();
+    let index = prices.len() / 2;
+    if prices.len() % 2 == 1 {
+        Some(prices[index])
+    } else {
+        Some((prices[index - 1] + prices[index]) / Uint128::from(2u128))
+    }
+}
+
+/// Combines the prices of the valid sources of asset_name according to policy. The price counts as
+/// updated when the oldest of the sources used was
+pub fn aggregate_prices(
+    asset_name: &str,
+    mut sources: Vec<SourcePrice>,
+    policy: &AggregationPolicy,
+) -> Result<PriceResponse, ContractError> {
+    let not_satisfied = |valid_sources: usize| ContractError::AggregationPolicyNotSatisfied {
+        asset_name: asset_name.to_string(),
+        valid_sources: valid_sources as u32,
+        min_valid_sources: policy.min_valid_sources,
+    };
+    if sources.is_empty() || sources.len() < policy.min_valid_sources as usize {
+        return Err(not_satisfied(sources.len()));
+    }
+    let mut prices: Vec<Decimal> = sources.iter().map(|source| source.price).collect();
+    let mut price = median(&mut prices).ok_or_else(|| not_satisfied(0))?;
+
+    if let Some(max_deviation) = policy.max_deviation {
+        let max_distance = price.checked_mul(max_deviation)?;
+        sources.retain(|source| source.price.abs_diff(price) <= max_distance);
+        if sources.is_empty() || sources.len() < policy.min_valid_sources as usize {
+            return Err(not_satisfied(sources.len()));
+        }
+        prices = sources.iter().map(|source| source.price).collect();
+        price = median(&mut prices).ok_or_else(|| not_satisfied(0))?;
+    }
+    // median sorted the prices
+    Ok(PriceResponse {
+        price,
+        last_updated: sources
+            .iter()
+            .map(|source| source.last_updated)
+            .min()
+            .unwrap_or_default(),
+        sources_used: sources.len() as u32,
+        min: prices[0],
+        max: prices[prices.len() - 1],
+    })
+}
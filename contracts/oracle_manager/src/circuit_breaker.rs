@@ -0,0 +1,77 @@
+use cosmwasm_std::Storage;
+
+use crate::error::ContractError;
+use crate::state::{ACCEPTED_PRICE, BREAKER_TRIPPED_AT, CIRCUIT_BREAKER};
+use steadifi::mars_protocol_math::Decimal;
+use steadifi::oracle_manager::msg::AcceptedPrice;
+
+/// Fails if the circuit breaker of asset_name is tripped or if price at time now would trip it.
+/// Assets without a circuit breaker accept any price
+pub fn check_circuit_breaker(
+    storage: &dyn Storage,
+    asset_name: &str,
+    price: Decimal,
+    now: u64,
+) -> Result<(), ContractError> {
+    let circuit_breaker = match CIRCUIT_BREAKER.may_load(storage, asset_name)? {
+        Some(circuit_breaker) => circuit_breaker,
+        None => return Ok(()),
+    };
+    let tripped = || ContractError::CircuitBreakerTripped {
+        asset_name: asset_name.to_string(),
+    };
+    if let Some(tripped_at) = BREAKER_TRIPPED_AT.may_load(storage, asset_name)? {
+        if now < tripped_at.saturating_add(circuit_breaker.window) {
+            return Err(tripped());
+        }
+    }
+    // Prices may move freely once the accepted price is older than the window
+    if let Some(accepted_price) = ACCEPTED_PRICE.may_load(storage, asset_name)? {
+        if now.saturating_sub(accepted_price.time) < circuit_breaker.window {
+            if price.abs_diff(accepted_price.price)
+                > accepted_price
+                    .price
+                    .checked_mul(circuit_breaker.max_deviation)?
+            {
+                return Err(tripped());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Accepts price as the price of asset_name at time now and resets its circuit breaker
+pub fn accept_price(
+    storage: &mut dyn Storage,
+    asset_name: &str,
+    price: Decimal,
+    now: u64,
+) -> Result<(), ContractError> {
+    ACCEPTED_PRICE.save(storage, asset_name, &AcceptedPrice { price, time: now })?;
+    BREAKER_TRIPPED_AT.remove(storage, asset_name);
+    Ok(())
+}
+
+/// Accepts price as the price of asset_name at time now unless it trips the circuit breaker, in
+/// which case the breaker is tripped. Returns whether the price was accepted
+pub fn record_price(
+    storage: &mut dyn Storage,
+    asset_name: &str,
+    price: Decimal,
+    now: u64,
+) -> Result<bool, ContractError> {
+    match check_circuit_breaker(storage, asset_name, price, now) {
+        Ok(()) => {
+            accept_price(storage, asset_name, price, now)?;
+            Ok(true)
+        }
+        Err(ContractError::CircuitBreakerTripped { .. }) => {
+            // A breaker that is already tripped keeps the time it tripped at
+            if BREAKER_TRIPPED_AT.may_load(storage, asset_name)?.is_none() {
+                BREAKER_TRIPPED_AT.save(storage, asset_name, &now)?;
+            }
+            Ok(false)
+        }
+        Err(err) => Err(err),
+    }
+}
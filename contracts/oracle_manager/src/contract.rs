@@ -1,32 +1,60 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+    to_binary, Addr, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Order, Response, StdError,
+    StdResult,
 };
 use cw0::maybe_addr;
+use cw_storage_plus::Bound;
 use terra_cosmwasm::TerraQuerier; //TODO: What the hell is this
 
+use crate::aggregation::{aggregate_prices, SourcePrice};
+use crate::circuit_breaker::{accept_price, check_circuit_breaker, record_price};
 use crate::error::ContractError;
-use crate::state::{ADMIN, ORACLE};
+use crate::history::{
+    query_history_twap_price, query_price_at, record_observation, set_price_history_config,
+};
+use crate::state::{
+    Config, ACCEPTED_PRICE, ADMIN, AGGREGATION_POLICY, BREAKER_TRIPPED_AT, CIRCUIT_BREAKER, CONFIG,
+    FEEDERS, FEEDER_REPORTS, GUARDIAN, ORACLE, PRICE_HISTORY_CONFIG,
+};
+use crate::twap::{query_astroport_twap_price, snapshot_cumulative_prices};
 use steadifi::mars_protocol_math::Decimal;
-use steadifi::oracle_manager::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use steadifi::oracle_manager::{Oracle, OracleUnvalidated};
+use steadifi::oracle_manager::msg::{
+    AssetPrice, AssetsResponse, CircuitBreakerResponse, ConfigResponse, ExecuteMsg, FeederReport,
+    FeedersResponse, InstantiateMsg, OraclesResponse, PriceResponse, PriceResult, PricesResponse,
+    QueryMsg,
+};
+use steadifi::oracle_manager::{
+    AggregationPolicy, CircuitBreaker, Oracle, OracleUnvalidated, PriceHistoryConfig,
+};
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    _msg: InstantiateMsg,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    ADMIN.set(deps, Some(info.sender))?;
+    ADMIN.set(deps.branch(), Some(info.sender))?;
+    GUARDIAN.set(deps.branch(), None)?;
+    if msg.quote_asset.is_empty() {
+        return Err(ContractError::EmptyQuoteAsset {});
+    }
+    let config = Config {
+        quote_asset: msg.quote_asset,
+    };
+    CONFIG.save(deps.storage, &config)?;
     Ok(Response::default())
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
@@ -35,14 +63,83 @@ pub fn execute(
             asset_name,
             oracle_unvalidated,
         } => execute_add_asset_price_oracle(deps, info, asset_name, oracle_unvalidated),
+        ExecuteMsg::RemoveAssetPriceOracle { asset_name, index } => {
+            execute_remove_asset_price_oracle(deps, info, asset_name, index)
+        }
+        ExecuteMsg::ReplaceAssetPriceOracle {
+            asset_name,
+            index,
+            oracle_unvalidated,
+        } => execute_replace_asset_price_oracle(deps, info, asset_name, index, oracle_unvalidated),
+        ExecuteMsg::ReorderAssetPriceOracles { asset_name, order } => {
+            execute_reorder_asset_price_oracles(deps, info, asset_name, order)
+        }
+        ExecuteMsg::SnapshotCumulativePrices { asset_name } => {
+            execute_snapshot_cumulative_prices(deps, env, asset_name)
+        }
+        ExecuteMsg::SetAggregationPolicy { asset_name, policy } => {
+            execute_set_aggregation_policy(deps, info, asset_name, policy)
+        }
+        ExecuteMsg::AddFeeder { address } => execute_add_feeder(deps, info, address),
+        ExecuteMsg::RemoveFeeder { address } => execute_remove_feeder(deps, info, address),
+        ExecuteMsg::SubmitPrice {
+            asset_name,
+            price,
+            timestamp,
+        } => execute_submit_price(deps, env, info, asset_name, price, timestamp),
+        ExecuteMsg::SetCircuitBreaker {
+            asset_name,
+            circuit_breaker,
+        } => execute_set_circuit_breaker(deps, info, asset_name, circuit_breaker),
+        ExecuteMsg::RecordPrice { asset_name } => execute_record_price(deps, env, asset_name),
+        ExecuteMsg::ConfirmPrice { asset_name } => {
+            execute_confirm_price(deps, env, info, asset_name)
+        }
+        ExecuteMsg::UpdateGuardian { guardian } => execute_update_guardian(deps, info, guardian),
+        ExecuteMsg::SetPriceHistoryConfig { asset_name, config } => {
+            execute_set_price_history_config(deps, info, asset_name, config)
+        }
         ExecuteMsg::UpdateAdmin { new_admin } => execute_update_admin(deps, info, new_admin),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::GetPrice { asset_name } => to_binary(&query_get_price(deps, asset_name)?),
+        QueryMsg::GetPrice { asset_name } => to_binary(&query_get_price(deps, &env, asset_name)?),
+        QueryMsg::GetPrices { asset_names } => {
+            to_binary(&query_get_prices(deps, &env, asset_names)?)
+        }
+        QueryMsg::GetTwapPrice {
+            asset_name,
+            start,
+            end,
+        } => to_binary(&query_get_twap_price(deps, &env, asset_name, start, end)?),
+        QueryMsg::PriceAt {
+            asset_name,
+            timestamp,
+        } => to_binary(&query_price_at(deps.storage, &asset_name, timestamp)?),
+        QueryMsg::PriceHistoryConfig { asset_name } => {
+            to_binary(&PRICE_HISTORY_CONFIG.may_load(deps.storage, asset_name.as_str())?)
+        }
+        QueryMsg::AggregationPolicy { asset_name } => {
+            to_binary(&query_aggregation_policy(deps, asset_name)?)
+        }
+        QueryMsg::ListOracles { asset_name } => to_binary(&query_list_oracles(deps, asset_name)?),
+        QueryMsg::AllAssets { start_after, limit } => {
+            to_binary(&query_all_assets(deps, start_after, limit)?)
+        }
+        QueryMsg::Feeders { start_after, limit } => {
+            to_binary(&query_feeders(deps, start_after, limit)?)
+        }
+        QueryMsg::FeederReport { feeder, asset_name } => {
+            to_binary(&query_feeder_report(deps, feeder, asset_name)?)
+        }
+        QueryMsg::CircuitBreaker { asset_name } => {
+            to_binary(&query_circuit_breaker(deps, asset_name)?)
+        }
+        QueryMsg::Guardian {} => to_binary(&GUARDIAN.query_admin(deps)?),
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
     }
 }
 
@@ -67,69 +164,553 @@ fn execute_add_asset_price_oracle(
     let mut oracle_list: Vec<Oracle> = ORACLE
         .may_load(deps.storage, asset_name.as_str())?
         .unwrap_or_default();
+    if oracle_list
+        .iter()
+        .any(|oracle| oracle.is_same_source(&new_oracle))
+    {
+        return Err(ContractError::DuplicateOracle { asset_name });
+    }
     oracle_list.push(new_oracle.clone());
+    ORACLE.save(deps.storage, asset_name.as_str(), &oracle_list)?;
     let response = Response::new()
-        .add_attribute("Asset name", format!("{}", asset_name))
+        .add_attribute("action", "add_asset_price_oracle")
+        .add_attribute("Asset name", asset_name)
         .add_attribute("Oracle details", format!("{:?}", new_oracle));
     Ok(response)
 }
 
-fn query_get_price(deps: Deps, asset_name: String) -> StdResult<Decimal> {
-    if let Ok(oracle_list) = ORACLE.load(deps.storage, asset_name.as_str()) {
-        let mut price_list = Vec::new();
-        for oracle in &oracle_list {
-            match oracle {
-                Oracle::Fixed { price } => {
-                    price_list.push(price.clone());
-                }
-                Oracle::Native { denom } => {
-                    let terra_querier = TerraQuerier::new(&deps.querier);
-
-                    // NOTE: Exchange rate returns how much of the quote (second argument) is required to
-                    // buy one unit of the base_denom (first argument).
-                    // We want to know how much uusd we need to buy 1 of the target currency
-                    let native_price_query = terra_querier
-                        .query_exchange_rates(denom.to_owned(), vec!["uusd".to_string()])?
-                        .exchange_rates
-                        .pop();
-                    //TODO: Fix this and dont use this third party library
-                    if let Some(exchange_rate_item) = native_price_query {
-                        price_list.push(exchange_rate_item.exchange_rate.into())
-                    } else {
-                        return Err(StdError::NotFound {
-                            kind: String::from("Native price was not found"),
-                        });
-                    }
-                }
+/// Removing the last oracle of an asset removes the asset
+fn execute_remove_asset_price_oracle(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_name: String,
+    index: u32,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut oracle_list = load_oracle_list(deps.as_ref(), &asset_name)?;
+    if index as usize >= oracle_list.len() {
+        return Err(ContractError::OracleIndexOutOfRange { asset_name, index });
+    }
+    let removed_oracle = oracle_list.remove(index as usize);
+    if oracle_list.is_empty() {
+        ORACLE.remove(deps.storage, asset_name.as_str());
+    } else {
+        ORACLE.save(deps.storage, asset_name.as_str(), &oracle_list)?;
+    }
+    let response = Response::new()
+        .add_attribute("action", "remove_asset_price_oracle")
+        .add_attribute("Asset name", asset_name)
+        .add_attribute("Oracle details", format!("{:?}", removed_oracle));
+    Ok(response)
+}
 
-                _ => {
-                    // TODO handle other cases of oracles
-                    price_list.push(Decimal::zero())
+fn execute_replace_asset_price_oracle(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_name: String,
+    index: u32,
+    oracle_unvalidated: OracleUnvalidated,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let new_oracle = oracle_unvalidated.to_validated(deps.api)?;
+    let mut oracle_list = load_oracle_list(deps.as_ref(), &asset_name)?;
+    if index as usize >= oracle_list.len() {
+        return Err(ContractError::OracleIndexOutOfRange { asset_name, index });
+    }
+    // The replaced oracle may be replaced by the same source, any other copy is a duplicate
+    if oracle_list
+        .iter()
+        .enumerate()
+        .any(|(i, oracle)| i != index as usize && oracle.is_same_source(&new_oracle))
+    {
+        return Err(ContractError::DuplicateOracle { asset_name });
+    }
+    oracle_list[index as usize] = new_oracle.clone();
+    ORACLE.save(deps.storage, asset_name.as_str(), &oracle_list)?;
+    let response = Response::new()
+        .add_attribute("action", "replace_asset_price_oracle")
+        .add_attribute("Asset name", asset_name)
+        .add_attribute("Oracle details", format!("{:?}", new_oracle));
+    Ok(response)
+}
+
+fn execute_reorder_asset_price_oracles(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_name: String,
+    order: Vec<u32>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let oracle_list = load_oracle_list(deps.as_ref(), &asset_name)?;
+    let mut sorted_order = order.clone();
+    sorted_order.sort_unstable();
+    if !sorted_order
+        .iter()
+        .enumerate()
+        .all(|(i, index)| i == *index as usize)
+        || sorted_order.len() != oracle_list.len()
+    {
+        return Err(ContractError::InvalidOracleOrder {});
+    }
+    let reordered_list: Vec<Oracle> = order
+        .iter()
+        .map(|index| oracle_list[*index as usize].clone())
+        .collect();
+    ORACLE.save(deps.storage, asset_name.as_str(), &reordered_list)?;
+    let response = Response::new()
+        .add_attribute("action", "reorder_asset_price_oracles")
+        .add_attribute("Asset name", asset_name);
+    Ok(response)
+}
+
+fn execute_snapshot_cumulative_prices(
+    deps: DepsMut,
+    env: Env,
+    asset_name: String,
+) -> Result<Response, ContractError> {
+    let oracle_list = load_oracle_list(deps.as_ref(), &asset_name)?;
+    let mut response = Response::new()
+        .add_attribute("action", "snapshot_cumulative_prices")
+        .add_attribute("Asset name", &asset_name);
+    for oracle in &oracle_list {
+        if let Oracle::AstroportTWAP { address, window } = oracle {
+            snapshot_cumulative_prices(deps.storage, &deps.querier, &env, address, *window)?;
+            response = response.add_attribute("pair", address);
+        }
+    }
+    Ok(response)
+}
+
+fn execute_set_aggregation_policy(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_name: String,
+    policy: AggregationPolicy,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    policy.validate()?;
+    AGGREGATION_POLICY.save(deps.storage, asset_name.as_str(), &policy)?;
+    let response = Response::new()
+        .add_attribute("action", "set_aggregation_policy")
+        .add_attribute("Asset name", asset_name)
+        .add_attribute("Aggregation policy", format!("{:?}", policy));
+    Ok(response)
+}
+
+fn execute_add_feeder(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let feeder = deps.api.addr_validate(&address)?;
+    if FEEDERS.has(deps.storage, &feeder) {
+        return Err(ContractError::DuplicateFeeder { address });
+    }
+    FEEDERS.save(deps.storage, &feeder, &Empty {})?;
+    Ok(Response::new()
+        .add_attribute("action", "add_feeder")
+        .add_attribute("feeder", feeder))
+}
+
+fn execute_remove_feeder(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let feeder = deps.api.addr_validate(&address)?;
+    if !FEEDERS.has(deps.storage, &feeder) {
+        return Err(ContractError::FeederNotFound { address });
+    }
+    FEEDERS.remove(deps.storage, &feeder);
+    Ok(Response::new()
+        .add_attribute("action", "remove_feeder")
+        .add_attribute("feeder", feeder))
+}
+
+fn execute_submit_price(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_name: String,
+    price: Decimal,
+    timestamp: u64,
+) -> Result<Response, ContractError> {
+    if FEEDERS.may_load(deps.storage, &info.sender)?.is_none() {
+        return Err(ContractError::Unauthorized {});
+    }
+    if timestamp > env.block.time.seconds() {
+        return Err(ContractError::InvalidReportTime {});
+    }
+    let key = (asset_name.as_str(), &info.sender);
+    if let Some(previous_report) = FEEDER_REPORTS.may_load(deps.storage, key)? {
+        if timestamp <= previous_report.timestamp {
+            return Err(ContractError::InvalidReportTime {});
+        }
+    }
+    FEEDER_REPORTS.save(deps.storage, key, &FeederReport { price, timestamp })?;
+    Ok(Response::new()
+        .add_attribute("action", "submit_price")
+        .add_attribute("Asset name", asset_name)
+        .add_attribute("feeder", info.sender)
+        .add_attribute("price", price.to_string())
+        .add_attribute("timestamp", timestamp.to_string()))
+}
+
+fn execute_set_circuit_breaker(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_name: String,
+    circuit_breaker: Option<CircuitBreaker>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    match &circuit_breaker {
+        Some(circuit_breaker) => {
+            circuit_breaker.validate()?;
+            CIRCUIT_BREAKER.save(deps.storage, asset_name.as_str(), circuit_breaker)?;
+        }
+        None => {
+            CIRCUIT_BREAKER.remove(deps.storage, asset_name.as_str());
+            BREAKER_TRIPPED_AT.remove(deps.storage, asset_name.as_str());
+        }
+    }
+    Ok(Response::new()
+        .add_attribute("action", "set_circuit_breaker")
+        .add_attribute("Asset name", asset_name)
+        .add_attribute("Circuit breaker", format!("{:?}", circuit_breaker)))
+}
+
+fn execute_record_price(
+    deps: DepsMut,
+    env: Env,
+    asset_name: String,
+) -> Result<Response, ContractError> {
+    let now = env.block.time.seconds();
+    let price = query_current_price(deps.as_ref(), &env, &asset_name)?.price;
+    let accepted = record_price(deps.storage, &asset_name, price, now)?;
+    // Prices rejected by the circuit breaker are kept out of the price history
+    let observed = accepted && record_observation(deps.storage, &asset_name, price, now)?;
+    Ok(Response::new()
+        .add_attribute("action", "record_price")
+        .add_attribute("Asset name", asset_name)
+        .add_attribute("price", price.to_string())
+        .add_attribute("accepted", accepted.to_string())
+        .add_attribute("observed", observed.to_string()))
+}
+
+fn execute_confirm_price(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_name: String,
+) -> Result<Response, ContractError> {
+    if !ADMIN.is_admin(deps.as_ref(), &info.sender)?
+        && !GUARDIAN.is_admin(deps.as_ref(), &info.sender)?
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+    let now = env.block.time.seconds();
+    let price = query_current_price(deps.as_ref(), &env, &asset_name)?.price;
+    accept_price(deps.storage, &asset_name, price, now)?;
+    let observed = record_observation(deps.storage, &asset_name, price, now)?;
+    Ok(Response::new()
+        .add_attribute("action", "confirm_price")
+        .add_attribute("Asset name", asset_name)
+        .add_attribute("price", price.to_string())
+        .add_attribute("observed", observed.to_string()))
+}
+
+fn execute_update_guardian(
+    deps: DepsMut,
+    info: MessageInfo,
+    guardian: Option<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let guardian = maybe_addr(deps.api, guardian)?;
+    let guardian_attribute = guardian
+        .as_ref()
+        .map(|guardian| guardian.to_string())
+        .unwrap_or_else(|| "none".to_string());
+    GUARDIAN.set(deps, guardian)?;
+    Ok(Response::new()
+        .add_attribute("action", "update_guardian")
+        .add_attribute("guardian", guardian_attribute))
+}
+
+fn execute_set_price_history_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_name: String,
+    config: Option<PriceHistoryConfig>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if let Some(config) = &config {
+        config.validate()?;
+    }
+    set_price_history_config(deps.storage, &asset_name, config.clone())?;
+    Ok(Response::new()
+        .add_attribute("action", "set_price_history_config")
+        .add_attribute("Asset name", asset_name)
+        .add_attribute("Price history config", format!("{:?}", config)))
+}
+
+fn load_oracle_list(deps: Deps, asset_name: &str) -> Result<Vec<Oracle>, ContractError> {
+    ORACLE
+        .may_load(deps.storage, asset_name)?
+        .ok_or_else(|| ContractError::OracleNotFound {
+            asset_name: asset_name.to_string(),
+        })
+}
+
+fn query_aggregation_policy(deps: Deps, asset_name: String) -> StdResult<AggregationPolicy> {
+    Ok(AGGREGATION_POLICY
+        .may_load(deps.storage, asset_name.as_str())?
+        .unwrap_or_default())
+}
+
+fn query_list_oracles(deps: Deps, asset_name: String) -> StdResult<OraclesResponse> {
+    let oracles = ORACLE
+        .may_load(deps.storage, asset_name.as_str())?
+        .unwrap_or_default();
+    Ok(OraclesResponse {
+        asset_name,
+        oracles,
+    })
+}
+
+fn query_all_assets(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AssetsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let assets: StdResult<Vec<String>> = ORACLE
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|key| String::from_utf8(key).map_err(Into::into))
+        .collect();
+    Ok(AssetsResponse { assets: assets? })
+}
+
+fn query_feeders(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<FeedersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let feeders: StdResult<Vec<String>> = FEEDERS
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|key| String::from_utf8(key).map_err(Into::into))
+        .collect();
+    Ok(FeedersResponse { feeders: feeders? })
+}
+
+fn query_feeder_report(
+    deps: Deps,
+    feeder: String,
+    asset_name: String,
+) -> StdResult<Option<FeederReport>> {
+    let feeder = deps.api.addr_validate(&feeder)?;
+    FEEDER_REPORTS.may_load(deps.storage, (asset_name.as_str(), &feeder))
+}
+
+fn query_circuit_breaker(deps: Deps, asset_name: String) -> StdResult<CircuitBreakerResponse> {
+    Ok(CircuitBreakerResponse {
+        circuit_breaker: CIRCUIT_BREAKER.may_load(deps.storage, asset_name.as_str())?,
+        accepted_price: ACCEPTED_PRICE.may_load(deps.storage, asset_name.as_str())?,
+        tripped_at: BREAKER_TRIPPED_AT.may_load(deps.storage, asset_name.as_str())?,
+    })
+}
+
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse {
+        quote_asset: config.quote_asset,
+    })
+}
+
+/// Returns the current price of asset_name unless its circuit breaker rejects it
+fn query_get_price(deps: Deps, env: &Env, asset_name: String) -> StdResult<PriceResponse> {
+    let price_response = query_current_price(deps, env, &asset_name)?;
+    check_circuit_breaker(
+        deps.storage,
+        &asset_name,
+        price_response.price,
+        env.block.time.seconds(),
+    )
+    .map_err(|err| StdError::generic_err(err.to_string()))?;
+    Ok(price_response)
+}
+
+/// Returns the price of asset_name combined from its sources, ignoring the circuit breaker
+fn query_current_price(deps: Deps, env: &Env, asset_name: &str) -> StdResult<PriceResponse> {
+    let quote_asset = CONFIG.load(deps.storage)?.quote_asset;
+    // The quote asset is always worth exactly one unit of itself
+    if asset_name == quote_asset {
+        return Ok(PriceResponse::exact(
+            Decimal::one(),
+            env.block.time.seconds(),
+        ));
+    }
+    let oracle_list =
+        ORACLE
+            .may_load(deps.storage, asset_name)?
+            .ok_or_else(|| StdError::NotFound {
+                kind: format!("Oracle list is empty for asset {}", asset_name),
+            })?;
+    // Sources that fail to price are left out, the aggregation policy decides if the rest suffice
+    let price_list: Vec<SourcePrice> = oracle_list
+        .iter()
+        .filter_map(|oracle| query_oracle_prices(deps, env, oracle, asset_name, &quote_asset).ok())
+        .flatten()
+        .collect();
+    let policy = AGGREGATION_POLICY
+        .may_load(deps.storage, asset_name)?
+        .unwrap_or_default();
+    aggregate_prices(asset_name, price_list, &policy)
+        .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+/// Returns the prices oracle reports for asset_name. Every oracle reports a single price except
+/// feeder oracles, which report one price per fresh feeder report. Only feeder reports can be older
+/// than the current block
+fn query_oracle_prices(
+    deps: Deps,
+    env: &Env,
+    oracle: &Oracle,
+    asset_name: &str,
+    quote_asset: &str,
+) -> StdResult<Vec<SourcePrice>> {
+    let now = env.block.time.seconds();
+    let current_price = |price: Decimal| SourcePrice {
+        price,
+        last_updated: now,
+    };
+    match oracle {
+        Oracle::Fixed { price } => Ok(vec![current_price(*price)]),
+        Oracle::Native { denom } => {
+            let terra_querier = TerraQuerier::new(&deps.querier);
+
+            // NOTE: Exchange rate returns how much of the quote (second argument) is required to
+            // buy one unit of the base_denom (first argument).
+            // We want to know how much of the quote asset we need to buy 1 of the target currency
+            let native_price_query = terra_querier
+                .query_exchange_rates(denom.to_owned(), vec![quote_asset.to_string()])?
+                .exchange_rates
+                .pop();
+            //TODO: Fix this and dont use this third party library
+            if let Some(exchange_rate_item) = native_price_query {
+                Ok(vec![current_price(exchange_rate_item.exchange_rate.into())])
+            } else {
+                Err(StdError::NotFound {
+                    kind: String::from("Native price was not found"),
+                })
+            }
+        }
+        Oracle::AstroportTWAP { address, window } => Ok(vec![current_price(
+            query_astroport_twap_price(deps, env, address, *window, asset_name, quote_asset)?,
+        )]),
+        Oracle::Feeder { max_age } => {
+            let mut prices = vec![];
+            for item in
+                FEEDER_REPORTS
+                    .prefix(asset_name)
+                    .range(deps.storage, None, None, Order::Ascending)
+            {
+                let (feeder, report) = item?;
+                // Reports of removed feeders are kept but no longer used
+                let feeder = Addr::unchecked(String::from_utf8(feeder)?);
+                if now - report.timestamp <= *max_age
+                    && FEEDERS.may_load(deps.storage, &feeder)?.is_some()
+                {
+                    prices.push(SourcePrice {
+                        price: report.price,
+                        last_updated: report.timestamp,
+                    });
                 }
             }
+            Ok(prices)
         }
-        Ok(get_median(&mut price_list))
-    } else {
-        Err(StdError::NotFound {
-            kind: String::from(format!("Oracle list is empty for asset {}", asset_name)),
-        })
     }
 }
 
-fn get_median(vec: &mut Vec<Decimal>) -> Decimal {
-    if vec.is_empty() {
-        return Decimal::zero();
+/// Assets with a price history are averaged over their recorded prices. Without a history a TWAP
+/// is only available for assets whose price can not change, i.e. the quote asset and assets priced
+/// by fixed oracles only
+fn query_get_twap_price(
+    deps: Deps,
+    env: &Env,
+    asset_name: String,
+    start: u64,
+    end: u64,
+) -> StdResult<Decimal> {
+    if start > end {
+        return Err(StdError::generic_err(
+            "TWAP period has to end after it starts",
+        ));
     }
-    if vec.len() == 1 {
-        return vec[0];
+    let quote_asset = CONFIG.load(deps.storage)?.quote_asset;
+    if asset_name == quote_asset {
+        return Ok(Decimal::one());
     }
-    vec.sort();
-    let index = vec.len() / 2;
-    vec[index]
-    //TODO: Handle even cases properly
-    //if vec.len() % 2 == 1 {
-    //    vec[index]
-    //} else {
-    //   Decimal::from_ratio(Uint128::one(),Uint128::one()).mul(vec[index - 1] + vec[index]).into()
-    //}
+    if PRICE_HISTORY_CONFIG.has(deps.storage, asset_name.as_str()) {
+        // Recorded prices only say something about the past
+        if end > env.block.time.seconds() {
+            return Err(StdError::generic_err(
+                "TWAP period can not end after the current block",
+            ));
+        }
+        return query_history_twap_price(deps.storage, &asset_name, start, end);
+    }
+    let oracle_list = ORACLE
+        .may_load(deps.storage, asset_name.as_str())?
+        .unwrap_or_default();
+    let mut price_list = Vec::new();
+    for oracle in &oracle_list {
+        match oracle {
+            Oracle::Fixed { price } => price_list.push(SourcePrice {
+                price: *price,
+                last_updated: end,
+            }),
+            _ => {
+                return Err(StdError::NotFound {
+                    kind: format!("Price history of asset {}", asset_name),
+                })
+            }
+        }
+    }
+    if price_list.is_empty() {
+        return Err(StdError::NotFound {
+            kind: format!("Oracle list is empty for asset {}", asset_name),
+        });
+    }
+    let policy = AGGREGATION_POLICY
+        .may_load(deps.storage, asset_name.as_str())?
+        .unwrap_or_default();
+    aggregate_prices(&asset_name, price_list, &policy)
+        .map(|price_response| price_response.price)
+        .map_err(|err| StdError::generic_err(err.to_string()))
 }
+
+/// Assets that fail to price are reported inline, so one bad asset does not fail the whole batch
+fn query_get_prices(deps: Deps, env: &Env, asset_names: Vec<String>) -> StdResult<PricesResponse> {
+    let prices = asset_names
+        .into_iter()
+        .map(|asset_name| {
+            let result = match query_get_price(deps, env, asset_name.clone()) {
+                Ok(price) => PriceResult::Ok(price),
+                Err(err) => PriceResult::Error(err.to_string()),
+            };
+            AssetPrice { asset_name, result }
+        })
+        .collect();
+    Ok(PricesResponse { prices })
+}
+
+#[cfg(test)]
+#[path = "./contract_unittests.rs"]
+mod contract_unittests;
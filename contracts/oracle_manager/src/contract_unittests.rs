@@ -0,0 +1,1525 @@
+use super::*;
+use crate::state::CUMULATIVE_PRICES;
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{attr, from_binary, Addr, Timestamp, Uint128};
+use cw20::TokenInfoResponse;
+use cw_controllers::{AdminError, AdminResponse};
+use steadifi::astroport::AssetInfo;
+use steadifi::oracle_manager::msg::{AcceptedPrice, PriceObservation};
+use steadifi::oracle_manager::MAX_PRICE_HISTORY_LENGTH;
+use steadifi::testing::MockPair;
+
+fn fixed_oracle(price: u64) -> OracleUnvalidated {
+    OracleUnvalidated::Fixed {
+        price: Decimal::from_ratio(price, 1u64),
+    }
+}
+
+fn add_oracle_msg(asset_name: &str, oracle_unvalidated: OracleUnvalidated) -> ExecuteMsg {
+    ExecuteMsg::AddAssetPriceOracle {
+        asset_name: asset_name.to_string(),
+        oracle_unvalidated,
+    }
+}
+
+fn list_oracles(deps: Deps, asset_name: &str) -> Vec<Oracle> {
+    query_list_oracles(deps, asset_name.to_string())
+        .unwrap()
+        .oracles
+}
+
+#[test]
+fn test_quote_asset() {
+    let mut deps = mock_dependencies(&[]);
+    let env = mock_env();
+    let admin_info = mock_info("admin", &[]);
+    let contract_result = instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        InstantiateMsg {
+            quote_asset: "".to_string(),
+        },
+    );
+    assert_eq!(contract_result, Err(ContractError::EmptyQuoteAsset {}));
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        InstantiateMsg {
+            quote_asset: "ukrw".to_string(),
+        },
+    )
+    .unwrap();
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.quote_asset, "ukrw");
+
+    // The quote asset is worth exactly one, every other asset needs a source
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "ukrw".to_string()).unwrap(),
+        PriceResponse::exact(Decimal::one(), env.block.time.seconds())
+    );
+    assert!(query_get_price(deps.as_ref(), &env, "uusd".to_string()).is_err());
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info,
+        add_oracle_msg("uusd", fixed_oracle(1300)),
+    )
+    .unwrap();
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "uusd".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(1300u64, 1u64)
+    );
+}
+
+#[test]
+fn test_oracle_source_management() {
+    // Initializations
+    let mut deps = mock_dependencies(&[]);
+    let env = mock_env();
+    let admin_info = mock_info("admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        InstantiateMsg {
+            quote_asset: "uusd".to_string(),
+        },
+    )
+    .unwrap();
+
+    // Added oracles are stored and used for the price
+    for price in &[10, 12, 11] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            add_oracle_msg("BTC", fixed_oracle(*price)),
+        )
+        .unwrap();
+    }
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        add_oracle_msg("ETH", fixed_oracle(2)),
+    )
+    .unwrap();
+    let oracles: OraclesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::ListOracles {
+                asset_name: "BTC".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        oracles,
+        OraclesResponse {
+            asset_name: "BTC".to_string(),
+            oracles: vec![
+                fixed_oracle(10).to_validated(&deps.api).unwrap(),
+                fixed_oracle(12).to_validated(&deps.api).unwrap(),
+                fixed_oracle(11).to_validated(&deps.api).unwrap(),
+            ],
+        }
+    );
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "BTC".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(11u64, 1u64)
+    );
+
+    // Oracles can be replaced, reordered and removed
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::ReplaceAssetPriceOracle {
+            asset_name: "BTC".to_string(),
+            index: 1,
+            oracle_unvalidated: fixed_oracle(13),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::ReorderAssetPriceOracles {
+            asset_name: "BTC".to_string(),
+            order: vec![2, 0, 1],
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        list_oracles(deps.as_ref(), "BTC"),
+        vec![
+            fixed_oracle(11).to_validated(&deps.api).unwrap(),
+            fixed_oracle(10).to_validated(&deps.api).unwrap(),
+            fixed_oracle(13).to_validated(&deps.api).unwrap(),
+        ]
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::RemoveAssetPriceOracle {
+            asset_name: "BTC".to_string(),
+            index: 0,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        list_oracles(deps.as_ref(), "BTC"),
+        vec![
+            fixed_oracle(10).to_validated(&deps.api).unwrap(),
+            fixed_oracle(13).to_validated(&deps.api).unwrap(),
+        ]
+    );
+
+    // Assets are listed in pages
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        add_oracle_msg("LUNA", fixed_oracle(3)),
+    )
+    .unwrap();
+    let assets: AssetsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::AllAssets {
+                start_after: None,
+                limit: Some(2),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(assets.assets, vec!["BTC".to_string(), "ETH".to_string()]);
+    let assets = query_all_assets(deps.as_ref(), Some("ETH".to_string()), None).unwrap();
+    assert_eq!(assets.assets, vec!["LUNA".to_string()]);
+
+    // Removing the last oracle of an asset removes the asset
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::RemoveAssetPriceOracle {
+            asset_name: "LUNA".to_string(),
+            index: 0,
+        },
+    )
+    .unwrap();
+    let assets = query_all_assets(deps.as_ref(), None, None).unwrap();
+    assert_eq!(assets.assets, vec!["BTC".to_string(), "ETH".to_string()]);
+
+    // The same oracle can not be added twice
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        add_oracle_msg("BTC", fixed_oracle(13)),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::DuplicateOracle {
+            asset_name: "BTC".to_string()
+        })
+    );
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::ReplaceAssetPriceOracle {
+            asset_name: "BTC".to_string(),
+            index: 0,
+            oracle_unvalidated: fixed_oracle(13),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::DuplicateOracle {
+            asset_name: "BTC".to_string()
+        })
+    );
+
+    // Feeders and pairs count once per asset, whatever their max_age or window
+    let twap_oracle = |window: u64| OracleUnvalidated::AstroportTWAP {
+        address_unvalidated: "btc_usd_pair".to_string(),
+        window,
+    };
+    for oracle in &[OracleUnvalidated::Feeder { max_age: 60 }, twap_oracle(3600)] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            add_oracle_msg("ETH", oracle.clone()),
+        )
+        .unwrap();
+    }
+    for oracle in &[OracleUnvalidated::Feeder { max_age: 120 }, twap_oracle(60)] {
+        let contract_result = execute(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            add_oracle_msg("ETH", oracle.clone()),
+        );
+        assert_eq!(
+            contract_result,
+            Err(ContractError::DuplicateOracle {
+                asset_name: "ETH".to_string()
+            })
+        );
+        let contract_result = execute(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            ExecuteMsg::ReplaceAssetPriceOracle {
+                asset_name: "ETH".to_string(),
+                index: 0,
+                oracle_unvalidated: oracle.clone(),
+            },
+        );
+        assert_eq!(
+            contract_result,
+            Err(ContractError::DuplicateOracle {
+                asset_name: "ETH".to_string()
+            })
+        );
+    }
+    // An oracle can still be replaced by the same source with other parameters
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::ReplaceAssetPriceOracle {
+            asset_name: "ETH".to_string(),
+            index: 2,
+            oracle_unvalidated: twap_oracle(60),
+        },
+    )
+    .unwrap();
+
+    // Indices and orders have to match the oracle list
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::RemoveAssetPriceOracle {
+            asset_name: "BTC".to_string(),
+            index: 2,
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::OracleIndexOutOfRange {
+            asset_name: "BTC".to_string(),
+            index: 2
+        })
+    );
+    for order in &[vec![0], vec![0, 0], vec![1, 2], vec![1, 0, 2]] {
+        let contract_result = execute(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            ExecuteMsg::ReorderAssetPriceOracles {
+                asset_name: "BTC".to_string(),
+                order: order.clone(),
+            },
+        );
+        assert_eq!(contract_result, Err(ContractError::InvalidOracleOrder {}));
+    }
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info,
+        ExecuteMsg::RemoveAssetPriceOracle {
+            asset_name: "LUNA".to_string(),
+            index: 0,
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::OracleNotFound {
+            asset_name: "LUNA".to_string()
+        })
+    );
+
+    // Only the admin can manage oracles
+    let contract_result = execute(
+        deps.as_mut(),
+        env,
+        mock_info("someone", &[]),
+        add_oracle_msg("LUNA", fixed_oracle(3)),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+}
+
+#[test]
+fn test_astroport_twap() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    let mut env = mock_env();
+    let start = env.block.time.seconds();
+    let admin_info = mock_info("admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        InstantiateMsg {
+            quote_asset: "uusd".to_string(),
+        },
+    )
+    .unwrap();
+    let reserves = |luna: u128, usd: u128| [Uint128::from(luna), Uint128::from(usd)];
+    deps.querier.set_pair(
+        "luna_usd_pair",
+        MockPair::new(
+            [
+                AssetInfo::NativeToken {
+                    denom: "uluna".to_string(),
+                },
+                AssetInfo::NativeToken {
+                    denom: "uusd".to_string(),
+                },
+            ],
+            reserves(100, 1000),
+            start,
+        ),
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info,
+        add_oracle_msg(
+            "uluna",
+            OracleUnvalidated::AstroportTWAP {
+                address_unvalidated: "luna_usd_pair".to_string(),
+                window: 3600,
+            },
+        ),
+    )
+    .unwrap();
+    let snapshot_msg = ExecuteMsg::SnapshotCumulativePrices {
+        asset_name: "uluna".to_string(),
+    };
+    let pair = Addr::unchecked("luna_usd_pair");
+    let history_error = || {
+        StdError::generic_err(
+            "Not enough price history of pair luna_usd_pair for a TWAP over 3600 seconds",
+        )
+    };
+
+    // Without snapshots covering the window the pair does not price
+    assert_eq!(
+        query_astroport_twap_price(deps.as_ref(), &env, &pair, 3600, "uluna", "uusd"),
+        Err(history_error())
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("anyone", &[]),
+        snapshot_msg.clone(),
+    )
+    .unwrap();
+    env.block.time = env.block.time.plus_seconds(1800);
+    deps.querier
+        .pair_mut("luna_usd_pair")
+        .set_reserves(reserves(100, 1200), start + 1800);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("anyone", &[]),
+        snapshot_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        query_astroport_twap_price(deps.as_ref(), &env, &pair, 3600, "uluna", "uusd"),
+        Err(history_error())
+    );
+
+    // The price is averaged from the newest snapshot at least one window old
+    env.block.time = env.block.time.plus_seconds(1800);
+    deps.querier
+        .pair_mut("luna_usd_pair")
+        .accumulate(start + 3600);
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "uluna".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(11u64, 1u64)
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("anyone", &[]),
+        snapshot_msg.clone(),
+    )
+    .unwrap();
+    env.block.time = env.block.time.plus_seconds(1800);
+    deps.querier
+        .pair_mut("luna_usd_pair")
+        .accumulate(start + 5400);
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "uluna".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(12u64, 1u64)
+    );
+
+    // Snapshots that are no longer needed are removed
+    execute(deps.as_mut(), env, mock_info("anyone", &[]), snapshot_msg).unwrap();
+    let snapshot_times: Vec<u64> = CUMULATIVE_PRICES
+        .prefix("luna_usd_pair")
+        .range(&deps.storage, None, None, Order::Ascending)
+        .map(|item| item.unwrap().1.time)
+        .collect();
+    assert_eq!(
+        snapshot_times,
+        vec![start + 1800, start + 3600, start + 5400]
+    );
+}
+
+#[test]
+fn test_astroport_twap_decimals() {
+    // Initializations
+    let mut deps = steadifi::testing::mock_dependencies(&[]);
+    let mut env = mock_env();
+    let start = env.block.time.seconds();
+    let admin_info = mock_info("admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        InstantiateMsg {
+            quote_asset: "uusd".to_string(),
+        },
+    )
+    .unwrap();
+
+    // One wbtc with 8 decimals trades for 50000 usd and one wfoo with 4 decimals for 2 usd. The
+    // pairs price base units, 500 and 200 uusd per base unit. wbig has too many decimals to scale
+    let pairs = [
+        ("wbtc", 8u8, [100_000_000u128, 50_000_000_000u128]),
+        ("wfoo", 4u8, [10_000u128, 2_000_000u128]),
+        ("wbig", 60u8, [1_000u128, 1_000u128]),
+    ];
+    for (asset_name, decimals, reserves) in pairs.iter() {
+        let contract_addr = format!("{}_cw20_address", asset_name);
+        let pair = format!("{}_usd_pair", asset_name);
+        deps.querier.set_token_info(
+            &contract_addr,
+            TokenInfoResponse {
+                name: asset_name.to_string(),
+                symbol: asset_name.to_uppercase(),
+                decimals: *decimals,
+                total_supply: Uint128::zero(),
+            },
+        );
+        deps.querier.set_pair(
+            &pair,
+            MockPair::new(
+                [
+                    AssetInfo::Token {
+                        contract_addr: Addr::unchecked(&contract_addr),
+                    },
+                    AssetInfo::NativeToken {
+                        denom: "uusd".to_string(),
+                    },
+                ],
+                [Uint128::from(reserves[0]), Uint128::from(reserves[1])],
+                start,
+            ),
+        );
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            add_oracle_msg(
+                asset_name,
+                OracleUnvalidated::AstroportTWAP {
+                    address_unvalidated: pair,
+                    window: 3600,
+                },
+            ),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::SnapshotCumulativePrices {
+                asset_name: asset_name.to_string(),
+            },
+        )
+        .unwrap();
+    }
+
+    // Prices are of whole units, scaled by the decimals of the asset and the quote asset
+    env.block.time = env.block.time.plus_seconds(3600);
+    for (asset_name, _, _) in pairs.iter() {
+        deps.querier
+            .pair_mut(&format!("{}_usd_pair", asset_name))
+            .accumulate(start + 3600);
+    }
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "wbtc".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(50_000u64, 1u64)
+    );
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "wfoo".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(2u64, 1u64)
+    );
+    assert_eq!(
+        query_astroport_twap_price(
+            deps.as_ref(),
+            &env,
+            &Addr::unchecked("wbig_usd_pair"),
+            3600,
+            "wbig",
+            "uusd"
+        ),
+        Err(StdError::generic_err("Invalid decimals 54"))
+    );
+
+    // A pair only prices the asset it trades against the quote asset
+    assert_eq!(
+        query_astroport_twap_price(
+            deps.as_ref(),
+            &env,
+            &Addr::unchecked("wbtc_usd_pair"),
+            3600,
+            "wfoo",
+            "uusd"
+        ),
+        Err(StdError::generic_err(
+            "Pair wbtc_usd_pair does not trade wfoo against the quote asset uusd"
+        ))
+    );
+}
+
+#[test]
+fn test_aggregation_policy() {
+    // Initializations
+    let mut deps = mock_dependencies(&[]);
+    let env = mock_env();
+    let admin_info = mock_info("admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        InstantiateMsg {
+            quote_asset: "uusd".to_string(),
+        },
+    )
+    .unwrap();
+    for price in &[100, 101, 99, 150] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            add_oracle_msg("BTC", fixed_oracle(*price)),
+        )
+        .unwrap();
+    }
+    // Has no price history, so it fails to price
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        add_oracle_msg(
+            "BTC",
+            OracleUnvalidated::AstroportTWAP {
+                address_unvalidated: "btc_usd_pair".to_string(),
+                window: 3600,
+            },
+        ),
+    )
+    .unwrap();
+    let set_policy_msg =
+        |min_valid_sources: u32, max_deviation: Option<Decimal>| ExecuteMsg::SetAggregationPolicy {
+            asset_name: "BTC".to_string(),
+            policy: AggregationPolicy {
+                min_valid_sources,
+                max_deviation,
+            },
+        };
+
+    // By default the price is the median of the sources that price, the mean of the middle two
+    assert_eq!(
+        query_aggregation_policy(deps.as_ref(), "BTC".to_string()).unwrap(),
+        AggregationPolicy::default()
+    );
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "BTC".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(1005u64, 10u64)
+    );
+
+    // Sources deviating too much from the median are rejected
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        set_policy_msg(3, Some(Decimal::percent(5))),
+    )
+    .unwrap();
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "BTC".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(100u64, 1u64)
+    );
+
+    // Without enough valid sources there is no price
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        set_policy_msg(4, Some(Decimal::percent(5))),
+    )
+    .unwrap();
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "BTC".to_string()),
+        Err(StdError::generic_err(
+            ContractError::AggregationPolicyNotSatisfied {
+                asset_name: "BTC".to_string(),
+                valid_sources: 3,
+                min_valid_sources: 4,
+            }
+            .to_string()
+        ))
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        set_policy_msg(5, None),
+    )
+    .unwrap();
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "BTC".to_string()),
+        Err(StdError::generic_err(
+            ContractError::AggregationPolicyNotSatisfied {
+                asset_name: "BTC".to_string(),
+                valid_sources: 4,
+                min_valid_sources: 5,
+            }
+            .to_string()
+        ))
+    );
+
+    // Policies have to require a valid source and only the admin can set them
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info,
+        set_policy_msg(0, None),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::Std(StdError::generic_err(
+            "Aggregation policy has to require at least one valid source"
+        )))
+    );
+    let contract_result = execute(
+        deps.as_mut(),
+        env,
+        mock_info("someone", &[]),
+        set_policy_msg(1, None),
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+}
+
+#[test]
+fn test_feeder_oracle() {
+    // Initializations
+    let mut deps = mock_dependencies(&[]);
+    let env = mock_env();
+    let now = env.block.time.seconds();
+    let admin_info = mock_info("admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        InstantiateMsg {
+            quote_asset: "uusd".to_string(),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        add_oracle_msg("GOLD", OracleUnvalidated::Feeder { max_age: 600 }),
+    )
+    .unwrap();
+    for feeder in &["feeder1", "feeder2", "feeder3"] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            ExecuteMsg::AddFeeder {
+                address: feeder.to_string(),
+            },
+        )
+        .unwrap();
+    }
+    let submit_price_msg = |price: u64, timestamp: u64| ExecuteMsg::SubmitPrice {
+        asset_name: "GOLD".to_string(),
+        price: Decimal::from_ratio(price, 1u64),
+        timestamp,
+    };
+
+    // Only fresh reports of whitelisted feeders are used
+    for (feeder, price, timestamp) in &[
+        ("feeder1", 100, now - 100),
+        ("feeder2", 102, now),
+        ("feeder3", 90, now - 1000),
+    ] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(feeder, &[]),
+            submit_price_msg(*price, *timestamp),
+        )
+        .unwrap();
+    }
+    let price: PriceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GetPrice {
+                asset_name: "GOLD".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        price,
+        PriceResponse {
+            price: Decimal::from_ratio(101u64, 1u64),
+            last_updated: now - 100,
+            sources_used: 2,
+            min: Decimal::from_ratio(100u64, 1u64),
+            max: Decimal::from_ratio(102u64, 1u64),
+        }
+    );
+    let report: Option<FeederReport> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::FeederReport {
+                feeder: "feeder3".to_string(),
+                asset_name: "GOLD".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        report,
+        Some(FeederReport {
+            price: Decimal::from_ratio(90u64, 1u64),
+            timestamp: now - 1000,
+        })
+    );
+    assert_eq!(
+        query_feeder_report(deps.as_ref(), "feeder1".to_string(), "BTC".to_string()).unwrap(),
+        None
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::RemoveFeeder {
+            address: "feeder2".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "GOLD".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(100u64, 1u64)
+    );
+    let feeders: FeedersResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Feeders {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        feeders.feeders,
+        vec!["feeder1".to_string(), "feeder3".to_string()]
+    );
+
+    // Reports have to come from whitelisted feeders and move forward in time
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder2", &[]),
+        submit_price_msg(103, now),
+    );
+    assert_eq!(contract_result, Err(ContractError::Unauthorized {}));
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder1", &[]),
+        submit_price_msg(103, now + 1),
+    );
+    assert_eq!(contract_result, Err(ContractError::InvalidReportTime {}));
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder1", &[]),
+        submit_price_msg(103, now - 100),
+    );
+    assert_eq!(contract_result, Err(ContractError::InvalidReportTime {}));
+
+    // Feeders can only be whitelisted once and removed while whitelisted
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::AddFeeder {
+            address: "feeder1".to_string(),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::DuplicateFeeder {
+            address: "feeder1".to_string()
+        })
+    );
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info,
+        ExecuteMsg::RemoveFeeder {
+            address: "feeder2".to_string(),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::FeederNotFound {
+            address: "feeder2".to_string()
+        })
+    );
+
+    // Only the admin can whitelist feeders
+    let contract_result = execute(
+        deps.as_mut(),
+        env,
+        mock_info("someone", &[]),
+        ExecuteMsg::AddFeeder {
+            address: "someone".to_string(),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+}
+
+#[test]
+fn test_circuit_breaker() {
+    // Initializations
+    let mut deps = mock_dependencies(&[]);
+    let mut env = mock_env();
+    let admin_info = mock_info("admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        InstantiateMsg {
+            quote_asset: "uusd".to_string(),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        add_oracle_msg("GOLD", fixed_oracle(100)),
+    )
+    .unwrap();
+    let set_price = |deps: DepsMut, price: u64| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::ReplaceAssetPriceOracle {
+                asset_name: "GOLD".to_string(),
+                index: 0,
+                oracle_unvalidated: fixed_oracle(price),
+            },
+        )
+        .unwrap();
+    };
+    let record_price_msg = ExecuteMsg::RecordPrice {
+        asset_name: "GOLD".to_string(),
+    };
+    let circuit_breaker = CircuitBreaker {
+        max_deviation: Decimal::percent(10),
+        window: 3600,
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::SetCircuitBreaker {
+            asset_name: "GOLD".to_string(),
+            circuit_breaker: Some(circuit_breaker.clone()),
+        },
+    )
+    .unwrap();
+
+    // Prices within 10% of the accepted price pass
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        record_price_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(response.attributes[3], attr("accepted", "true"));
+    set_price(deps.as_mut(), 105);
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "GOLD".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(105u64, 1u64)
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        record_price_msg.clone(),
+    )
+    .unwrap();
+
+    // A jump of more than 10% is rejected and recording it trips the breaker
+    set_price(deps.as_mut(), 150);
+    let query_result = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::GetPrice {
+            asset_name: "GOLD".to_string(),
+        },
+    );
+    assert_eq!(
+        query_result,
+        Err(StdError::generic_err(
+            "Circuit breaker of asset GOLD is tripped"
+        ))
+    );
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        record_price_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(response.attributes[3], attr("accepted", "false"));
+    let breaker_response: CircuitBreakerResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::CircuitBreaker {
+                asset_name: "GOLD".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        breaker_response,
+        CircuitBreakerResponse {
+            circuit_breaker: Some(circuit_breaker),
+            accepted_price: Some(AcceptedPrice {
+                price: Decimal::from_ratio(105u64, 1u64),
+                time: env.block.time.seconds(),
+            }),
+            tripped_at: Some(env.block.time.seconds()),
+        }
+    );
+
+    // While tripped even prices close to the accepted price are rejected
+    set_price(deps.as_mut(), 104);
+    assert!(query_get_price(deps.as_ref(), &env, "GOLD".to_string()).is_err());
+
+    // Once the window passed the new price is accepted
+    set_price(deps.as_mut(), 150);
+    env.block.time = env.block.time.plus_seconds(3600);
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "GOLD".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(150u64, 1u64)
+    );
+    let response = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        record_price_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(response.attributes[3], attr("accepted", "true"));
+
+    // The guardian can confirm a price that tripped the breaker
+    set_price(deps.as_mut(), 300);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        record_price_msg.clone(),
+    )
+    .unwrap();
+    assert!(query_get_price(deps.as_ref(), &env, "GOLD".to_string()).is_err());
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info,
+        ExecuteMsg::UpdateGuardian {
+            guardian: Some("guardian".to_string()),
+        },
+    )
+    .unwrap();
+    let guardian: AdminResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Guardian {}).unwrap()).unwrap();
+    assert_eq!(guardian.admin, Some("guardian".to_string()));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::ConfirmPrice {
+            asset_name: "GOLD".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "GOLD".to_string())
+            .unwrap()
+            .price,
+        Decimal::from_ratio(300u64, 1u64)
+    );
+    let breaker_response = query_circuit_breaker(deps.as_ref(), "GOLD".to_string()).unwrap();
+    assert_eq!(breaker_response.tripped_at, None);
+
+    // A breaker with the longest possible window stays tripped instead of overflowing
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::SetCircuitBreaker {
+            asset_name: "GOLD".to_string(),
+            circuit_breaker: Some(CircuitBreaker {
+                max_deviation: Decimal::percent(10),
+                window: u64::MAX,
+            }),
+        },
+    )
+    .unwrap();
+    set_price(deps.as_mut(), 600);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        record_price_msg,
+    )
+    .unwrap();
+    set_price(deps.as_mut(), 300);
+    assert_eq!(
+        query_get_price(deps.as_ref(), &env, "GOLD".to_string()),
+        Err(StdError::generic_err(
+            "Circuit breaker of asset GOLD is tripped"
+        ))
+    );
+
+    // Only the admin or guardian can confirm prices
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("someone", &[]),
+        ExecuteMsg::ConfirmPrice {
+            asset_name: "GOLD".to_string(),
+        },
+    );
+    assert_eq!(contract_result, Err(ContractError::Unauthorized {}));
+
+    // Circuit breakers need a positive window and only the admin can set them
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::SetCircuitBreaker {
+            asset_name: "GOLD".to_string(),
+            circuit_breaker: Some(CircuitBreaker {
+                max_deviation: Decimal::percent(10),
+                window: 0,
+            }),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::Std(StdError::generic_err(
+            "Window of a circuit breaker has to be positive"
+        )))
+    );
+    let contract_result = execute(
+        deps.as_mut(),
+        env,
+        mock_info("guardian", &[]),
+        ExecuteMsg::SetCircuitBreaker {
+            asset_name: "GOLD".to_string(),
+            circuit_breaker: None,
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+}
+
+#[test]
+fn test_price_history() {
+    // Initializations
+    let mut deps = mock_dependencies(&[]);
+    let mut env = mock_env();
+    let start = env.block.time.seconds();
+    let admin_info = mock_info("admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        InstantiateMsg {
+            quote_asset: "uusd".to_string(),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        add_oracle_msg("GOLD", fixed_oracle(100)),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::SetPriceHistoryConfig {
+            asset_name: "GOLD".to_string(),
+            config: Some(PriceHistoryConfig {
+                length: 3,
+                min_interval: 100,
+            }),
+        },
+    )
+    .unwrap();
+    let record_price = |mut deps: DepsMut, time: u64, price: u64| {
+        execute(
+            deps.branch(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::ReplaceAssetPriceOracle {
+                asset_name: "GOLD".to_string(),
+                index: 0,
+                oracle_unvalidated: fixed_oracle(price),
+            },
+        )
+        .unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(time);
+        execute(
+            deps,
+            env,
+            mock_info("someone", &[]),
+            ExecuteMsg::RecordPrice {
+                asset_name: "GOLD".to_string(),
+            },
+        )
+        .unwrap()
+    };
+    let price_at = |deps: Deps, timestamp: u64| {
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::PriceAt {
+                asset_name: "GOLD".to_string(),
+                timestamp,
+            },
+        )
+        .and_then(|binary| from_binary::<PriceObservation>(&binary))
+    };
+
+    // Observations are only recorded min_interval apart
+    let response = record_price(deps.as_mut(), start, 100);
+    assert_eq!(response.attributes[4], attr("observed", "true"));
+    let response = record_price(deps.as_mut(), start + 50, 105);
+    assert_eq!(response.attributes[4], attr("observed", "false"));
+    record_price(deps.as_mut(), start + 100, 110);
+    record_price(deps.as_mut(), start + 300, 130);
+    assert_eq!(
+        price_at(deps.as_ref(), start + 150).unwrap(),
+        PriceObservation {
+            price: Decimal::from_ratio(110u64, 1u64),
+            time: start + 100,
+        }
+    );
+
+    // Every observation is the price until the next one
+    env.block.time = Timestamp::from_seconds(start + 400);
+    let twap_price_msg = |start: u64, end: u64| QueryMsg::GetTwapPrice {
+        asset_name: "GOLD".to_string(),
+        start,
+        end,
+    };
+    let twap_price: Decimal = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            twap_price_msg(start, start + 400),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(twap_price, Decimal::from_ratio(225u64, 2u64));
+
+    // A full ring buffer overwrites the oldest observation
+    record_price(deps.as_mut(), start + 400, 140);
+    assert!(price_at(deps.as_ref(), start + 50).is_err());
+    let twap_price: Decimal = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            twap_price_msg(start + 200, start + 400),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(twap_price, Decimal::from_ratio(120u64, 1u64));
+    assert!(query(
+        deps.as_ref(),
+        env.clone(),
+        twap_price_msg(start, start + 400)
+    )
+    .is_err());
+    assert!(query(
+        deps.as_ref(),
+        env.clone(),
+        twap_price_msg(start + 200, start + 500)
+    )
+    .is_err());
+
+    // Changing the minimum interval keeps the history, changing the length clears it
+    let new_config = PriceHistoryConfig {
+        length: 3,
+        min_interval: 10,
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::SetPriceHistoryConfig {
+            asset_name: "GOLD".to_string(),
+            config: Some(new_config.clone()),
+        },
+    )
+    .unwrap();
+    let config: Option<PriceHistoryConfig> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::PriceHistoryConfig {
+                asset_name: "GOLD".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(config, Some(new_config));
+    assert_eq!(
+        price_at(deps.as_ref(), start + 400).unwrap().price,
+        Decimal::from_ratio(140u64, 1u64)
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::SetPriceHistoryConfig {
+            asset_name: "GOLD".to_string(),
+            config: Some(PriceHistoryConfig {
+                length: 5,
+                min_interval: 10,
+            }),
+        },
+    )
+    .unwrap();
+    assert!(price_at(deps.as_ref(), start + 400).is_err());
+
+    // A price history needs a positive length up to the maximum and only the admin can configure it
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        ExecuteMsg::SetPriceHistoryConfig {
+            asset_name: "GOLD".to_string(),
+            config: Some(PriceHistoryConfig {
+                length: 0,
+                min_interval: 10,
+            }),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::Std(StdError::generic_err(
+            "Price history has to keep at least one observation"
+        )))
+    );
+    let contract_result = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info,
+        ExecuteMsg::SetPriceHistoryConfig {
+            asset_name: "GOLD".to_string(),
+            config: Some(PriceHistoryConfig {
+                length: MAX_PRICE_HISTORY_LENGTH + 1,
+                min_interval: 10,
+            }),
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::Std(StdError::generic_err(format!(
+            "Price history can keep at most {} observations",
+            MAX_PRICE_HISTORY_LENGTH
+        ))))
+    );
+    let contract_result = execute(
+        deps.as_mut(),
+        env,
+        mock_info("someone", &[]),
+        ExecuteMsg::SetPriceHistoryConfig {
+            asset_name: "GOLD".to_string(),
+            config: None,
+        },
+    );
+    assert_eq!(
+        contract_result,
+        Err(ContractError::ControllerError(AdminError::NotAdmin {}))
+    );
+}
+
+#[test]
+fn test_get_prices() {
+    // Initializations
+    let mut deps = mock_dependencies(&[]);
+    let env = mock_env();
+    let now = env.block.time.seconds();
+    let admin_info = mock_info("admin", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        InstantiateMsg {
+            quote_asset: "uusd".to_string(),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info.clone(),
+        add_oracle_msg("GOLD", fixed_oracle(100)),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin_info,
+        add_oracle_msg("SILVER", OracleUnvalidated::Feeder { max_age: 600 }),
+    )
+    .unwrap();
+
+    // Assets that can not be priced are reported inline
+    let prices: PricesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GetPrices {
+                asset_names: vec![
+                    "GOLD".to_string(),
+                    "uusd".to_string(),
+                    "SILVER".to_string(),
+                    "BTC".to_string(),
+                ],
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        prices.prices,
+        vec![
+            AssetPrice {
+                asset_name: "GOLD".to_string(),
+                result: PriceResult::Ok(PriceResponse {
+                    price: Decimal::from_ratio(100u64, 1u64),
+                    last_updated: now,
+                    sources_used: 1,
+                    min: Decimal::from_ratio(100u64, 1u64),
+                    max: Decimal::from_ratio(100u64, 1u64),
+                }),
+            },
+            AssetPrice {
+                asset_name: "uusd".to_string(),
+                result: PriceResult::Ok(PriceResponse::exact(Decimal::one(), now)),
+            },
+            AssetPrice {
+                asset_name: "SILVER".to_string(),
+                result: PriceResult::Error(
+                    "Generic error: Price of asset SILVER does not satisfy its aggregation \
+                     policy, 0 valid sources but 1 required"
+                        .to_string()
+                ),
+            },
+            AssetPrice {
+                asset_name: "BTC".to_string(),
+                result: PriceResult::Error(
+                    "Oracle list is empty for asset BTC not found".to_string()
+                ),
+            },
+        ]
+    );
+}
@@ -10,9 +10,48 @@ pub enum ContractError {
     #[error("{0}")]
     ControllerError(#[from] AdminError),
 
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Quote asset can not be empty")]
+    EmptyQuoteAsset {},
+
+    #[error("Report time has to be after the previous report and not in the future")]
+    InvalidReportTime {},
+
     #[error("No oracle registered for asset {}", asset_name)]
     OracleNotFound { asset_name: String },
 
     #[error("Oracle for Native asset returned an error")]
     NativePriceNotFound {},
+
+    #[error("Oracle is already a price source of asset {}", asset_name)]
+    DuplicateOracle { asset_name: String },
+
+    #[error("Asset {} has no oracle at index {}", asset_name, index)]
+    OracleIndexOutOfRange { asset_name: String, index: u32 },
+
+    #[error("New order has to list every oracle index exactly once")]
+    InvalidOracleOrder {},
+
+    #[error("{} is already a feeder", address)]
+    DuplicateFeeder { address: String },
+
+    #[error("{} is not a feeder", address)]
+    FeederNotFound { address: String },
+
+    #[error(
+        "Price of asset {} does not satisfy its aggregation policy, {} valid sources but {} required",
+        asset_name,
+        valid_sources,
+        min_valid_sources
+    )]
+    AggregationPolicyNotSatisfied {
+        asset_name: String,
+        valid_sources: u32,
+        min_valid_sources: u32,
+    },
+
+    #[error("Circuit breaker of asset {} is tripped", asset_name)]
+    CircuitBreakerTripped { asset_name: String },
 }
@@ -0,0 +1,146 @@
+use cosmwasm_std::{StdError, StdResult, Storage, Uint128};
+use cw_storage_plus::U64Key;
+
+use crate::state::{PRICE_HISTORY_CONFIG, PRICE_OBSERVATIONS, PRICE_OBSERVATION_COUNT};
+use steadifi::mars_protocol_math::Decimal;
+use steadifi::oracle_manager::msg::PriceObservation;
+use steadifi::oracle_manager::PriceHistoryConfig;
+
+/// Sets how the price history of asset_name is kept. Observations are stored by ring buffer slot,
+/// so a new length or removing the config clears the history
+pub fn set_price_history_config(
+    storage: &mut dyn Storage,
+    asset_name: &str,
+    config: Option<PriceHistoryConfig>,
+) -> StdResult<()> {
+    let old_config = PRICE_HISTORY_CONFIG.may_load(storage, asset_name)?;
+    let keep_history = match (&old_config, &config) {
+        (Some(old_config), Some(config)) => old_config.length == config.length,
+        _ => false,
+    };
+    if !keep_history {
+        if let Some(old_config) = old_config {
+            let count = PRICE_OBSERVATION_COUNT
+                .may_load(storage, asset_name)?
+                .unwrap_or_default();
+            for slot in 0..count.min(old_config.length as u64) {
+                PRICE_OBSERVATIONS.remove(storage, (asset_name, U64Key::new(slot)));
+            }
+        }
+        PRICE_OBSERVATION_COUNT.remove(storage, asset_name);
+    }
+    match config {
+        Some(config) => PRICE_HISTORY_CONFIG.save(storage, asset_name, &config),
+        None => {
+            PRICE_HISTORY_CONFIG.remove(storage, asset_name);
+            Ok(())
+        }
+    }
+}
+
+/// Adds price at time now to the price history of asset_name, overwriting the oldest observation
+/// once the ring buffer is full. Returns whether it was added, which it is not if asset_name has no
+/// price history or the previous observation is less than min_interval seconds old
+pub fn record_observation(
+    storage: &mut dyn Storage,
+    asset_name: &str,
+    price: Decimal,
+    now: u64,
+) -> StdResult<bool> {
+    let config = match PRICE_HISTORY_CONFIG.may_load(storage, asset_name)? {
+        Some(config) => config,
+        None => return Ok(false),
+    };
+    let length = config.length as u64;
+    let count = PRICE_OBSERVATION_COUNT
+        .may_load(storage, asset_name)?
+        .unwrap_or_default();
+    if count > 0 {
+        let last_slot = U64Key::new((count - 1) % length);
+        let last_observation = PRICE_OBSERVATIONS.load(storage, (asset_name, last_slot))?;
+        if now < last_observation.time + config.min_interval {
+            return Ok(false);
+        }
+    }
+    PRICE_OBSERVATIONS.save(
+        storage,
+        (asset_name, U64Key::new(count % length)),
+        &PriceObservation { price, time: now },
+    )?;
+    PRICE_OBSERVATION_COUNT.save(storage, asset_name, &(count + 1))?;
+    Ok(true)
+}
+
+/// Returns the price history of asset_name from oldest to newest observation. Once the ring buffer
+/// is full the oldest observation is in the slot the next one will be written to
+fn load_observations(storage: &dyn Storage, asset_name: &str) -> StdResult<Vec<PriceObservation>> {
+    let length = match PRICE_HISTORY_CONFIG.may_load(storage, asset_name)? {
+        Some(config) => config.length as u64,
+        None => return Ok(vec![]),
+    };
+    let count = PRICE_OBSERVATION_COUNT
+        .may_load(storage, asset_name)?
+        .unwrap_or_default();
+    let first = count.saturating_sub(length);
+    (first..count)
+        .map(|index| PRICE_OBSERVATIONS.load(storage, (asset_name, U64Key::new(index % length))))
+        .collect()
+}
+
+/// Returns the index of the newest observation at or before timestamp
+fn index_at(
+    observations: &[PriceObservation],
+    asset_name: &str,
+    timestamp: u64,
+) -> StdResult<usize> {
+    observations
+        .iter()
+        .rposition(|observation| observation.time <= timestamp)
+        .ok_or_else(|| {
+            StdError::generic_err(format!(
+                "Price history of asset {} does not reach back to {}",
+                asset_name, timestamp
+            ))
+        })
+}
+
+/// Returns the newest observation in the price history of asset_name at or before timestamp
+pub fn query_price_at(
+    storage: &dyn Storage,
+    asset_name: &str,
+    timestamp: u64,
+) -> StdResult<PriceObservation> {
+    let observations = load_observations(storage, asset_name)?;
+    let index = index_at(&observations, asset_name, timestamp)?;
+    Ok(observations[index].clone())
+}
+
+/// Returns the time weighted average price of asset_name between start and end from its price
+/// history. Every observation is taken as the price until the next one
+pub fn query_history_twap_price(
+    storage: &dyn Storage,
+    asset_name: &str,
+    start: u64,
+    end: u64,
+) -> StdResult<Decimal> {
+    let observations = load_observations(storage, asset_name)?;
+    let first_index = index_at(&observations, asset_name, start)?;
+    if start == end {
+        return Ok(observations[first_index].price);
+    }
+    let mut weighted_sum = Decimal::zero();
+    for (index, observation) in observations.iter().enumerate().skip(first_index) {
+        if observation.time >= end {
+            break;
+        }
+        let period_start = observation.time.max(start);
+        let period_end = observations
+            .get(index + 1)
+            .map_or(end, |next_observation| next_observation.time.min(end));
+        weighted_sum = weighted_sum
+            + observation
+                .price
+                .checked_mul(Decimal::from_ratio(period_end - period_start, 1u64))?;
+    }
+    Ok(weighted_sum / Uint128::from(end - start))
+}
@@ -1,3 +1,7 @@
+pub mod aggregation;
+pub mod circuit_breaker;
 pub mod contract;
 pub mod error;
+pub mod history;
 pub mod state;
+pub mod twap;
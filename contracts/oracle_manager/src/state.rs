@@ -1,10 +1,67 @@
+use cosmwasm_std::{Addr, Empty, Uint128};
 use cw_controllers::Admin;
-use cw_storage_plus::Map;
-use steadifi::oracle_manager::Oracle;
+use cw_storage_plus::{Item, Map, U64Key};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use steadifi::oracle_manager::msg::{AcceptedPrice, FeederReport, PriceObservation};
+use steadifi::oracle_manager::{AggregationPolicy, CircuitBreaker, Oracle, PriceHistoryConfig};
 
 // Admin of contract can add or remove supported assets
 // Eventually the admin will be the governance contract
 pub const ADMIN: Admin = Admin::new("admin");
 
+// Besides the admin, the guardian can confirm prices that tripped a circuit breaker
+pub const GUARDIAN: Admin = Admin::new("guardian");
+
 // Maps asset name to list of Oracles
 pub const ORACLE: Map<&str, Vec<Oracle>> = Map::new("oracle");
+
+// Maps asset name to how the prices of its oracles are combined, the default policy if missing
+pub const AGGREGATION_POLICY: Map<&str, AggregationPolicy> = Map::new("aggregation_policy");
+
+// Addresses whitelisted by the admin to submit prices
+pub const FEEDERS: Map<&Addr, Empty> = Map::new("feeders");
+
+// Maps asset name and feeder address to the latest report of the feeder for the asset
+pub const FEEDER_REPORTS: Map<(&str, &Addr), FeederReport> = Map::new("feeder_reports");
+
+// Maps asset name to the circuit breaker guarding its price, unguarded if missing
+pub const CIRCUIT_BREAKER: Map<&str, CircuitBreaker> = Map::new("circuit_breaker");
+
+// Maps asset name to the last price accepted by RecordPrice or ConfirmPrice
+pub const ACCEPTED_PRICE: Map<&str, AcceptedPrice> = Map::new("accepted_price");
+
+// Maps asset name to the time its circuit breaker tripped, missing while prices are accepted
+pub const BREAKER_TRIPPED_AT: Map<&str, u64> = Map::new("breaker_tripped_at");
+
+// Maps asset name to how its price history is kept, no history is kept if missing
+pub const PRICE_HISTORY_CONFIG: Map<&str, PriceHistoryConfig> = Map::new("price_history_config");
+
+// Maps asset name to the number of observations recorded since its history was configured. The
+// next observation goes into slot count % length of the ring buffer
+pub const PRICE_OBSERVATION_COUNT: Map<&str, u64> = Map::new("price_observation_count");
+
+// Maps asset name and ring buffer slot to a price observation
+pub const PRICE_OBSERVATIONS: Map<(&str, U64Key), PriceObservation> =
+    Map::new("price_observations");
+
+//Contract config
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Config {
+    // Asset all prices are denominated in
+    pub quote_asset: String,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
+// Cumulative prices of an Astroport pair at some time
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct CumulativePricesSnapshot {
+    pub time: u64,
+    pub price0_cumulative: Uint128,
+    pub price1_cumulative: Uint128,
+}
+
+// Maps pair address and time to the cumulative prices of the pair at that time
+pub const CUMULATIVE_PRICES: Map<(&str, U64Key), CumulativePricesSnapshot> =
+    Map::new("cumulative_prices");
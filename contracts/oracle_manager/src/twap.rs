@@ -0,0 +1,167 @@
+use cosmwasm_std::{Addr, Deps, Env, Order, QuerierWrapper, StdError, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, U64Key};
+
+use cw20::{Cw20QueryMsg, TokenInfoResponse};
+
+use crate::state::{CumulativePricesSnapshot, CUMULATIVE_PRICES};
+use steadifi::asset::decimals_to_unit;
+use steadifi::astroport::{AssetInfo, CumulativePricesResponse, PairQueryMsg, TWAP_PRECISION};
+use steadifi::mars_protocol_math::Decimal;
+
+// Decimals of native tokens, unlike tokens they can not be queried for them
+const NATIVE_DECIMALS: u8 = 6;
+
+fn query_cumulative_prices(
+    querier: &QuerierWrapper,
+    pair: &Addr,
+) -> StdResult<CumulativePricesResponse> {
+    querier.query_wasm_smart(pair, &PairQueryMsg::CumulativePrices {})
+}
+
+/// Returns the names an asset of a pair can go by and its decimals. Tokens go by their address and
+/// by their cw20 name, which is the name of the asset they stand for
+fn query_names_and_decimals(
+    querier: &QuerierWrapper,
+    info: &AssetInfo,
+) -> StdResult<(Vec<String>, u8)> {
+    match info {
+        AssetInfo::NativeToken { denom } => Ok((vec![denom.clone()], NATIVE_DECIMALS)),
+        AssetInfo::Token { contract_addr } => {
+            let token_info: TokenInfoResponse =
+                querier.query_wasm_smart(contract_addr, &Cw20QueryMsg::TokenInfo {})?;
+            Ok((
+                vec![contract_addr.to_string(), token_info.name],
+                token_info.decimals,
+            ))
+        }
+    }
+}
+
+/// Returns the newest snapshot of pair taken at or before time
+fn load_snapshot_before(
+    storage: &dyn Storage,
+    pair: &Addr,
+    time: u64,
+) -> StdResult<Option<CumulativePricesSnapshot>> {
+    CUMULATIVE_PRICES
+        .prefix(pair.as_str())
+        .range(
+            storage,
+            None,
+            Some(Bound::inclusive_int(time)),
+            Order::Descending,
+        )
+        .next()
+        .map(|item| item.map(|(_, snapshot)| snapshot))
+        .transpose()
+}
+
+/// Records the current cumulative prices of pair. Only the newest snapshot at least window old is
+/// needed for a TWAP, older ones are removed
+pub fn snapshot_cumulative_prices(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    pair: &Addr,
+    window: u64,
+) -> StdResult<()> {
+    let now = env.block.time.seconds();
+    let response = query_cumulative_prices(querier, pair)?;
+    CUMULATIVE_PRICES.save(
+        storage,
+        (pair.as_str(), U64Key::new(now)),
+        &CumulativePricesSnapshot {
+            time: now,
+            price0_cumulative: response.price0_cumulative_last,
+            price1_cumulative: response.price1_cumulative_last,
+        },
+    )?;
+    if let Some(cutoff) = now.checked_sub(window) {
+        let stale_times: Vec<u64> = CUMULATIVE_PRICES
+            .prefix(pair.as_str())
+            .range(
+                storage,
+                None,
+                Some(Bound::inclusive_int(cutoff)),
+                Order::Descending,
+            )
+            .skip(1)
+            .map(|item| item.map(|(_, snapshot)| snapshot.time))
+            .collect::<StdResult<_>>()?;
+        for time in stale_times {
+            CUMULATIVE_PRICES.remove(storage, (pair.as_str(), U64Key::new(time)));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the average price of asset_name in the quote asset from pair, from the newest snapshot
+/// at least window old until now. Fails if there is no such snapshot or pair does not trade
+/// asset_name against the quote asset
+pub fn query_astroport_twap_price(
+    deps: Deps,
+    env: &Env,
+    pair: &Addr,
+    window: u64,
+    asset_name: &str,
+    quote_asset: &str,
+) -> StdResult<Decimal> {
+    let now = env.block.time.seconds();
+    let snapshot = match now.checked_sub(window) {
+        Some(cutoff) => load_snapshot_before(deps.storage, pair, cutoff)?,
+        None => None,
+    }
+    .ok_or_else(|| {
+        StdError::generic_err(format!(
+            "Not enough price history of pair {} for a TWAP over {} seconds",
+            pair, window
+        ))
+    })?;
+    let response = query_cumulative_prices(&deps.querier, pair)?;
+
+    // price0 is the price of assets[0] in assets[1] and price1 the other way round
+    let (asset_index, cumulative_now, cumulative_then) =
+        if response.assets[1].info.get_name() == quote_asset {
+            (
+                0,
+                response.price0_cumulative_last,
+                snapshot.price0_cumulative,
+            )
+        } else if response.assets[0].info.get_name() == quote_asset {
+            (
+                1,
+                response.price1_cumulative_last,
+                snapshot.price1_cumulative,
+            )
+        } else {
+            return Err(StdError::generic_err(format!(
+                "Pair {} does not trade against the quote asset {}",
+                pair, quote_asset
+            )));
+        };
+    let elapsed = Uint128::from(now - snapshot.time);
+    let price = Decimal::from_ratio(
+        cumulative_now.wrapping_sub(cumulative_then),
+        elapsed.checked_mul(Uint128::from(10u128.pow(TWAP_PRECISION)))?,
+    );
+
+    let (asset_names, asset_decimals) =
+        query_names_and_decimals(&deps.querier, &response.assets[asset_index].info)?;
+    if !asset_names.iter().any(|name| name == asset_name) {
+        return Err(StdError::generic_err(format!(
+            "Pair {} does not trade {} against the quote asset {}",
+            pair, asset_name, quote_asset
+        )));
+    }
+
+    // The pair prices base units in base units, prices are of whole units in whole units
+    let (_, quote_decimals) =
+        query_names_and_decimals(&deps.querier, &response.assets[1 - asset_index].info)?;
+    if asset_decimals >= quote_decimals {
+        let scale = decimals_to_unit(asset_decimals - quote_decimals)?;
+        price.checked_mul(Decimal::from_ratio(scale, 1u128))
+    } else {
+        let scale = decimals_to_unit(quote_decimals - asset_decimals)?;
+        Ok(price / scale)
+    }
+}
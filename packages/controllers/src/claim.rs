@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{Addr, BlockInfo, Deps, StdResult, Storage, Uint128};
 use cw0::Expiration;
-use cw_storage_plus::Map;
+use cw_storage_plus::{Map, PrimaryKey};
 
 // TODO: pull into cw0?
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -28,19 +28,26 @@ impl Claim {
 }
 
 // TODO: revisit design (split each claim on own key?)
-pub struct Claims<'a>(Map<'a, &'a Addr, Vec<Claim>>);
+/// Claims are stored per key, which defaults to the claimant's address. Contracts that handle
+/// several denominations can key them by e.g. `(&Addr, &str)` to keep one queue per denomination.
+pub struct Claims<'a, K = &'a Addr>(Map<'a, K, Vec<Claim>>);
 
-impl<'a> Claims<'a> {
+impl<'a, K> Claims<'a, K> {
     pub const fn new(storage_key: &'a str) -> Self {
         Claims(Map::new(storage_key))
     }
+}
 
+impl<'a, K> Claims<'a, K>
+where
+    K: PrimaryKey<'a>,
+{
     /// This creates a claim, such that the given address can claim an amount of tokens after
     /// the release date.
     pub fn create_claim(
         &self,
         storage: &mut dyn Storage,
-        addr: &Addr,
+        addr: K,
         amount: Uint128,
         release_at: Expiration,
     ) -> StdResult<()> {
@@ -58,7 +65,7 @@ impl<'a> Claims<'a> {
     pub fn claim_tokens(
         &self,
         storage: &mut dyn Storage,
-        addr: &Addr,
+        addr: K,
         block: &BlockInfo,
         cap: Option<Uint128>,
     ) -> StdResult<Uint128> {
@@ -86,7 +93,7 @@ impl<'a> Claims<'a> {
         Ok(to_send)
     }
 
-    pub fn query_claims(&self, deps: Deps, address: &Addr) -> StdResult<ClaimsResponse> {
+    pub fn query_claims(&self, deps: Deps, address: K) -> StdResult<ClaimsResponse> {
         let claims = self.0.may_load(deps.storage, address)?.unwrap_or_default();
         Ok(ClaimsResponse { claims })
     }
@@ -1,5 +1,5 @@
 use crate::mars_protocol_math::Decimal;
-use cosmwasm_std::{Addr, Api, StdResult, Uint128};
+use cosmwasm_std::{Addr, Api, StdError, StdResult, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -9,10 +9,12 @@ pub enum AssetInfo {
     FutureAsset {
         asset_name: String,
         contract_addr: Addr,
+        mint_authority: Addr,
         collateralizeable: bool,
         ratio: Decimal,
         underlying: NormalAssetInfoUnvalidated,
-        decimals: Uint128,
+        decimals: u8,
+        expiry: u64, // Expiry of the future as seconds since epoch
     },
     NormalAsset(NormalAssetInfo),
 }
@@ -25,13 +27,13 @@ pub enum NormalAssetInfo {
         contract_addr: Addr,
         collateralizeable: bool,
         ratio: Decimal,
-        decimals: Uint128,
+        decimals: u8,
     },
     NativeToken {
         denom: String,
         collateralizeable: bool,
         ratio: Decimal,
-        decimals: Uint128,
+        decimals: u8,
     },
 }
 
@@ -41,10 +43,12 @@ pub enum AssetInfoUnvalidated {
     FutureAsset {
         asset_name: String,
         contract_addr: String,
+        mint_authority: String,
         collateralizeable: bool,
         ratio: Decimal,
         underlying: NormalAssetInfoUnvalidated,
-        decimals: Uint128,
+        decimals: u8,
+        expiry: u64, // Expiry of the future as seconds since epoch
     },
     NormalAsset(NormalAssetInfoUnvalidated),
 }
@@ -57,33 +61,63 @@ pub enum NormalAssetInfoUnvalidated {
         contract_addr: String,
         collateralizeable: bool,
         ratio: Decimal,
-        decimals: Uint128,
+        decimals: u8,
     },
     NativeToken {
         denom: String,
         collateralizeable: bool,
         ratio: Decimal,
-        decimals: Uint128,
+        decimals: u8,
     },
 }
 
+/// Largest number of decimals an asset can have, same limit as cw20-base
+pub const MAX_DECIMALS: u8 = 18;
+
+fn validate_decimals(decimals: u8) -> StdResult<u8> {
+    if decimals > MAX_DECIMALS {
+        return Err(StdError::generic_err(format!(
+            "Decimals must not exceed {}",
+            MAX_DECIMALS
+        )));
+    }
+    Ok(decimals)
+}
+
+///Returns the number of base units in one whole token with the given decimals, i.e. 10^decimals
+pub fn decimals_to_unit(decimals: u8) -> StdResult<Uint128> {
+    10u128
+        .checked_pow(decimals.into())
+        .map(Uint128::from)
+        .ok_or_else(|| StdError::generic_err(format!("Invalid decimals {}", decimals)))
+}
+
 impl AssetInfoUnvalidated {
     pub fn to_validated(self, api: &dyn Api) -> StdResult<AssetInfo> {
         match self {
             AssetInfoUnvalidated::FutureAsset {
                 asset_name,
                 contract_addr,
+                mint_authority,
                 collateralizeable,
                 ratio,
                 underlying,
                 decimals,
+                expiry,
             } => Ok(AssetInfo::FutureAsset {
                 asset_name,
                 contract_addr: api.addr_validate(contract_addr.as_str())?,
+                mint_authority: api.addr_validate(mint_authority.as_str())?,
                 collateralizeable,
                 ratio,
-                underlying,
-                decimals, //TODO: Add some validation here
+                // The underlying is stored as given, but it is paid out on settlement so it has to
+                // be valid as well
+                underlying: {
+                    underlying.clone().to_validated(api)?;
+                    underlying
+                },
+                decimals: validate_decimals(decimals)?,
+                expiry,
             }),
             AssetInfoUnvalidated::NormalAsset(normal_asset_info) => {
                 Ok(AssetInfo::NormalAsset(normal_asset_info.to_validated(api)?))
@@ -106,7 +140,7 @@ impl NormalAssetInfoUnvalidated {
                 contract_addr: api.addr_validate(contract_addr.as_str())?,
                 ratio,
                 collateralizeable,
-                decimals, //TODO: add some validation here
+                decimals: validate_decimals(decimals)?,
             }),
 
             NormalAssetInfoUnvalidated::NativeToken {
@@ -118,10 +152,16 @@ impl NormalAssetInfoUnvalidated {
                 denom,
                 ratio,
                 collateralizeable,
-                decimals, //TODO: add some validation here
+                decimals: validate_decimals(decimals)?,
             }),
         }
     }
+    pub fn get_name(&self) -> String {
+        match self {
+            NormalAssetInfoUnvalidated::NativeToken { denom, .. } => denom.clone(),
+            NormalAssetInfoUnvalidated::CW20Token { asset_name, .. } => asset_name.clone(),
+        }
+    }
 }
 
 impl AssetInfo {
@@ -143,7 +183,13 @@ impl AssetInfo {
             },
         }
     }
-    pub fn get_decimals(&self) -> Uint128 {
+    pub fn is_native(&self) -> bool {
+        matches!(
+            self,
+            AssetInfo::NormalAsset(NormalAssetInfo::NativeToken { .. })
+        )
+    }
+    pub fn get_decimals(&self) -> u8 {
         match self {
             AssetInfo::FutureAsset { decimals, .. } => *decimals,
             AssetInfo::NormalAsset(normal_asset_info) => match normal_asset_info {
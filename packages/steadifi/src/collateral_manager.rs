@@ -1,18 +1,41 @@
 use crate::asset::AssetInfoUnvalidated;
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{to_binary, Binary, CosmosMsg, Decimal, StdResult, Uint128, WasmMsg};
+use cw0::Duration;
 use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 pub mod msg {
     use super::{
-        AssetInfoUnvalidated, Cw20ReceiveMsg, Deserialize, JsonSchema, Serialize, Uint128,
+        AssetInfoUnvalidated, Cw20ReceiveMsg, Decimal, Deserialize, Duration, JsonSchema,
+        Serialize, Uint128,
     };
+    use crate::mars_protocol_math::Decimal as PriceDecimal;
 
     ///////////////////////////////////////////////
     // Instantiate messages
     #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
-    pub struct InstantiateMsg {}
+    pub struct InstantiateMsg {
+        pub max_ltv: Decimal,
+        pub oracle_manager_addr: String,
+        /// Asset positions are valued in. Has to match the quote asset of the oracle manager
+        pub quote_asset: String,
+        /// Seconds before expiry at which the settlement window of a future opens
+        pub settlement_window: u64,
+        /// Seconds after expiry at which a future is finalized
+        pub finalization_delay: u64,
+        /// Length in seconds of the TWAP before expiry used for settlement prices. Zero, or an underlying
+        /// without a price history in the oracle manager, uses the spot price
+        pub fixing_twap_window: u64,
+        /// Seconds after expiry in which anyone can fix the settlement price of a future. Afterwards
+        /// only the admin can
+        pub fixing_period: u64,
+        /// Share of the repaid value liquidators receive on top in collateral, below one
+        pub liquidation_bonus: PriceDecimal,
+        /// Largest share of a debt a single liquidation can repay, above zero and at most one.
+        /// Debt in an expired future can be liquidated in full
+        pub close_factor: PriceDecimal,
+    }
 
     //////////////////////////////////////////////
     // Execute messages
@@ -20,13 +43,40 @@ pub mod msg {
     #[serde(rename_all = "snake_case")]
     pub enum ExecuteMsg {
         NativeDeposit {}, // Deposit native tokens as collateral
-        NativeSettle {},  // Settle native borrow only available 7 days prior expiry of contract
+        NativeSettle {
+            asset_name: String,
+        }, // Settle debt in the future asset_name by sending its native underlying, once its settlement window opens
         NativeWithdraw {
             coin_denom: String,
             amount: Uint128,
         }, // Withdraw Native tokens
-        NativeLiquidate {}, // Liquidate account
         Receive(Cw20ReceiveMsg), //Exactly same operations but for cw20 tokens
+        Cw20Withdraw {
+            asset_name: String,
+            amount: Uint128,
+        }, // Withdraw CW20 tokens
+        Borrow {
+            asset_name: String,
+            amount: Uint128,
+        }, // Borrow future assets against deposited collateral
+        Batch {
+            ops: Vec<PositionOp>,
+        }, // Apply several operations in order, checking the position only once at the end
+        Roll {
+            from_asset: String,
+            to_asset: String,
+            amount: Uint128,
+        }, // Move amount of debt in the future from_asset into the later series to_asset of the same underlying
+        ClaimWithdrawals {
+            asset_name: String,
+        }, // Pay out all withdrawals of asset_name whose cooldown has passed
+        FixSettlementPrice {
+            asset_name: String,
+        }, // Record the settlement price of the expired future asset_name. Anyone can call this within the fixing period
+        SetSettlementPrice {
+            asset_name: String,
+            price: PriceDecimal,
+        }, // Record the settlement price of the expired future asset_name nobody fixed within the fixing period. Only the admin can call this
         AddSupportedAsset {
             asset_name: String,
             asset_info_unvalidated: AssetInfoUnvalidated,
@@ -34,11 +84,40 @@ pub mod msg {
         RemoveSupportedAsset {
             asset_name: String,
         },
+        SetWithdrawCooldown {
+            asset_name: String,
+            cooldown: Option<Duration>,
+        }, // Withdrawals of asset_name are paid out after cooldown. None pays out immediately
+        SetPriceMaxAge {
+            asset_name: String,
+            max_age: Option<u64>,
+        }, // Borrows and withdrawals reject prices of asset_name older than max_age seconds. None accepts any age
+        AddHook {
+            addr: String,
+        }, // Register a contract that is notified of every position change, with limited gas. At most 10 hooks
+        RemoveHook {
+            addr: String,
+        },
+        SetFutureFactory {
+            address: Option<String>,
+        }, // Allow the future factory at address to add the futures it deploys as supported assets. None revokes it
         UpdateAdmin {
             new_admin: String,
         },
     }
 
+    /// Operations that can be combined in a batch. CW20 deposits and repayments are pulled from the
+    /// sender with TransferFrom, so the sender has to give the collateral manager an allowance first.
+    /// Native deposits are paid with the funds sent along with the batch
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub enum PositionOp {
+        Deposit { asset_name: String, amount: Uint128 },
+        Withdraw { asset_name: String, amount: Uint128 },
+        Borrow { asset_name: String, amount: Uint128 },
+        Repay { asset_name: String, amount: Uint128 },
+    }
+
     //////////////////////////////////////////////
     //If sending a cw20 token to the collateral manager a message needs to come as well
     // to specify what this cw20 token is for
@@ -48,12 +127,20 @@ pub mod msg {
     pub enum Cw20HookMsg {
         /// Deposit more collateral
         Deposit { asset_name: String },
-        /// Settle Loan - Only possible 7 days prior to expiry of contract
+        /// Settle debt in the future asset_name by delivering its underlying. Only possible once the
+        /// settlement window of the future opened. The underlying is kept to pay out redemptions
         Settle { asset_name: String },
-        /// Liquidate under-collateralized accounts or accounts that have not settled debt after expiry date
-        Liquidate {},
-        /// Withdraw CW20 Tokens
-        Withdraw { asset_name: String, amount: Uint128 },
+        /// Liquidate under-collateralized accounts or accounts that have not settled debt after expiry date.
+        /// The tokens sent repay debt of borrower in asset_name and the liquidator receives collateral_asset
+        /// of the same value
+        Liquidate {
+            asset_name: String,
+            borrower: String,
+            collateral_asset: String,
+        },
+        /// Redeem the tokens sent of the finalized future asset_name for its underlying at the
+        /// settlement price. Redemptions are capped pro-rata if the settled underlying falls short
+        Redeem { asset_name: String },
     }
 
     #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -65,6 +152,100 @@ pub mod msg {
         /// Returns registered information of asset_name
         /// Return type: AssetInfoValidated
         AssetInfo { asset_name: String },
+        /// Returns the withdrawal cooldown of asset_name, None if withdrawals are paid out immediately
+        /// Return type: Option<Duration>
+        WithdrawCooldown { asset_name: String },
+        /// Returns the maximum age in seconds of prices of asset_name used for borrows and
+        /// withdrawals, None if prices of any age are used
+        /// Return type: Option<u64>
+        PriceMaxAge { asset_name: String },
+        /// Returns the pending withdrawals of asset_name for the given address
+        /// Return type: ClaimsResponse
+        Claims { address: String, asset_name: String },
+        /// Returns the addresses of all contracts that are notified of position changes
+        /// Return type: HooksResponse
+        Hooks {},
+        /// Returns the lifecycle state of the future asset_name at the current block time
+        /// Return type: FutureStateResponse
+        FutureState { asset_name: String },
+        /// Returns the settlement price of the future asset_name, None if it was not fixed yet
+        /// Return type: Option<SettlementFixing>
+        SettlementPrice { asset_name: String },
+        /// Returns how much of its underlying has been delivered for the future asset_name and is
+        /// left for redemptions
+        /// Return type: Uint128
+        SettlementPool { asset_name: String },
+        /// Returns the annualized rates implied by the prices of all live futures on underlying
+        /// against its spot price, ordered by expiry
+        /// Return type: TermStructureResponse
+        TermStructure { underlying: String },
+        /// Returns the future factory that can add future assets, None if there is none
+        /// Return type: Option<Addr>
+        FutureFactory {},
+    }
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+    pub struct TermStructureResponse {
+        pub underlying: String,
+        pub spot_price: PriceDecimal,
+        pub points: Vec<TermStructurePoint>,
+    }
+
+    /// Implied rate of a single future, a simple annualized rate of its premium over spot
+    #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+    pub struct TermStructurePoint {
+        pub asset_name: String,
+        pub expiry: u64,
+        pub price: PriceDecimal,
+        /// Absolute value of the rate, 0.05 is 5% a year
+        pub implied_rate: PriceDecimal,
+        /// True if the future trades below spot, i.e. the implied rate is negative
+        pub negative: bool,
+    }
+
+    /// Price of the underlying a future settles at. Recorded once at or after expiry and never changed
+    #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+    pub struct SettlementFixing {
+        pub price: PriceDecimal,
+        /// Block time the fixing was recorded at
+        pub fixed_at: u64,
+        /// True if the price is a TWAP over the window before expiry, false if it is a spot price
+        pub twap: bool,
+    }
+
+    /// Lifecycle of a future asset, decided by block time relative to its expiry
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub enum FutureState {
+        /// Can be borrowed freely
+        Trading,
+        /// No new borrows, debt can still be repaid
+        SettlementWindow,
+        /// Unsettled debt can be liquidated
+        Expired,
+        /// Settlement is over
+        Finalized,
+    }
+
+    impl FutureState {
+        /// Returns the state of a future expiring at expiry at block time now, all in seconds
+        pub fn at(expiry: u64, settlement_window: u64, finalization_delay: u64, now: u64) -> Self {
+            if now < expiry.saturating_sub(settlement_window) {
+                FutureState::Trading
+            } else if now < expiry {
+                FutureState::SettlementWindow
+            } else if now < expiry.saturating_add(finalization_delay) {
+                FutureState::Expired
+            } else {
+                FutureState::Finalized
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+    pub struct FutureStateResponse {
+        pub state: FutureState,
+        pub expiry: u64,
     }
 
     /// amount of collateral and borrow. At most one of these two can be non-zero.
@@ -74,3 +255,60 @@ pub mod msg {
         pub borrow: Uint128,
     }
 }
+
+pub mod hook {
+    use super::{
+        to_binary, Binary, CosmosMsg, Deserialize, JsonSchema, Serialize, StdResult, Uint128,
+        WasmMsg,
+    };
+
+    /// Kind of position change a hook message reports
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub enum PositionAction {
+        Deposit,
+        Withdraw,
+        Borrow,
+        Repay,
+        Liquidate { liquidator: String },
+    }
+
+    /// Sent to every registered hook contract when the position of address in asset_name changes.
+    /// collateral and borrow are the balances after the change was applied
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub struct PositionChangedHookMsg {
+        pub action: PositionAction,
+        pub address: String,
+        pub asset_name: String,
+        pub delta: Uint128,
+        pub collateral: Uint128,
+        pub borrow: Uint128,
+    }
+
+    impl PositionChangedHookMsg {
+        /// serializes the message
+        pub fn into_binary(self) -> StdResult<Binary> {
+            let msg = PositionChangedExecuteMsg::PositionChangedHook(self);
+            to_binary(&msg)
+        }
+
+        /// creates a cosmos_msg sending this struct to the named contract
+        pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+            let msg = self.into_binary()?;
+            let execute = WasmMsg::Execute {
+                contract_addr: contract_addr.into(),
+                msg,
+                funds: vec![],
+            };
+            Ok(execute.into())
+        }
+    }
+
+    /// Hook contracts have to accept this variant in their ExecuteMsg
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub enum PositionChangedExecuteMsg {
+        PositionChangedHook(PositionChangedHookMsg),
+    }
+}
@@ -0,0 +1,85 @@
+use crate::asset::NormalAssetInfoUnvalidated;
+use crate::mars_protocol_math::Decimal;
+use cosmwasm_std::Addr;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Definition of a future series, i.e. a future on an underlying asset with a given expiry
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SeriesDefinition {
+    pub underlying: NormalAssetInfoUnvalidated,
+    pub expiry: u64, // Expiry of the future as seconds since epoch
+    pub ratio: Decimal,
+    pub decimals: u8,
+    pub collateralizeable: bool,
+    pub symbol: String, // Ticker symbol of the cw20 token of the series
+}
+
+impl SeriesDefinition {
+    /// Name the future asset is registered under in the collateral manager
+    pub fn asset_name(&self) -> String {
+        format!("{}-{}", self.underlying.get_name(), self.expiry)
+    }
+}
+
+/// A deployed future series
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SeriesInfo {
+    pub asset_name: String,
+    pub underlying: NormalAssetInfoUnvalidated,
+    pub expiry: u64,
+    pub ratio: Decimal,
+    pub decimals: u8,
+    pub collateralizeable: bool,
+    pub contract_addr: Addr,
+    pub mint_authority: Addr,
+}
+
+pub mod msg {
+    use super::{Deserialize, JsonSchema, Serialize, SeriesDefinition, SeriesInfo};
+
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+    pub struct InstantiateMsg {
+        /// The factory has to be set as the future factory of the collateral manager to register
+        /// new series
+        pub collateral_manager_addr: String,
+        pub cw20_code_id: u64,
+        pub mint_authority_code_id: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ExecuteMsg {
+        CreateSeries { series: SeriesDefinition }, // Deploy the contracts of a new series and register it
+        UpdateAdmin { new_admin: String },
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub enum QueryMsg {
+        /// Return type: ConfigResponse
+        Config {},
+        /// Returns the series of underlying that expires at expiry
+        /// Return type: SeriesInfo
+        Series { underlying: String, expiry: u64 },
+        /// Returns the series of underlying ordered by expiry
+        /// Return type: SeriesListResponse
+        SeriesByUnderlying {
+            underlying: String,
+            start_after: Option<u64>,
+            limit: Option<u32>,
+        },
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct ConfigResponse {
+        pub collateral_manager_addr: String,
+        pub cw20_code_id: u64,
+        pub mint_authority_code_id: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct SeriesListResponse {
+        pub series: Vec<SeriesInfo>,
+    }
+}
@@ -1,6 +1,9 @@
 pub mod asset;
+pub mod astroport;
 pub mod collateral_manager;
+pub mod future_factory;
 pub mod mars_protocol_math;
 pub mod oracle_manager;
 
-mod testing;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod testing;
@@ -75,6 +75,22 @@ impl Decimal {
         Ok(Decimal(result))
     }
 
+    /// Subtract 'other' from 'self'.
+    /// Function can return errors such as:
+    /// - OverflowError if 'other' is larger than 'self'.
+    pub fn checked_sub(self, other: Self) -> StdResult<Self> {
+        Ok(Decimal(self.0.checked_sub(other.0)?))
+    }
+
+    /// Distance between 'self' and 'other', which can not underflow.
+    pub fn abs_diff(self, other: Self) -> Self {
+        if self.0 > other.0 {
+            Decimal(self.0 - other.0)
+        } else {
+            Decimal(other.0 - self.0)
+        }
+    }
+
     /// Divide 'self' by 'other'.
     /// Function can return errors such as:
     /// - OverflowError from multiplication,
@@ -618,15 +634,26 @@ mod tests {
     }
 
     #[test]
-    fn decimal_sub() {
-        let value = Decimal::one() - Decimal::percent(50); // 0.5
+    fn checked_decimal_subtraction() {
+        let value = Decimal::one().checked_sub(Decimal::percent(50)).unwrap(); // 0.5
         assert_eq!(value.0, Decimal::DECIMAL_FRACTIONAL / Uint128::from(2u8));
+
+        let error = Decimal::zero()
+            .checked_sub(Decimal::percent(50))
+            .unwrap_err();
+        assert!(matches!(error, StdError::Overflow { .. }));
     }
 
     #[test]
-    #[should_panic(expected = "attempt to subtract with overflow")]
-    fn decimal_sub_overflow_panics() {
-        let _value = Decimal::zero() - Decimal::percent(50);
+    fn decimal_abs_diff() {
+        assert_eq!(
+            Decimal::one().abs_diff(Decimal::percent(150)),
+            Decimal::percent(50)
+        );
+        assert_eq!(
+            Decimal::percent(150).abs_diff(Decimal::one()),
+            Decimal::percent(50)
+        );
     }
 
     #[test]
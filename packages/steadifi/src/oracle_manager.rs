@@ -1,46 +1,208 @@
 use crate::mars_protocol_math::Decimal;
-use crate::oracle_manager::msg::QueryMsg as OracleQueryMsg;
-use cosmwasm_std::{Addr, Api, QuerierWrapper, StdResult};
+use crate::oracle_manager::msg::{
+    ConfigResponse, PriceResponse, PriceResult, PricesResponse, QueryMsg as OracleQueryMsg,
+};
+use cosmwasm_std::{Addr, Api, BlockInfo, QuerierWrapper, StdError, StdResult};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-///Get asset price denominated in USD from the oracle_manager
+///Get asset price denominated in the quote asset from the oracle_manager
 pub fn get_oracle_price(
     querier: QuerierWrapper,
     oracle_manager_address: &Addr,
+    quote_asset: &str,
     asset_name: &str,
+    block: &BlockInfo,
+) -> StdResult<PriceResponse> {
+    // For the quote asset, we skip the query and just return 1 to save gas
+    if asset_name == quote_asset {
+        Ok(PriceResponse::exact(Decimal::one(), block.time.seconds()))
+    } else {
+        querier.query_wasm_smart(
+            oracle_manager_address.as_str(),
+            &OracleQueryMsg::GetPrice {
+                asset_name: asset_name.to_string(),
+            },
+        )
+    }
+}
+
+///Get prices of several assets denominated in the quote asset from the oracle_manager with a
+/// single query. Returns a map from asset name to the price or the error pricing it failed with
+pub fn get_oracle_price_results(
+    querier: QuerierWrapper,
+    oracle_manager_address: &Addr,
+    quote_asset: &str,
+    asset_names: &[String],
+    block: &BlockInfo,
+) -> StdResult<HashMap<String, PriceResult>> {
+    let mut prices = HashMap::new();
+    // For the quote asset, we skip the query and just return 1 to save gas
+    let mut query_asset_names = vec![];
+    for asset_name in asset_names {
+        if asset_name == quote_asset {
+            prices.insert(
+                asset_name.clone(),
+                PriceResult::Ok(PriceResponse::exact(Decimal::one(), block.time.seconds())),
+            );
+        } else if !query_asset_names.contains(asset_name) {
+            query_asset_names.push(asset_name.clone());
+        }
+    }
+    if !query_asset_names.is_empty() {
+        let response: PricesResponse = querier.query_wasm_smart(
+            oracle_manager_address.as_str(),
+            &OracleQueryMsg::GetPrices {
+                asset_names: query_asset_names,
+            },
+        )?;
+        for asset_price in response.prices {
+            prices.insert(asset_price.asset_name, asset_price.result);
+        }
+    }
+    Ok(prices)
+}
+
+///Get prices of several assets denominated in the quote asset from the oracle_manager with a
+/// single query. Returns a map from asset name to price, fails if any of the assets has no price
+pub fn get_oracle_prices(
+    querier: QuerierWrapper,
+    oracle_manager_address: &Addr,
+    quote_asset: &str,
+    asset_names: &[String],
+    block: &BlockInfo,
+) -> StdResult<HashMap<String, PriceResponse>> {
+    let mut price_results = get_oracle_price_results(
+        querier,
+        oracle_manager_address,
+        quote_asset,
+        asset_names,
+        block,
+    )?;
+    let mut prices = HashMap::new();
+    for asset_name in asset_names {
+        if prices.contains_key(asset_name) {
+            continue;
+        }
+        let price = match price_results.remove(asset_name) {
+            Some(PriceResult::Ok(price)) => price,
+            Some(PriceResult::Error(error)) => {
+                return Err(StdError::generic_err(format!(
+                    "Failed to price {}: {}",
+                    asset_name, error
+                )))
+            }
+            None => {
+                return Err(StdError::generic_err(format!(
+                    "Oracle manager returned no price for {}",
+                    asset_name
+                )))
+            }
+        };
+        prices.insert(asset_name.clone(), price);
+    }
+    Ok(prices)
+}
+
+///Get the time weighted average price of asset_name between start and end from the oracle_manager
+pub fn get_oracle_twap_price(
+    querier: QuerierWrapper,
+    oracle_manager_address: &Addr,
+    quote_asset: &str,
+    asset_name: &str,
+    start: u64,
+    end: u64,
 ) -> StdResult<Decimal> {
-    // For UST, we skip the query and just return 1 to save gas
-    if asset_name == "uusd" {
+    if asset_name == quote_asset {
         Ok(Decimal::one())
     } else {
-        // TODO: This is wrong need an execute message type here
         querier.query_wasm_smart(
             oracle_manager_address.as_str(),
-            &OracleQueryMsg::GetPrice {
+            &OracleQueryMsg::GetTwapPrice {
                 asset_name: asset_name.to_string(),
+                start,
+                end,
             },
         )
     }
 }
 
+///Get how the oracle_manager keeps the price history of asset_name, None if it keeps no history
+pub fn get_oracle_price_history_config(
+    querier: QuerierWrapper,
+    oracle_manager_address: &Addr,
+    asset_name: &str,
+) -> StdResult<Option<PriceHistoryConfig>> {
+    querier.query_wasm_smart(
+        oracle_manager_address.as_str(),
+        &OracleQueryMsg::PriceHistoryConfig {
+            asset_name: asset_name.to_string(),
+        },
+    )
+}
+
+///Get the quote asset the oracle_manager denominates its prices in
+pub fn get_oracle_quote_asset(
+    querier: QuerierWrapper,
+    oracle_manager_address: &Addr,
+) -> StdResult<String> {
+    let config: ConfigResponse =
+        querier.query_wasm_smart(oracle_manager_address.as_str(), &OracleQueryMsg::Config {})?;
+    Ok(config.quote_asset)
+}
+
 /// Oracle types used by oracle manager
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Oracle {
-    AstroportTWAP { address: Addr },
+    // TWAP of an Astroport pair against the quote asset over the last window seconds. The pair has
+    // to trade the asset itself, a token matches by its address or its cw20 name
+    AstroportTWAP { address: Addr, window: u64 },
     Native { denom: String },
+    // Latest reports of the whitelisted feeders that are at most max_age seconds old, each report
+    // counts as one source
+    Feeder { max_age: u64 },
     // Only used for integration tests
     Fixed { price: Decimal },
 }
 
+impl Oracle {
+    /// Returns true if other reads the same source as this oracle. An asset has a single set of
+    /// feeders and a pair has a single price, so they are the same source whatever their max_age
+    /// or window
+    pub fn is_same_source(&self, other: &Oracle) -> bool {
+        match (self, other) {
+            (
+                Oracle::AstroportTWAP { address, .. },
+                Oracle::AstroportTWAP {
+                    address: other_address,
+                    ..
+                },
+            ) => address == other_address,
+            (Oracle::Feeder { .. }, Oracle::Feeder { .. }) => true,
+            _ => self == other,
+        }
+    }
+}
+
 /// Oracles types with unvalidated addresses
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum OracleUnvalidated {
-    AstroportTWAP { address_unvalidated: String },
-    Native { denom: String },
-    Fixed { price: Decimal },
+    AstroportTWAP {
+        address_unvalidated: String,
+        window: u64,
+    },
+    Native {
+        denom: String,
+    },
+    Feeder {
+        max_age: u64,
+    },
+    Fixed {
+        price: Decimal,
+    },
 }
 
 impl OracleUnvalidated {
@@ -48,21 +210,124 @@ impl OracleUnvalidated {
         match self {
             OracleUnvalidated::AstroportTWAP {
                 address_unvalidated,
-            } => Ok(Oracle::AstroportTWAP {
-                address: api.addr_validate(address_unvalidated.as_str())?,
-            }),
+                window,
+            } => {
+                if window == 0 {
+                    return Err(StdError::generic_err("TWAP window has to be positive"));
+                }
+                Ok(Oracle::AstroportTWAP {
+                    address: api.addr_validate(address_unvalidated.as_str())?,
+                    window,
+                })
+            }
             OracleUnvalidated::Native { denom } => Ok(Oracle::Native { denom }),
+            OracleUnvalidated::Feeder { max_age } => Ok(Oracle::Feeder { max_age }),
             OracleUnvalidated::Fixed { price } => Ok(Oracle::Fixed { price }),
         }
     }
 }
 
+/// How the prices of the sources of an asset are combined. Sources that fail to price are left out,
+/// then sources deviating more than max_deviation from the median of the rest are rejected. The
+/// price is the median of the remaining sources if there are at least min_valid_sources of them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AggregationPolicy {
+    pub min_valid_sources: u32,
+    // Largest accepted relative deviation from the median, e.g. 0.05 for 5%. None accepts any
+    pub max_deviation: Option<Decimal>,
+}
+
+impl Default for AggregationPolicy {
+    fn default() -> Self {
+        AggregationPolicy {
+            min_valid_sources: 1,
+            max_deviation: None,
+        }
+    }
+}
+
+impl AggregationPolicy {
+    pub fn validate(&self) -> StdResult<()> {
+        if self.min_valid_sources == 0 {
+            return Err(StdError::generic_err(
+                "Aggregation policy has to require at least one valid source",
+            ));
+        }
+        if self.max_deviation == Some(Decimal::zero()) {
+            return Err(StdError::generic_err(
+                "Maximum deviation of an aggregation policy has to be positive",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Guards the price of an asset against sudden moves. A price deviating more than max_deviation from
+/// the last accepted price, while that price is less than window seconds old, trips the breaker. A
+/// tripped breaker rejects prices until the admin or guardian confirms a price or window seconds pass
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CircuitBreaker {
+    // Largest accepted relative deviation from the last accepted price, e.g. 0.1 for 10%
+    pub max_deviation: Decimal,
+    pub window: u64,
+}
+
+impl CircuitBreaker {
+    pub fn validate(&self) -> StdResult<()> {
+        if self.max_deviation == Decimal::zero() {
+            return Err(StdError::generic_err(
+                "Maximum deviation of a circuit breaker has to be positive",
+            ));
+        }
+        if self.window == 0 {
+            return Err(StdError::generic_err(
+                "Window of a circuit breaker has to be positive",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Most observations a price history can keep, every TWAP and historical price query reads them all
+pub const MAX_PRICE_HISTORY_LENGTH: u32 = 500;
+
+/// How the accepted prices of an asset are kept for TWAPs and historical prices. The last length
+/// observations are kept, an observation is only recorded min_interval seconds after the previous one
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceHistoryConfig {
+    pub length: u32,
+    pub min_interval: u64,
+}
+
+impl PriceHistoryConfig {
+    pub fn validate(&self) -> StdResult<()> {
+        if self.length == 0 {
+            return Err(StdError::generic_err(
+                "Price history has to keep at least one observation",
+            ));
+        }
+        if self.length > MAX_PRICE_HISTORY_LENGTH {
+            return Err(StdError::generic_err(format!(
+                "Price history can keep at most {} observations",
+                MAX_PRICE_HISTORY_LENGTH
+            )));
+        }
+        Ok(())
+    }
+}
+
 pub mod msg {
-    use super::{Deserialize, JsonSchema, OracleUnvalidated, Serialize};
+    use super::{
+        AggregationPolicy, CircuitBreaker, Decimal, Deserialize, JsonSchema, Oracle,
+        OracleUnvalidated, PriceHistoryConfig, Serialize,
+    };
 
     #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
     #[serde(rename_all = "snake_case")]
-    pub struct InstantiateMsg {}
+    pub struct InstantiateMsg {
+        /// Asset all prices are denominated in. Native oracles quote against this denom
+        pub quote_asset: String,
+    }
 
     #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
     #[serde(rename_all = "snake_case")]
@@ -70,7 +335,55 @@ pub mod msg {
         AddAssetPriceOracle {
             asset_name: String,
             oracle_unvalidated: OracleUnvalidated,
-        },
+        }, // Appends a price source of asset_name, a source can only be added once per asset
+        RemoveAssetPriceOracle {
+            asset_name: String,
+            index: u32,
+        }, // Removes the source at index in the list of asset_name
+        ReplaceAssetPriceOracle {
+            asset_name: String,
+            index: u32,
+            oracle_unvalidated: OracleUnvalidated,
+        }, // Replaces the source at index in the list of asset_name
+        ReorderAssetPriceOracles {
+            asset_name: String,
+            order: Vec<u32>,
+        }, // Reorders the sources of asset_name, order lists every current index once in the new order
+        SnapshotCumulativePrices {
+            asset_name: String,
+        }, // Records the cumulative prices of the Astroport pairs pricing asset_name. Anyone can call it
+        SetAggregationPolicy {
+            asset_name: String,
+            policy: AggregationPolicy,
+        }, // Sets how the prices of the sources of asset_name are combined
+        AddFeeder {
+            address: String,
+        }, // Whitelists address to submit prices
+        RemoveFeeder {
+            address: String,
+        }, // Removes address from the whitelist, its reports are no longer used
+        SubmitPrice {
+            asset_name: String,
+            price: Decimal,
+            timestamp: u64, //Time the price was observed in seconds since epoch, not in the future
+        }, // Only whitelisted feeders can report, a report replaces an older report of the sender
+        SetCircuitBreaker {
+            asset_name: String,
+            circuit_breaker: Option<CircuitBreaker>,
+        }, // Guards the price of asset_name with circuit_breaker. None removes the breaker
+        RecordPrice {
+            asset_name: String,
+        }, // Accepts the current price of asset_name unless it trips the circuit breaker and adds accepted prices to its price history. Anyone can call it
+        ConfirmPrice {
+            asset_name: String,
+        }, // Accepts the current price of asset_name and resets its circuit breaker. Only admin or guardian
+        UpdateGuardian {
+            guardian: Option<String>,
+        }, // Sets the address that can confirm prices besides the admin
+        SetPriceHistoryConfig {
+            asset_name: String,
+            config: Option<PriceHistoryConfig>,
+        }, // Keeps a history of the prices of asset_name recorded by RecordPrice and ConfirmPrice. Changing the length clears the history, None removes it
         UpdateAdmin {
             new_admin: String,
         },
@@ -79,6 +392,152 @@ pub mod msg {
     #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
     #[serde(rename_all = "snake_case")]
     pub enum QueryMsg {
+        /// Returns the price of asset_name with the number and range of the sources it combines
+        /// Return type: PriceResponse
         GetPrice { asset_name: String },
+        /// Returns the prices of all given assets in one query. Assets that can not be priced are
+        /// reported with their error instead of failing the whole query
+        /// Return type: PricesResponse
+        GetPrices { asset_names: Vec<String> },
+        /// Returns the time weighted average price of asset_name between start and end, in seconds
+        /// since epoch. Fails if the price history of asset_name does not cover the period
+        /// Return type: Decimal
+        GetTwapPrice {
+            asset_name: String,
+            start: u64,
+            end: u64,
+        },
+        /// Returns the newest observation in the price history of asset_name at or before timestamp
+        /// Return type: PriceObservation
+        PriceAt { asset_name: String, timestamp: u64 },
+        /// Returns how the price history of asset_name is kept, None if it has no price history
+        /// Return type: Option<PriceHistoryConfig>
+        PriceHistoryConfig { asset_name: String },
+        /// Returns the price sources of asset_name in order
+        /// Return type: OraclesResponse
+        ListOracles { asset_name: String },
+        /// Returns how the prices of the sources of asset_name are combined
+        /// Return type: AggregationPolicy
+        AggregationPolicy { asset_name: String },
+        /// Returns the names of the assets with price sources in ascending order
+        /// Return type: AssetsResponse
+        AllAssets {
+            start_after: Option<String>,
+            limit: Option<u32>,
+        },
+        /// Returns the whitelisted feeders in ascending order
+        /// Return type: FeedersResponse
+        Feeders {
+            start_after: Option<String>,
+            limit: Option<u32>,
+        },
+        /// Returns the latest report of feeder for asset_name, None if it has not reported
+        /// Return type: Option<FeederReport>
+        FeederReport { feeder: String, asset_name: String },
+        /// Returns the circuit breaker of asset_name, its last accepted price and whether it tripped
+        /// Return type: CircuitBreakerResponse
+        CircuitBreaker { asset_name: String },
+        /// Returns the address that can confirm prices besides the admin
+        /// Return type: AdminResponse
+        Guardian {},
+        /// Return type: ConfigResponse
+        Config {},
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct ConfigResponse {
+        pub quote_asset: String,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct PriceResponse {
+        pub price: Decimal,
+        // Time the oldest source used was updated, in seconds since epoch
+        pub last_updated: u64,
+        pub sources_used: u32,
+        // Lowest and highest price of the sources used
+        pub min: Decimal,
+        pub max: Decimal,
+    }
+
+    impl PriceResponse {
+        /// Price that needs no sources, like the quote asset, updated at time
+        pub fn exact(price: Decimal, time: u64) -> Self {
+            PriceResponse {
+                price,
+                last_updated: time,
+                sources_used: 0,
+                min: price,
+                max: price,
+            }
+        }
+
+        /// Returns whether the price was updated more than max_age seconds before now
+        pub fn is_stale(&self, now: u64, max_age: u64) -> bool {
+            now.saturating_sub(self.last_updated) > max_age
+        }
+    }
+
+    /// Price of a single asset in a batch, or the error pricing it failed with
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub enum PriceResult {
+        Ok(PriceResponse),
+        Error(String),
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct AssetPrice {
+        pub asset_name: String,
+        pub result: PriceResult,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct PricesResponse {
+        pub prices: Vec<AssetPrice>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct OraclesResponse {
+        pub asset_name: String,
+        pub oracles: Vec<Oracle>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct AssetsResponse {
+        pub assets: Vec<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct FeedersResponse {
+        pub feeders: Vec<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct FeederReport {
+        pub price: Decimal,
+        pub timestamp: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct AcceptedPrice {
+        pub price: Decimal,
+        // Time the price was accepted in seconds since epoch
+        pub time: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct CircuitBreakerResponse {
+        pub circuit_breaker: Option<CircuitBreaker>,
+        pub accepted_price: Option<AcceptedPrice>,
+        // Time the breaker tripped in seconds since epoch, None if it is not tripped
+        pub tripped_at: Option<u64>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct PriceObservation {
+        pub price: Decimal,
+        // Time the price was observed in seconds since epoch
+        pub time: u64,
     }
 }
@@ -0,0 +1,66 @@
+use cosmwasm_std::Uint128;
+
+use crate::astroport::{Asset, AssetInfo, CumulativePricesResponse, TWAP_PRECISION};
+
+/// Stands in for an Astroport pair contract. Prices accumulate the way the pair accumulates them
+/// whenever its reserves change
+#[derive(Clone, Debug)]
+pub struct MockPair {
+    pub assets: [Asset; 2],
+    pub price0_cumulative_last: Uint128,
+    pub price1_cumulative_last: Uint128,
+    pub block_time_last: u64,
+}
+
+impl MockPair {
+    pub fn new(asset_infos: [AssetInfo; 2], reserves: [Uint128; 2], time: u64) -> Self {
+        let [info0, info1] = asset_infos;
+        MockPair {
+            assets: [
+                Asset {
+                    info: info0,
+                    amount: reserves[0],
+                },
+                Asset {
+                    info: info1,
+                    amount: reserves[1],
+                },
+            ],
+            price0_cumulative_last: Uint128::zero(),
+            price1_cumulative_last: Uint128::zero(),
+            block_time_last: time,
+        }
+    }
+
+    /// Accumulates the prices of the current reserves up to time, then sets the new reserves
+    pub fn set_reserves(&mut self, reserves: [Uint128; 2], time: u64) {
+        self.accumulate(time);
+        self.assets[0].amount = reserves[0];
+        self.assets[1].amount = reserves[1];
+    }
+
+    /// Accumulates the prices of the current reserves up to time
+    pub fn accumulate(&mut self, time: u64) {
+        let time_elapsed =
+            Uint128::from(time - self.block_time_last) * Uint128::from(10u128.pow(TWAP_PRECISION));
+        let (x, y) = (self.assets[0].amount, self.assets[1].amount);
+        if !x.is_zero() && !y.is_zero() {
+            self.price0_cumulative_last = self
+                .price0_cumulative_last
+                .wrapping_add(time_elapsed.multiply_ratio(y, x));
+            self.price1_cumulative_last = self
+                .price1_cumulative_last
+                .wrapping_add(time_elapsed.multiply_ratio(x, y));
+        }
+        self.block_time_last = time;
+    }
+
+    pub fn query_cumulative_prices(&self) -> CumulativePricesResponse {
+        CumulativePricesResponse {
+            assets: self.assets.clone(),
+            total_share: Uint128::zero(),
+            price0_cumulative_last: self.price0_cumulative_last,
+            price1_cumulative_last: self.price1_cumulative_last,
+        }
+    }
+}
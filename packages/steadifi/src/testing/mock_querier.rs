@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use crate::astroport::PairQueryMsg;
+use crate::mars_protocol_math::Decimal;
+use crate::oracle_manager::msg::{
+    AssetPrice, ConfigResponse, PriceResponse, PriceResult, PricesResponse,
+    QueryMsg as OracleQueryMsg,
+};
+use crate::oracle_manager::PriceHistoryConfig;
+use cosmwasm_std::testing::{mock_env, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Coin, ContractResult, Empty, OwnedDeps, Querier,
+    QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
+};
+use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+
+use super::MockPair;
+
+/// Same as cosmwasm_std::testing::mock_dependencies but with a querier that also answers
+/// smart queries made to the oracle manager
+pub fn mock_dependencies(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: WasmMockQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, contract_balance)])),
+    }
+}
+
+pub struct WasmMockQuerier {
+    base: MockQuerier<Empty>,
+    // Maps asset name to the price returned by the oracle manager
+    oracle_prices: HashMap<String, PriceResponse>,
+    // Maps asset name to the TWAP returned by the oracle manager, whatever the period
+    oracle_twap_prices: HashMap<String, Decimal>,
+    // Maps asset name to the price history config returned by the oracle manager
+    oracle_price_histories: HashMap<String, PriceHistoryConfig>,
+    // Maps cw20 contract address to the token info it returns
+    token_infos: HashMap<String, TokenInfoResponse>,
+    // Maps cw20 contract address and holder address to the balance it returns
+    token_balances: HashMap<(String, String), Uint128>,
+    // Maps Astroport pair contract address to the pair
+    pairs: HashMap<String, MockPair>,
+    // Quote asset returned by the oracle manager
+    oracle_quote_asset: String,
+}
+
+impl Querier for WasmMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn new(base: MockQuerier<Empty>) -> Self {
+        WasmMockQuerier {
+            base,
+            oracle_prices: HashMap::new(),
+            oracle_twap_prices: HashMap::new(),
+            oracle_price_histories: HashMap::new(),
+            token_infos: HashMap::new(),
+            token_balances: HashMap::new(),
+            pairs: HashMap::new(),
+            oracle_quote_asset: "uusd".to_string(),
+        }
+    }
+
+    /// Sets a price from a single source, updated at the block time of mock_env
+    pub fn set_oracle_price(&mut self, asset_name: &str, price: Decimal) {
+        let mut price_response = PriceResponse::exact(price, mock_env().block.time.seconds());
+        price_response.sources_used = 1;
+        self.set_oracle_price_response(asset_name, price_response);
+    }
+
+    pub fn set_oracle_price_response(&mut self, asset_name: &str, price_response: PriceResponse) {
+        self.oracle_prices
+            .insert(asset_name.to_string(), price_response);
+    }
+
+    /// Sets the quote asset the oracle manager returns in its config, uusd by default
+    pub fn set_oracle_quote_asset(&mut self, quote_asset: &str) {
+        self.oracle_quote_asset = quote_asset.to_string();
+    }
+
+    /// Sets the TWAP the oracle manager returns for the given asset
+    pub fn set_oracle_twap_price(&mut self, asset_name: &str, price: Decimal) {
+        self.oracle_twap_prices
+            .insert(asset_name.to_string(), price);
+    }
+
+    /// Sets the price history config the oracle manager returns for the given asset
+    pub fn set_oracle_price_history(&mut self, asset_name: &str, config: PriceHistoryConfig) {
+        self.oracle_price_histories
+            .insert(asset_name.to_string(), config);
+    }
+
+    /// Sets the token info returned by the cw20 contract at contract_addr
+    pub fn set_token_info(&mut self, contract_addr: &str, token_info: TokenInfoResponse) {
+        self.token_infos
+            .insert(contract_addr.to_string(), token_info);
+    }
+
+    /// Sets the balance of address returned by the cw20 contract at contract_addr
+    pub fn set_token_balance(&mut self, contract_addr: &str, address: &str, balance: Uint128) {
+        self.token_balances
+            .insert((contract_addr.to_string(), address.to_string()), balance);
+    }
+
+    /// Returns the balance of address the cw20 contract at contract_addr returns, zero if unset
+    pub fn token_balance(&self, contract_addr: &str, address: &str) -> Uint128 {
+        self.token_balances
+            .get(&(contract_addr.to_string(), address.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set_pair(&mut self, contract_addr: &str, pair: MockPair) {
+        self.pairs.insert(contract_addr.to_string(), pair);
+    }
+
+    pub fn pair_mut(&mut self, contract_addr: &str) -> &mut MockPair {
+        self.pairs.get_mut(contract_addr).expect("no mock pair set")
+    }
+
+    pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
+        match request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                if let Some(token_info) = self.token_infos.get(contract_addr) {
+                    return match from_binary::<Cw20QueryMsg>(msg) {
+                        Ok(Cw20QueryMsg::TokenInfo {}) => {
+                            SystemResult::Ok(ContractResult::from(to_binary(token_info)))
+                        }
+                        Ok(Cw20QueryMsg::Balance { address }) => {
+                            let balance = self
+                                .token_balances
+                                .get(&(contract_addr.clone(), address))
+                                .copied()
+                                .unwrap_or_default();
+                            SystemResult::Ok(ContractResult::from(to_binary(&BalanceResponse {
+                                balance,
+                            })))
+                        }
+                        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                            kind: format!("Cw20 query {}", msg),
+                        }),
+                    };
+                }
+                if let Some(pair) = self.pairs.get(contract_addr) {
+                    return match from_binary::<PairQueryMsg>(msg) {
+                        Ok(PairQueryMsg::CumulativePrices {}) => SystemResult::Ok(
+                            ContractResult::from(to_binary(&pair.query_cumulative_prices())),
+                        ),
+                        Err(_) => SystemResult::Err(SystemError::UnsupportedRequest {
+                            kind: format!("Pair query {}", msg),
+                        }),
+                    };
+                }
+                match from_binary::<OracleQueryMsg>(msg) {
+                    Ok(oracle_query) => self.handle_oracle_query(oracle_query),
+                    Err(_) => SystemResult::Err(SystemError::UnsupportedRequest {
+                        kind: format!("Smart query {}", msg),
+                    }),
+                }
+            }
+            _ => self.base.handle_query(request),
+        }
+    }
+
+    fn handle_oracle_query(&self, query: OracleQueryMsg) -> QuerierResult {
+        match query {
+            OracleQueryMsg::GetPrice { asset_name } => match self.oracle_prices.get(&asset_name) {
+                Some(price) => SystemResult::Ok(ContractResult::from(to_binary(price))),
+                None => SystemResult::Ok(ContractResult::Err(format!(
+                    "No oracle price set for {}",
+                    asset_name
+                ))),
+            },
+            OracleQueryMsg::GetPrices { asset_names } => {
+                let mut prices = vec![];
+                for asset_name in asset_names {
+                    let result = match self.oracle_prices.get(&asset_name) {
+                        Some(price) => PriceResult::Ok(price.clone()),
+                        None => {
+                            PriceResult::Error(format!("No oracle price set for {}", asset_name))
+                        }
+                    };
+                    prices.push(AssetPrice { asset_name, result });
+                }
+                SystemResult::Ok(ContractResult::from(to_binary(&PricesResponse { prices })))
+            }
+            OracleQueryMsg::GetTwapPrice { asset_name, .. } => {
+                match self.oracle_twap_prices.get(&asset_name) {
+                    Some(price) => SystemResult::Ok(ContractResult::from(to_binary(price))),
+                    None => SystemResult::Ok(ContractResult::Err(format!(
+                        "No price history for {}",
+                        asset_name
+                    ))),
+                }
+            }
+            OracleQueryMsg::PriceHistoryConfig { asset_name } => SystemResult::Ok(
+                ContractResult::from(to_binary(&self.oracle_price_histories.get(&asset_name))),
+            ),
+            OracleQueryMsg::Config {} => {
+                SystemResult::Ok(ContractResult::from(to_binary(&ConfigResponse {
+                    quote_asset: self.oracle_quote_asset.clone(),
+                })))
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: format!("Oracle query {:?}", query),
+            }),
+        }
+    }
+}
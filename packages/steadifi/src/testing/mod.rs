@@ -1 +1,5 @@
+mod mock_pair;
+mod mock_querier;
 
+pub use mock_pair::MockPair;
+pub use mock_querier::{mock_dependencies, WasmMockQuerier};
@@ -32,12 +32,31 @@ async function instantiateContracts() {
   const wallet = ctx.getTestWallet('test1');
   const user = ctx.getTestWallet('test2');
 
+  const oracleCodeId = ctx.getCodeInfo('oracle').codeId;
+  const oracleContractAddr = await instantiateContract(
+    ctx.client,
+    wallet,
+    oracleCodeId,
+    { quote_asset: 'uusd' },
+  );
+  ctx.addContractInfo('oracle', oracleContractAddr, '_main');
+
   const { codeId } = ctx.getCodeInfo('collateral_manager');
   const contractAddress = await instantiateContract(
     ctx.client,
     wallet,
     codeId,
-    {},
+    {
+      max_ltv: '0.8',
+      oracle_manager_addr: oracleContractAddr,
+      quote_asset: 'uusd',
+      settlement_window: 7 * 24 * 60 * 60,
+      finalization_delay: 7 * 24 * 60 * 60,
+      fixing_twap_window: 60 * 60,
+      fixing_period: 60 * 60,
+      liquidation_bonus: '0.05',
+      close_factor: '0.5',
+    },
   );
   ctx.addContractInfo('collateral_manager', contractAddress, '_main');
 